@@ -0,0 +1,38 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.22;
+
+import {Script} from "forge-std/Script.sol";
+import {TimelockController} from "@openzeppelin/contracts/governance/TimelockController.sol";
+import {ERC1967Proxy} from "@openzeppelin/contracts/proxy/ERC1967/ERC1967Proxy.sol";
+import {ERC20TokenLoanUpgradeable} from "../src/upgradeable/ERC20TokenLoanUpgradeable.sol";
+import {YieldHarvestUpgradeable} from "../src/upgradeable/YieldHarvestUpgradeable.sol";
+
+contract DeployUpgradeable is Script {
+    uint256 public constant UPGRADE_DELAY = 2 days;
+    
+    function run() external returns (address loanProxy, address harvestProxy, address timelock) {
+        uint256 deployerPrivateKey = vm.envUint("PRIVATE_KEY");
+        address deployer = vm.addr(deployerPrivateKey);
+        vm.startBroadcast(deployerPrivateKey);
+        
+        // The deployer proposes and executes upgrades; nobody can bypass the delay
+        address[] memory proposers = new address[](1);
+        proposers[0] = deployer;
+        timelock = address(new TimelockController(UPGRADE_DELAY, proposers, proposers, address(0)));
+        
+        loanProxy = address(
+            new ERC1967Proxy(
+                address(new ERC20TokenLoanUpgradeable()),
+                abi.encodeCall(ERC20TokenLoanUpgradeable.initialize, (deployer, timelock))
+            )
+        );
+        harvestProxy = address(
+            new ERC1967Proxy(
+                address(new YieldHarvestUpgradeable()),
+                abi.encodeCall(YieldHarvestUpgradeable.initialize, (deployer, timelock))
+            )
+        );
+        
+        vm.stopBroadcast();
+    }
+}
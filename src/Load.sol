@@ -3,21 +3,56 @@ pragma solidity ^0.8.19;
 
 // Import OpenZeppelin's ERC20 interface and SafeMath (though not needed in ^0.8.0)
 import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
+import "@openzeppelin/contracts/token/ERC20/extensions/IERC20Metadata.sol";
+import "@openzeppelin/contracts/token/ERC20/utils/SafeERC20.sol";
 import "@openzeppelin/contracts/access/Ownable.sol";
-import "@openzeppelin/contracts/security/ReentrancyGuard.sol";
+import "@openzeppelin/contracts/utils/ReentrancyGuard.sol";
+import "@openzeppelin/contracts/utils/cryptography/EIP712.sol";
+import "@openzeppelin/contracts/utils/cryptography/ECDSA.sol";
+import "@openzeppelin/contracts/interfaces/IERC3156FlashLender.sol";
+import "@openzeppelin/contracts/utils/structs/EnumerableSet.sol";
+import "./oracles/IPriceOracle.sol";
+import "./rates/IInterestRateModel.sol";
 
 /**
  * @title ERC20TokenLoan
  * @dev A decentralized lending protocol for ERC20 tokens
  * Features:
  * - Lenders can deposit tokens to earn interest
+ * - Per-token pool shares that appreciate as interest is repaid
  * - Borrowers can take loans with collateral
- * - Dynamic interest rates based on utilization
+ * - Borrowers can top up or withdraw collateral, extend and refinance loans
+ * - Peer-to-peer order book (lender offers, borrower requests, EIP-712 signed offers)
+ * - ERC-3156 flash loans from pool liquidity, fees accrue to lenders
+ * - Protocol reserves from interest and liquidation penalties, withdrawable to a treasury
+ * - Cross-margin accounts borrowing several tokens against a basket of collaterals
+ * - Guardian-controlled per-token action pauses, borrow caps and supply caps
+ * - Bad debt write-offs covered by an insurance fund, then reserves, then lenders pro-rata
+ * - Keeper views: paginated liquidatable loans, health factors and liquidation previews
+ * - Credit delegation: depositors back uncollateralized loans from their own pool position
+ * - Upgradeable deployment behind a UUPS proxy (see ERC20TokenLoanUpgradeable)
+ * - Dynamic interest rates from a pluggable per-token rate model
+ * - Reserve factor on interest
+ * - Continuous interest accrual through a per-token borrow index
  * - Collateral ratio requirements
- * - Liquidations for undercollateralized loans
+ * - Oracle-based collateral valuation (Chainlink / TWAP adapters)
+ * - Partial liquidations bounded by a close factor, with a per-collateral bonus
  * - Loan term limits
  */
-contract ERC20TokenLoan is Ownable, ReentrancyGuard {
+contract ERC20TokenLoan is Ownable, ReentrancyGuard, EIP712, IERC3156FlashLender {
+    using SafeERC20 for IERC20;
+    using EnumerableSet for EnumerableSet.AddressSet;
+    using EnumerableSet for EnumerableSet.UintSet;
+    
+    
+    // ============ ENUMS ============
+    enum Action {
+        DEPOSIT,     // 0: Pool deposits and P2P offers
+        BORROW,      // 1: Every path that lends tokens out, including flash loans
+        REPAY,       // 2: Loan and account repayments
+        LIQUIDATE,   // 3: Loan and account liquidations
+        WITHDRAW     // 4: Pool withdrawals and collateral withdrawals
+    }
     
     // ============ STRUCTS ============
     
@@ -26,11 +61,11 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
      * @param borrower Address of the borrower
      * @param lender Address of the lender (0 if from pool)
      * @param amountPrincipal Original loan amount
-     * @param amountOwed Current amount owed (principal + interest)
+     * @param scaledDebt Debt divided by the loan's index (pool borrow index, or fixed-rate index for P2P loans)
      * @param collateralAmount Amount of collateral tokens deposited
      * @param collateralToken Address of collateral token
      * @param loanToken Address of loan token
-     * @param interestRate Annual interest rate (in basis points, 100 = 1%)
+     * @param interestRate Annual interest rate when the loan was taken (in basis points, 100 = 1%)
      * @param startTime Timestamp when loan was taken
      * @param dueTime Timestamp when loan is due
      * @param isActive Whether the loan is active
@@ -40,7 +75,7 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
         address borrower;
         address lender;
         uint256 amountPrincipal;
-        uint256 amountOwed;
+        uint256 scaledDebt;
         uint256 collateralAmount;
         address collateralToken;
         address loanToken;
@@ -52,101 +87,79 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
     }
     
     /**
-     * @dev Struct representing a lender's position
-     * @param amountDeposited Total amount deposited
-     * @param amountLent Amount currently lent out
-     * @param lastInterestUpdate Last time interest was calculated
-     * @param accumulatedInterest Interest earned so far
+     * @dev Struct representing a lender-posted loan offer (tokens escrowed in the contract)
+     * @param lender Address of the lender
+     * @param loanToken Address of token offered
+     * @param amountAvailable Amount still available to borrowers
+     * @param interestRate Fixed annual interest rate (in basis points)
+     * @param maxTerm Maximum loan duration in seconds
+     * @param acceptedCollaterals Collateral tokens the lender accepts
+     * @param isActive Whether the offer can still be taken
      */
-    struct LenderPosition {
-        uint256 amountDeposited;
-        uint256 amountLent;
-        uint256 lastInterestUpdate;
-        uint256 accumulatedInterest;
+    struct LoanOffer {
+        address lender;
+        address loanToken;
+        uint256 amountAvailable;
+        uint256 interestRate;
+        uint256 maxTerm;
+        address[] acceptedCollaterals;
+        bool isActive;
     }
     
     /**
-     * @dev Struct for token configuration
-     * @param enabled Whether this token is enabled for lending/borrowing
-     * @param minCollateralRatio Minimum collateral ratio (in basis points)
-     * @param maxLoanTerm Maximum loan term in seconds
-     * @param baseInterestRate Base interest rate (in basis points)
+     * @dev Struct representing a borrower-posted loan request (collateral escrowed in the contract)
+     * @param borrower Address of the borrower
+     * @param loanToken Address of token requested
+     * @param amount Amount requested
+     * @param collateralToken Address of collateral token
+     * @param collateralAmount Amount of collateral escrowed
+     * @param interestRate Maximum fixed annual interest rate the borrower pays (in basis points)
+     * @param duration Loan duration in seconds
+     * @param isActive Whether the request can still be filled
      */
-    struct TokenConfig {
-        bool enabled;
-        uint256 minCollateralRatio; // 15000 = 150%
-        uint256 maxLoanTerm; // in seconds
-        uint256 baseInterestRate; // in basis points
+    struct LoanRequest {
+        address borrower;
+        address loanToken;
+        uint256 amount;
+        address collateralToken;
+        uint256 collateralAmount;
+        uint256 interestRate;
+        uint256 duration;
+        bool isActive;
     }
     
-    // ============ CONSTANTS ============
-    uint256 public constant BASIS_POINTS = 10000; // 100% = 10000 basis points
-    uint256 public constant SECONDS_PER_YEAR = 365 days;
-// SPDX-License-Identifier: MIT
-pragma solidity ^0.8.19;
-
-// Import OpenZeppelin's ERC20 interface and SafeMath (though not needed in ^0.8.0)
-import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
-import "@openzeppelin/contracts/access/Ownable.sol";
-import "@openzeppelin/contracts/security/ReentrancyGuard.sol";
-
-/**
- * @title ERC20TokenLoan
- * @dev A decentralized lending protocol for ERC20 tokens
- * Features:
- * - Lenders can deposit tokens to earn interest
- * - Borrowers can take loans with collateral
- * - Dynamic interest rates based on utilization
- * - Collateral ratio requirements
- * - Liquidations for undercollateralized loans
- * - Loan term limits
- */
-contract ERC20TokenLoan is Ownable, ReentrancyGuard {
-    
-    // ============ STRUCTS ============
-    
     /**
-     * @dev Struct representing a loan
-     * @param borrower Address of the borrower
-     * @param lender Address of the lender (0 if from pool)
-     * @param amountPrincipal Original loan amount
-     * @param amountOwed Current amount owed (principal + interest)
-     * @param collateralAmount Amount of collateral tokens deposited
-     * @param collateralToken Address of collateral token
-     * @param loanToken Address of loan token
-     * @param interestRate Annual interest rate (in basis points, 100 = 1%)
-     * @param startTime Timestamp when loan was taken
-     * @param dueTime Timestamp when loan is due
-     * @param isActive Whether the loan is active
-     * @param isLiquidated Whether the loan has been liquidated
+     * @dev Off-chain loan offer signed by the lender (EIP-712), funded from the lender's wallet on acceptance
+     * @param lender Address of the lender (signer)
+     * @param loanToken Address of token offered
+     * @param amount Total amount that can be borrowed across fills
+     * @param interestRate Fixed annual interest rate (in basis points)
+     * @param maxTerm Maximum loan duration in seconds
+     * @param acceptedCollaterals Collateral tokens the lender accepts
+     * @param nonce Lender-chosen nonce, used for cancellation
+     * @param deadline Timestamp after which the offer can no longer be taken
      */
-    struct Loan {
-        address borrower;
+    struct SignedLoanOffer {
         address lender;
-        uint256 amountPrincipal;
-        uint256 amountOwed;
-        uint256 collateralAmount;
-        address collateralToken;
         address loanToken;
-        uint256 interestRate; // in basis points (1% = 100)
-        uint256 startTime;
-        uint256 dueTime;
-        bool isActive;
-        bool isLiquidated;
+        uint256 amount;
+        uint256 interestRate;
+        uint256 maxTerm;
+        address[] acceptedCollaterals;
+        uint256 nonce;
+        uint256 deadline;
     }
     
     /**
-     * @dev Struct representing a lender's position
-     * @param amountDeposited Total amount deposited
-     * @param amountLent Amount currently lent out
-     * @param lastInterestUpdate Last time interest was calculated
-     * @param accumulatedInterest Interest earned so far
+     * @dev Struct representing a lender's position in one token pool
+     * @param shares Pool shares owned by the lender
+     * @param amountDeposited Net amount deposited (deposits minus withdrawals, floored at 0)
+     * @param lastDepositTime Timestamp of the last deposit
      */
     struct LenderPosition {
+        uint256 shares;
         uint256 amountDeposited;
-        uint256 amountLent;
-        uint256 lastInterestUpdate;
-        uint256 accumulatedInterest;
+        uint256 lastDepositTime;
     }
     
     /**
@@ -154,33 +167,127 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
      * @param enabled Whether this token is enabled for lending/borrowing
      * @param minCollateralRatio Minimum collateral ratio (in basis points)
      * @param maxLoanTerm Maximum loan term in seconds
-     * @param baseInterestRate Base interest rate (in basis points)
+     * @param interestRateModel Rate model used for both borrow and supply rates
+     * @param reserveFactor Share of interest kept as protocol reserves (in basis points)
      */
     struct TokenConfig {
         bool enabled;
         uint256 minCollateralRatio; // 15000 = 150%
         uint256 maxLoanTerm; // in seconds
-        uint256 baseInterestRate; // in basis points
+        address interestRateModel;
+        uint256 reserveFactor; // 1000 = 10%
+    }
+    
+    /**
+     * @dev Struct for a token's price feed
+     * @param primaryOracle Oracle used for valuation
+     * @param secondaryOracle Optional oracle used to sanity check the primary price (0 if unused)
+     * @param maxDeviation Maximum allowed deviation between both oracles (in basis points)
+     * @param tokenDecimals Cached decimals of the token, used for value normalization
+     */
+    struct PriceFeed {
+        address primaryOracle;
+        address secondaryOracle;
+        uint256 maxDeviation; // 500 = 5%
+        uint8 tokenDecimals;
+    }
+    
+    /**
+     * @dev Credit line granted by a depositor to a borrower
+     * @param amount Amount the borrower can still draw
+     * @param interestRate Fixed annual interest rate (in basis points)
+     * @param maxTerm Maximum loan duration in seconds
+     */
+    struct CreditDelegation {
+        uint256 amount;
+        uint256 interestRate;
+        uint256 maxTerm;
+    }
+    
+    /**
+     * @dev Cumulative bad debt written off for a token and how it was absorbed
+     * @param totalWrittenOff Total debt written off
+     * @param coveredByCollateral Part paid by the sale of the seized collateral (or handed to P2P lenders)
+     * @param coveredByInsurance Part paid from the insurance fund
+     * @param coveredByReserves Part paid from protocol reserves
+     * @param socializedLoss Part absorbed by pool lenders
+     */
+    struct BadDebtStats {
+        uint256 totalWrittenOff;
+        uint256 coveredByCollateral;
+        uint256 coveredByInsurance;
+        uint256 coveredByReserves;
+        uint256 socializedLoss;
     }
     
     // ============ CONSTANTS ============
     uint256 public constant BASIS_POINTS = 10000; // 100% = 10000 basis points
     uint256 public constant SECONDS_PER_YEAR = 365 days;
-    uint256 public constant LIQUIDATION_PENALTY = 500; // 5% penalty
-    uint256 public constant LIQUIDATION_REWARD = 100; // 1% reward for liquidator
+    uint256 public constant LIQUIDATION_PENALTY = 500; // 5% of repaid value, seized in collateral for reserves
+    uint256 public constant MAX_LIQUIDATION_BONUS = 2000; // 20% max bonus for liquidators
+    uint256 public constant MAX_COLLATERAL_FACTOR = 9000; // 90% max borrowing power per unit of collateral
     uint256 public constant MAX_INTEREST_RATE = 5000; // 50% max interest
+    uint256 public constant MAX_RESERVE_FACTOR = 5000; // 50% max reserve factor
+    uint256 public constant MAX_LATE_FEE = 2000; // 20% max late repayment fee
+    uint256 public constant MAX_FLASH_FEE = 100; // 1% max flash loan fee
+    uint256 public constant MAX_WRITE_OFF_DISCOUNT = 1000; // 10% max discount on written-off collateral
+    bytes32 public constant FLASH_CALLBACK_SUCCESS = keccak256("ERC3156FlashBorrower.onFlashLoan");
+    uint256 public constant INDEX_SCALE = 1e18; // Borrow index starts at 1.0
+    uint256 public constant HEALTH_FACTOR_ONE = 1e18; // Loans below this health factor can be liquidated
+    uint256 public constant LIQUIDATION_THRESHOLD = 9000; // Loans are liquidatable below 90% of the minimum collateral ratio
+    bytes32 public constant LOAN_OFFER_TYPEHASH = keccak256(
+        "LoanOffer(address lender,address loanToken,uint256 amount,uint256 interestRate,uint256 maxTerm,address[] acceptedCollaterals,uint256 nonce,uint256 deadline)"
+    );
     
     // ============ STATE VARIABLES ============
     uint256 public loanCounter;
+    address public treasury; // Receiver of withdrawn reserves
+    address public guardian; // Can pause actions, only the owner can unpause
+    uint256 public closeFactor; // Max share of a loan's debt repayable in one liquidation (basis points)
+    uint256 public lateFee; // Penalty on late repayments (basis points of the amount repaid)
+    uint256 public flashLoanFee; // Fee on flash loans (basis points of the amount borrowed)
+    uint256 public offerCounter;
+    uint256 public requestCounter;
     
     // Mappings
     mapping(uint256 => Loan) public loans;
     mapping(address => uint256[]) public userLoans;
-    mapping(address => LenderPosition) public lenderPositions;
+    mapping(address => mapping(address => LenderPosition)) public lenderPositions; // token => lender => position
+    mapping(address => uint256) public totalShares; // Total pool shares per token
     mapping(address => uint256) public totalLiquidity; // Total tokens available for lending
-    mapping(address => uint256) public totalBorrowed; // Total tokens currently borrowed
+    mapping(address => uint256) public totalBorrowed; // Total tokens currently borrowed, including accrued interest
+    mapping(address => uint256) public totalReserves; // Protocol share of interest and penalties, held in liquidity
+    mapping(address => bool) public isPoolToken; // Tokens tracked by pool or collateral accounting
+    mapping(address => mapping(Action => bool)) public actionPaused; // token => action => paused
+    mapping(address => uint256) public borrowCaps; // token => max total pool borrows (0 = no cap)
+    mapping(address => uint256) public supplyCaps; // token => max total pool assets (0 = no cap)
+    mapping(address => uint256) public insuranceFund; // token => first-loss capital, held outside pool liquidity
+    mapping(address => BadDebtStats) public badDebtStats;
+    EnumerableSet.UintSet internal activeLoanIds;
+    mapping(address => mapping(address => mapping(address => CreditDelegation))) public creditDelegations; // delegator => token => borrower
+    mapping(uint256 => bool) public isDelegatedLoan;
+    mapping(address => mapping(address => uint256)) public delegatedDefaults; // delegator => token => debt defaulted
+    mapping(address => uint256) public borrowIndex; // Cumulative interest factor per token
+    mapping(address => uint256) public lastAccrualTime; // Last time the borrow index was updated
     mapping(address => TokenConfig) public tokenConfigs;
     mapping(address => mapping(address => bool)) public approvedCollaterals; // loanToken => collateralToken => approved
+    mapping(address => PriceFeed) public priceFeeds;
+    mapping(address => uint256) public liquidationBonus; // collateralToken => bonus in basis points
+    mapping(address => uint256) public collateralFactors; // collateralToken => account borrowing power in basis points
+    mapping(address => mapping(address => uint256)) public accountCollateral; // account => token => amount
+    mapping(address => mapping(address => uint256)) public accountScaledBorrows; // account => loanToken => scaled debt
+    mapping(address => EnumerableSet.AddressSet) internal accountCollateralTokens;
+    mapping(address => EnumerableSet.AddressSet) internal accountBorrowTokens;
+    mapping(uint256 => LoanOffer) public loanOffers;
+    mapping(uint256 => LoanRequest) public loanRequests;
+    mapping(address => uint256[]) public offersByToken; // loanToken => offer IDs
+    mapping(address => uint256[]) public requestsByToken; // loanToken => request IDs
+    mapping(bytes32 => uint256) public signedOfferFilled; // offer digest => amount already borrowed
+    mapping(address => mapping(uint256 => bool)) public cancelledOfferNonces; // lender => nonce => cancelled
+    uint256 public writeOffDiscount; // Discount on collateral bought in bad debt write-offs (basis points)
+    
+    // Reserved storage so new state can be appended ahead of proxy-only variables
+    uint256[49] private __gap;
     
     // ============ EVENTS ============
     event LoanCreated(
@@ -202,6 +309,22 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
         uint256 collateralReturned
     );
     
+    event LateFeeCharged(uint256 indexed loanId, uint256 fee);
+    
+    event CollateralAdded(uint256 indexed loanId, uint256 amount, uint256 newCollateralAmount);
+    
+    event CollateralWithdrawn(uint256 indexed loanId, uint256 amount, uint256 newCollateralAmount);
+    
+    event LoanExtended(uint256 indexed loanId, uint256 newDueTime);
+    
+    event LoanRefinanced(
+        uint256 indexed loanId,
+        address indexed previousLender,
+        uint256 debt,
+        uint256 interestRate,
+        uint256 newDueTime
+    );
+    
     event LoanLiquidated(
         uint256 indexed loanId,
         address indexed liquidator,
@@ -213,19 +336,22 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
     event LiquidityAdded(
         address indexed lender,
         address indexed token,
-        uint256 amount
+        uint256 amount,
+        uint256 shares
     );
     
     event LiquidityWithdrawn(
         address indexed lender,
         address indexed token,
-        uint256 amount
+        uint256 amount,
+        uint256 shares
     );
     
     event InterestAccrued(
-        address indexed lender,
         address indexed token,
-        uint256 interestAmount
+        uint256 interestAmount,
+        uint256 newBorrowIndex,
+        uint256 newTotalBorrowed
     );
     
     event TokenConfigUpdated(
@@ -233,7 +359,8 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
         bool enabled,
         uint256 minCollateralRatio,
         uint256 maxLoanTerm,
-        uint256 baseInterestRate
+        address interestRateModel,
+        uint256 reserveFactor
     );
     
     event CollateralApproved(
@@ -242,6 +369,121 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
         bool approved
     );
     
+    event PriceFeedUpdated(
+        address indexed token,
+        address primaryOracle,
+        address secondaryOracle,
+        uint256 maxDeviation
+    );
+    
+    event CloseFactorUpdated(uint256 closeFactor);
+    
+    event LateFeeUpdated(uint256 lateFee);
+    
+    event TreasuryUpdated(address indexed treasury);
+    
+    event GuardianUpdated(address indexed guardian);
+    
+    event CreditDelegated(
+        address indexed delegator,
+        address indexed borrower,
+        address indexed token,
+        uint256 amount,
+        uint256 interestRate,
+        uint256 maxTerm
+    );
+    
+    event DelegatedLoanDefaulted(uint256 indexed loanId, address indexed delegator, uint256 debt);
+    
+    event InsuranceFunded(address indexed token, address indexed funder, uint256 amount);
+    
+    event BadDebtWrittenOff(
+        uint256 indexed loanId,
+        address indexed token,
+        uint256 debt,
+        uint256 coveredByCollateral,
+        uint256 coveredByInsurance,
+        uint256 coveredByReserves,
+        uint256 socializedLoss
+    );
+    
+    event AccountBadDebtWrittenOff(
+        address indexed account,
+        address indexed token,
+        uint256 debt,
+        uint256 coveredByCollateral,
+        uint256 coveredByInsurance,
+        uint256 coveredByReserves,
+        uint256 socializedLoss
+    );
+    
+    event ActionPaused(address indexed token, Action indexed action, bool paused);
+    
+    event BorrowCapUpdated(address indexed token, uint256 borrowCap);
+    
+    event SupplyCapUpdated(address indexed token, uint256 supplyCap);
+    
+    event ReservesWithdrawn(address indexed token, address indexed treasury, uint256 amount);
+    
+    event LiquidationPenaltyCollected(uint256 indexed loanId, address indexed collateralToken, uint256 amount);
+    
+    event FlashLoan(address indexed receiver, address indexed token, uint256 amount, uint256 fee);
+    
+    event FlashLoanFeeUpdated(uint256 flashLoanFee);
+    
+    event WriteOffDiscountUpdated(uint256 writeOffDiscount);
+    
+    event LoanOfferCreated(
+        uint256 indexed offerId,
+        address indexed lender,
+        address indexed loanToken,
+        uint256 amount,
+        uint256 interestRate,
+        uint256 maxTerm
+    );
+    
+    event LoanOfferCancelled(uint256 indexed offerId, uint256 amountReturned);
+    
+    event LoanRequestCreated(
+        uint256 indexed requestId,
+        address indexed borrower,
+        address indexed loanToken,
+        uint256 amount,
+        address collateralToken,
+        uint256 collateralAmount,
+        uint256 interestRate,
+        uint256 duration
+    );
+    
+    event LoanRequestCancelled(uint256 indexed requestId);
+    
+    event SignedLoanOfferCancelled(address indexed lender, uint256 nonce);
+    
+    event LiquidationBonusUpdated(
+        address indexed collateralToken,
+        uint256 bonus
+    );
+    
+    event CollateralFactorUpdated(address indexed collateralToken, uint256 collateralFactor);
+    
+    event AccountCollateralDeposited(address indexed account, address indexed token, uint256 amount);
+    
+    event AccountCollateralWithdrawn(address indexed account, address indexed token, uint256 amount);
+    
+    event AccountBorrow(address indexed account, address indexed loanToken, uint256 amount);
+    
+    event AccountRepay(address indexed account, address indexed loanToken, uint256 amount);
+    
+    event AccountLiquidated(
+        address indexed account,
+        address indexed liquidator,
+        address indexed loanToken,
+        address collateralToken,
+        uint256 amountRepaid,
+        uint256 collateralSeized,
+        uint256 penalty
+    );
+    
     // ============ MODIFIERS ============
     
     /**
@@ -252,6 +494,11 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
         _;
     }
     
+    modifier whenNotPaused(address token, Action action) {
+        _requireNotPaused(token, action);
+        _;
+    }
+    
     /**
      * @dev Modifier to check if loan exists and is active
      */
@@ -264,64 +511,132 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
     
     // ============ CONSTRUCTOR ============
     
-    constructor() {
+    constructor() Ownable(msg.sender) EIP712("ERC20TokenLoan", "1") {
+        _initializeDefaults(msg.sender);
+    }
+    
+    /**
+     * @dev Set the default risk parameters, shared by the constructor and proxy initializer
+     * @param _treasury Initial receiver of withdrawn reserves
+     */
+    function _initializeDefaults(address _treasury) internal {
         loanCounter = 0;
+        treasury = _treasury;
+        closeFactor = 5000; // 50%
+        lateFee = 500; // 5%
+        flashLoanFee = 9; // 0.09%
+        writeOffDiscount = 500; // 5%
     }
     
     // ============ LENDING FUNCTIONS ============
     
     /**
-     * @dev Deposit tokens to lend
+     * @dev Deposit tokens to lend in exchange for pool shares
      * @param token Address of token to deposit
      * @param amount Amount to deposit
+     * @return shares Amount of pool shares minted
      */
     function depositLiquidity(address token, uint256 amount) 
         external 
         nonReentrant 
+        whenNotPaused(token, Action.DEPOSIT) 
         tokenEnabled(token) 
+        returns (uint256 shares)
     {
         require(amount > 0, "Amount must be > 0");
         
-        // Transfer tokens from lender
-        IERC20(token).transferFrom(msg.sender, address(this), amount);
+        accrueInterest(token);
+        
+        // Transfer tokens from lender, crediting only what arrived
+        amount = _pullTokens(token, msg.sender, amount);
+        require(
+            supplyCaps[token] == 0 || totalPoolAssets(token) + amount <= supplyCaps[token],
+            "Supply cap reached"
+        );
+        
+        // Price shares before the deposit changes pool assets
+        shares = convertToShares(token, amount);
+        require(shares > 0, "Zero shares");
         
         // Update lender position
-        LenderPosition storage position = lenderPositions[msg.sender];
+        LenderPosition storage position = lenderPositions[token][msg.sender];
+        position.shares += shares;
         position.amountDeposited += amount;
-        position.lastInterestUpdate = block.timestamp;
+        position.lastDepositTime = block.timestamp;
         
-        // Update total liquidity
+        // Update pool totals
+        totalShares[token] += shares;
         totalLiquidity[token] += amount;
         
-        emit LiquidityAdded(msg.sender, token, amount);
+        emit LiquidityAdded(msg.sender, token, amount, shares);
     }
     
     /**
-     * @dev Withdraw deposited tokens (if not lent out)
+     * @dev Withdraw an exact amount of tokens, burning the shares it is worth
      * @param token Address of token to withdraw
      * @param amount Amount to withdraw
+     * @return shares Amount of pool shares burned
      */
     function withdrawLiquidity(address token, uint256 amount) 
         external 
         nonReentrant 
+        whenNotPaused(token, Action.WITHDRAW) 
+        returns (uint256 shares)
+    {
+        require(amount > 0, "Amount must be > 0");
+        
+        accrueInterest(token);
+        
+        // Round up so the lender never receives more than their shares are worth
+        shares = _convertToSharesRoundUp(token, amount);
+        
+        _withdraw(token, amount, shares);
+    }
+    
+    /**
+     * @dev Redeem pool shares for tokens at the current exchange rate
+     * @param token Address of token to withdraw
+     * @param shares Amount of pool shares to burn
+     * @return amount Amount of tokens withdrawn
+     */
+    function redeemShares(address token, uint256 shares) 
+        external 
+        nonReentrant 
+        whenNotPaused(token, Action.WITHDRAW) 
+        returns (uint256 amount)
     {
-        LenderPosition storage position = lenderPositions[msg.sender];
+        require(shares > 0, "Shares must be > 0");
+        
+        accrueInterest(token);
+        
+        amount = convertToAssets(token, shares);
+        require(amount > 0, "Zero amount");
         
-        // Calculate available balance (deposited - lent out)
-        uint256 availableBalance = position.amountDeposited - position.amountLent;
-        require(amount <= availableBalance, "Insufficient available balance");
+        _withdraw(token, amount, shares);
+    }
+    
+    /**
+     * @dev Burn lender shares and transfer the underlying tokens
+     * @param token Address of token
+     * @param amount Amount of tokens to transfer
+     * @param shares Amount of shares to burn
+     */
+    function _withdraw(address token, uint256 amount, uint256 shares) internal {
+        LenderPosition storage position = lenderPositions[token][msg.sender];
         
-        // Accrue interest before withdrawal
-        _accrueInterest(msg.sender, token);
+        require(shares <= position.shares, "Insufficient shares");
+        require(amount <= _availableLiquidity(token), "Insufficient liquidity");
         
         // Update positions
-        position.amountDeposited -= amount;
+        position.shares -= shares;
+        position.amountDeposited = amount >= position.amountDeposited ? 0 : position.amountDeposited - amount;
+        totalShares[token] -= shares;
         totalLiquidity[token] -= amount;
         
         // Transfer tokens to lender
-        IERC20(token).transfer(msg.sender, amount);
+        IERC20(token).safeTransfer(msg.sender, amount);
         
-        emit LiquidityWithdrawn(msg.sender, token, amount);
+        emit LiquidityWithdrawn(msg.sender, token, amount, shares);
     }
     
     // ============ BORROWING FUNCTIONS ============
@@ -343,6 +658,7 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
     ) 
         external 
         nonReentrant 
+        whenNotPaused(loanToken, Action.BORROW) 
         tokenEnabled(loanToken)
         returns (uint256) 
     {
@@ -351,226 +667,1605 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
         require(collateralAmount > 0, "Collateral amount must be > 0");
         require(approvedCollaterals[loanToken][collateralToken], "Collateral not approved");
         
-        TokenConfig storage config = tokenConfigs[loanToken];
-        
         // Check loan duration
-        require(loanDuration <= config.maxLoanTerm, "Loan duration too long");
-        
-        // Calculate collateral ratio
-        uint256 collateralValue = _getTokenValue(collateralToken, collateralAmount);
-        uint256 loanValue = _getTokenValue(loanToken, loanAmount);
-        uint256 collateralRatio = (collateralValue * BASIS_POINTS) / loanValue;
-        
-        require(collateralRatio >= config.minCollateralRatio, "Insufficient collateral");
-        
-        // Check available liquidity
-        require(loanAmount <= totalLiquidity[loanToken], "Insufficient liquidity");
+        require(loanDuration <= tokenConfigs[loanToken].maxLoanTerm, "Loan duration too long");
         
-        // Calculate dynamic interest rate
-        uint256 interestRate = _calculateInterestRate(loanToken, loanAmount);
+        // Transfer collateral from borrower
+        collateralAmount = _pullTokens(collateralToken, msg.sender, collateralAmount);
         
-        // Calculate total owed
-        uint256 interestAmount = (loanAmount * interestRate * loanDuration) / 
-                                (BASIS_POINTS * SECONDS_PER_YEAR);
-        uint256 totalOwed = loanAmount + interestAmount;
+        // Bring the index up to date before recording the debt against it
+        accrueInterest(loanToken);
         
-        // Transfer collateral from borrower
-        IERC20(collateralToken).transferFrom(msg.sender, address(this), collateralAmount);
+        // Check available liquidity, reserves are kept for the treasury
+        require(loanAmount <= _availableLiquidity(loanToken), "Insufficient liquidity");
+        _checkBorrowCap(loanToken, loanAmount);
         
-        // Transfer loan tokens to borrower
-        IERC20(loanToken).transfer(msg.sender, loanAmount);
+        // Update pool state
+        totalLiquidity[loanToken] -= loanAmount;
+        totalBorrowed[loanToken] += loanAmount;
         
-        // Create loan
-        uint256 loanId = loanCounter++;
-        loans[loanId] = Loan({
+        // Create loan (rate after this loan is taken is informational, the debt follows the index)
+        uint256 loanId = _storeLoan(Loan({
             borrower: msg.sender,
             lender: address(0), // From pool
             amountPrincipal: loanAmount,
-            amountOwed: totalOwed,
+            scaledDebt: _toScaledDebtRoundUp(loanAmount, borrowIndex[loanToken]),
             collateralAmount: collateralAmount,
             collateralToken: collateralToken,
             loanToken: loanToken,
-            interestRate: interestRate,
+            interestRate: getBorrowRate(loanToken),
             startTime: block.timestamp,
             dueTime: block.timestamp + loanDuration,
             isActive: true,
             isLiquidated: false
-        });
+        }));
         
-        // Update state
-        userLoans[msg.sender].push(loanId);
-        totalLiquidity[loanToken] -= loanAmount;
-        totalBorrowed[loanToken] += loanAmount;
-        
-        emit LoanCreated(
-            loanId,
-            msg.sender,
-            address(0),
-            loanToken,
-            collateralToken,
-            loanAmount,
-            collateralAmount,
-            interestRate,
-            block.timestamp + loanDuration
-        );
+        // Transfer loan tokens to borrower
+        IERC20(loanToken).safeTransfer(msg.sender, loanAmount);
         
         return loanId;
     }
     
     /**
-     * @dev Repay a loan
+     * @dev Repay part or all of a loan, interest is charged up to the current block
+     * Repayments after the due date pay the late fee on top of the amount repaid.
      * @param loanId ID of loan to repay
+     * @param amount Amount to repay (type(uint256).max repays the full debt)
      */
-    function repayLoan(uint256 loanId) 
+    function repayLoan(uint256 loanId, uint256 amount) 
         external 
         nonReentrant 
         loanActive(loanId) 
     {
         Loan storage loan = loans[loanId];
         require(msg.sender == loan.borrower, "Only borrower can repay");
-        require(block.timestamp <= loan.dueTime, "Loan overdue");
+        _requireNotPaused(loan.loanToken, Action.REPAY);
         
-        uint256 amountToRepay = loan.amountOwed;
+        accrueInterest(loan.loanToken);
         
-        // Transfer repayment from borrower
-        IERC20(loan.loanToken).transferFrom(msg.sender, address(this), amountToRepay);
+        uint256 debt = _loanDebt(loan);
+        uint256 amountToRepay = amount >= debt ? debt : amount;
+        require(amountToRepay > 0, "Amount must be > 0");
         
-        // Return collateral to borrower
-        IERC20(loan.collateralToken).transfer(loan.borrower, loan.collateralAmount);
+        uint256 fee = 0;
+        if (block.timestamp > loan.dueTime) {
+            fee = (amountToRepay * lateFee) / BASIS_POINTS;
+        }
         
-        // Update loan state
-        loan.isActive = false;
-        loan.amountOwed = 0;
+        // Transfer repayment from borrower, a token transfer fee reduces the amount credited
+        uint256 received = _pullTokens(loan.loanToken, msg.sender, amountToRepay + fee);
+        if (received < amountToRepay + fee) {
+            uint256 repaid = (received * amountToRepay) / (amountToRepay + fee);
+            fee = received - repaid;
+            amountToRepay = repaid;
+        }
         
-        // Update pool state
+        _reduceDebt(loan, amountToRepay, debt);
+        
+        // Credit the repayment and late fee to the pool/lender
         if (loan.lender == address(0)) {
             // Loan was from pool
-            totalLiquidity[loan.loanToken] += amountToRepay;
-            totalBorrowed[loan.loanToken] -= loan.amountPrincipal;
+            totalLiquidity[loan.loanToken] += amountToRepay + fee;
+        } else if (isDelegatedLoan[loanId]) {
+            // Delegated credit, the repayment goes back into the delegator's pool position
+            _creditDelegator(loan.loanToken, loan.lender, amountToRepay + fee);
+        } else {
+            // P2P loan
+            IERC20(loan.loanToken).safeTransfer(loan.lender, amountToRepay + fee);
+        }
+        
+        if (fee > 0) {
+            emit LateFeeCharged(loanId, fee);
+        }
+        
+        uint256 collateralReturned = 0;
+        if (amountToRepay == debt) {
+            // Fully repaid, close the loan and return collateral
+            collateralReturned = loan.collateralAmount;
+            loan.collateralAmount = 0;
+            loan.isActive = false;
+            activeLoanIds.remove(loanId);
+            if (collateralReturned > 0) {
+                IERC20(loan.collateralToken).safeTransfer(loan.borrower, collateralReturned);
+            }
         }
         
-        emit LoanRepaid(loanId, msg.sender, amountToRepay, loan.collateralAmount);
+        emit LoanRepaid(loanId, msg.sender, amountToRepay, collateralReturned);
     }
     
-    // ============ LIQUIDATION FUNCTIONS ============
-    
     /**
-     * @dev Liquidate an undercollateralized loan
-     * @param loanId ID of loan to liquidate
+     * @dev Add collateral to a loan
+     * @param loanId ID of loan
+     * @param amount Amount of collateral to add
      */
-    function liquidateLoan(uint256 loanId) 
-        external 
-        nonReentrant 
-        loanActive(loanId) 
-    {
+    function addCollateral(uint256 loanId, uint256 amount) external nonReentrant loanActive(loanId) {
         Loan storage loan = loans[loanId];
+        require(msg.sender == loan.borrower, "Only borrower");
+        require(amount > 0, "Amount must be > 0");
         
-        // Check if loan is overdue or undercollateralized
-        bool isOverdue = block.timestamp > loan.dueTime;
-        bool isUndercollateralized = _isUndercollateralized(loanId);
+        amount = _pullTokens(loan.collateralToken, msg.sender, amount);
+        loan.collateralAmount += amount;
         
-        require(isOverdue || isUndercollateralized, "Loan not liquidatable");
+        emit CollateralAdded(loanId, amount, loan.collateralAmount);
+    }
+    
+    /**
+     * @dev Withdraw collateral in excess of the minimum collateral ratio
+     * The loan keeps the minimum ratio, which sits above its liquidation threshold by LIQUIDATION_THRESHOLD.
+     * @param loanId ID of loan
+     * @param amount Amount of collateral to withdraw
+     */
+    function withdrawCollateral(uint256 loanId, uint256 amount) external nonReentrant loanActive(loanId) {
+        Loan storage loan = loans[loanId];
+        require(msg.sender == loan.borrower, "Only borrower");
+        require(amount > 0 && amount < loan.collateralAmount, "Invalid amount");
+        _requireNotPaused(loan.collateralToken, Action.WITHDRAW);
+        require(block.timestamp <= loan.dueTime, "Loan overdue");
         
-        // Calculate amounts
-        uint256 repaymentRequired = loan.amountOwed;
-        uint256 collateralValue = _getTokenValue(loan.collateralToken, loan.collateralAmount);
+        accrueInterest(loan.loanToken);
         
-        // Calculate liquidation penalty (5% of loan value)
-        uint256 penalty = (repaymentRequired * LIQUIDATION_PENALTY) / BASIS_POINTS;
-        uint256 totalRepayment = repaymentRequired + penalty;
+        loan.collateralAmount -= amount;
+        _checkCollateralRatio(loan.loanToken, loan.collateralToken, _loanDebt(loan), loan.collateralAmount);
         
-        // Check if collateral covers repayment + penalty
-        require(collateralValue >= totalRepayment, "Insufficient collateral for liquidation");
+        IERC20(loan.collateralToken).safeTransfer(msg.sender, amount);
         
-        // Calculate liquidator reward (1% of collateral)
-        uint256 liquidatorReward = (loan.collateralAmount * LIQUIDATION_REWARD) / BASIS_POINTS;
-        uint256 remainingCollateral = loan.collateralAmount - liquidatorReward;
+        emit CollateralWithdrawn(loanId, amount, loan.collateralAmount);
+    }
+    
+    /**
+     * @dev Push back the due date of a pool loan
+     * The new due date is capped at the token's max loan term from now.
+     * @param loanId ID of loan
+     * @param extension Additional time in seconds
+     */
+    function extendLoan(uint256 loanId, uint256 extension) external nonReentrant loanActive(loanId) {
+        Loan storage loan = loans[loanId];
+        require(msg.sender == loan.borrower, "Only borrower");
+        require(loan.lender == address(0), "Only pool loans");
+        require(extension > 0, "Extension must be > 0");
+        require(block.timestamp <= loan.dueTime, "Loan overdue");
         
-        // Transfer repayment from liquidator
-        IERC20(loan.loanToken).transferFrom(msg.sender, address(this), repaymentRequired);
+        uint256 newDueTime = loan.dueTime + extension;
+        require(newDueTime - block.timestamp <= tokenConfigs[loan.loanToken].maxLoanTerm, "Loan duration too long");
         
-        // Transfer collateral to liquidator (reward)
-        IERC20(loan.collateralToken).transfer(msg.sender, liquidatorReward);
+        accrueInterest(loan.loanToken);
+        _checkCollateralRatio(loan.loanToken, loan.collateralToken, _loanDebt(loan), loan.collateralAmount);
         
-        // Transfer remaining collateral to pool/lender
-        if (loan.lender == address(0)) {
-            // Loan was from pool
-            IERC20(loan.collateralToken).transfer(address(this), remainingCollateral);
-            totalLiquidity[loan.loanToken] += repaymentRequired;
-        } else {
-            // P2P loan
-            IERC20(loan.collateralToken).transfer(loan.lender, remainingCollateral);
-        }
+        loan.dueTime = newDueTime;
         
-        // Update loan state
-        loan.isActive = false;
-        loan.isLiquidated = true;
-        loan.amountOwed = 0;
+        emit LoanExtended(loanId, newDueTime);
+    }
+    
+    /**
+     * @dev Refinance a loan into the pool at the current rate for a new term
+     * A P2P lender is paid out of pool liquidity and the debt moves onto the pool's borrow index.
+     * @param loanId ID of loan
+     * @param duration New loan duration in seconds from now
+     */
+    function refinanceLoan(uint256 loanId, uint256 duration) external nonReentrant loanActive(loanId) {
+        Loan storage loan = loans[loanId];
+        address loanToken = loan.loanToken;
+        require(msg.sender == loan.borrower, "Only borrower");
+        require(!isDelegatedLoan[loanId], "Delegated loan");
+        require(tokenConfigs[loanToken].enabled, "Token not enabled");
+        _requireNotPaused(loanToken, Action.BORROW);
+        require(block.timestamp <= loan.dueTime, "Loan overdue");
+        require(duration <= tokenConfigs[loanToken].maxLoanTerm, "Loan duration too long");
+        
+        accrueInterest(loanToken);
+        
+        uint256 debt = _loanDebt(loan);
+        _checkCollateralRatio(loanToken, loan.collateralToken, debt, loan.collateralAmount);
+        
+        address previousLender = loan.lender;
+        if (previousLender != address(0)) {
+            // Pool buys the P2P loan off the lender
+            require(debt <= _availableLiquidity(loanToken), "Insufficient liquidity");
+            _checkBorrowCap(loanToken, debt);
+            totalLiquidity[loanToken] -= debt;
+            totalBorrowed[loanToken] += debt;
+            
+            loan.lender = address(0);
+            loan.scaledDebt = _toScaledDebtRoundUp(debt, borrowIndex[loanToken]);
+            
+            IERC20(loanToken).safeTransfer(previousLender, debt);
+        }
+        
+        loan.interestRate = getBorrowRate(loanToken);
+        loan.startTime = block.timestamp;
+        loan.dueTime = block.timestamp + duration;
+        
+        emit LoanRefinanced(loanId, previousLender, debt, loan.interestRate, loan.dueTime);
+    }
+    
+    /**
+     * @dev Validate collateralization, store a new loan and emit its creation
+     * @param newLoan Loan to store
+     * @return loanId ID of the new loan
+     */
+    function _storeLoan(Loan memory newLoan) internal returns (uint256 loanId) {
+        require(newLoan.amountPrincipal > 0, "Loan amount must be > 0");
+        require(newLoan.collateralAmount > 0, "Collateral amount must be > 0");
+        require(tokenConfigs[newLoan.loanToken].enabled, "Token not enabled");
+        require(approvedCollaterals[newLoan.loanToken][newLoan.collateralToken], "Collateral not approved");
+        
+        _checkCollateralRatio(
+            newLoan.loanToken,
+            newLoan.collateralToken,
+            newLoan.amountPrincipal,
+            newLoan.collateralAmount
+        );
+        
+        loanId = _recordLoan(newLoan);
+    }
+    
+    /**
+     * @dev Store a new loan and emit its creation
+     * @param newLoan Loan to store
+     * @return loanId ID of the new loan
+     */
+    function _recordLoan(Loan memory newLoan) internal returns (uint256 loanId) {
+        loanId = loanCounter++;
+        loans[loanId] = newLoan;
+        userLoans[newLoan.borrower].push(loanId);
+        activeLoanIds.add(loanId);
+        
+        emit LoanCreated(
+            loanId,
+            newLoan.borrower,
+            newLoan.lender,
+            newLoan.loanToken,
+            newLoan.collateralToken,
+            newLoan.amountPrincipal,
+            newLoan.collateralAmount,
+            newLoan.interestRate,
+            newLoan.dueTime
+        );
+    }
+    
+    /**
+     * @dev Require a collateral amount to cover a loan amount at the token's minimum ratio
+     * @param loanToken Address of loan token
+     * @param collateralToken Address of collateral token
+     * @param loanAmount Amount borrowed
+     * @param collateralAmount Amount of collateral
+     */
+    function _checkCollateralRatio(
+        address loanToken,
+        address collateralToken,
+        uint256 loanAmount,
+        uint256 collateralAmount
+    ) internal view {
+        uint256 collateralValue = _getTokenValue(collateralToken, collateralAmount);
+        uint256 loanValue = _getTokenValue(loanToken, loanAmount);
+        require(loanValue > 0, "Loan value too small");
+        uint256 collateralRatio = (collateralValue * BASIS_POINTS) / loanValue;
+        
+        require(collateralRatio >= tokenConfigs[loanToken].minCollateralRatio, "Insufficient collateral");
+    }
+    
+    // ============ P2P ORDER BOOK ============
+    
+    /**
+     * @dev Post a fixed-rate loan offer, escrowing the offered tokens
+     * @param loanToken Address of token to lend
+     * @param amount Amount offered
+     * @param interestRate Fixed annual interest rate in basis points
+     * @param maxTerm Maximum loan duration in seconds
+     * @param acceptedCollaterals Collateral tokens accepted by the lender
+     * @return offerId ID of the new offer
+     */
+    function createLoanOffer(
+        address loanToken,
+        uint256 amount,
+        uint256 interestRate,
+        uint256 maxTerm,
+        address[] calldata acceptedCollaterals
+    ) 
+        external 
+        nonReentrant 
+        whenNotPaused(loanToken, Action.DEPOSIT) 
+        tokenEnabled(loanToken) 
+        returns (uint256 offerId) 
+    {
+        require(amount > 0, "Amount must be > 0");
+        _validateOfferTerms(loanToken, interestRate, maxTerm, acceptedCollaterals);
+        
+        // Escrow the offered tokens
+        amount = _pullTokens(loanToken, msg.sender, amount);
+        
+        offerId = offerCounter++;
+        LoanOffer storage offer = loanOffers[offerId];
+        offer.lender = msg.sender;
+        offer.loanToken = loanToken;
+        offer.amountAvailable = amount;
+        offer.interestRate = interestRate;
+        offer.maxTerm = maxTerm;
+        offer.acceptedCollaterals = acceptedCollaterals;
+        offer.isActive = true;
+        offersByToken[loanToken].push(offerId);
+        
+        emit LoanOfferCreated(offerId, msg.sender, loanToken, amount, interestRate, maxTerm);
+    }
+    
+    /**
+     * @dev Cancel a loan offer and return the unused escrow
+     * @param offerId ID of offer to cancel
+     */
+    function cancelLoanOffer(uint256 offerId) external nonReentrant {
+        LoanOffer storage offer = loanOffers[offerId];
+        require(offer.lender == msg.sender, "Not offer lender");
+        require(offer.isActive, "Offer not active");
+        
+        uint256 remaining = offer.amountAvailable;
+        offer.amountAvailable = 0;
+        offer.isActive = false;
+        
+        if (remaining > 0) {
+            IERC20(offer.loanToken).safeTransfer(msg.sender, remaining);
+        }
+        
+        emit LoanOfferCancelled(offerId, remaining);
+    }
+    
+    /**
+     * @dev Borrow from a posted loan offer
+     * @param offerId ID of offer to take
+     * @param amount Amount to borrow
+     * @param collateralToken Address of collateral token (must be accepted by the offer)
+     * @param collateralAmount Amount of collateral to deposit
+     * @param duration Duration of loan in seconds
+     * @return loanId ID of the new loan
+     */
+    function acceptLoanOffer(
+        uint256 offerId,
+        uint256 amount,
+        address collateralToken,
+        uint256 collateralAmount,
+        uint256 duration
+    ) external nonReentrant returns (uint256 loanId) {
+        LoanOffer storage offer = loanOffers[offerId];
+        require(offer.isActive, "Offer not active");
+        require(amount > 0 && amount <= offer.amountAvailable, "Invalid amount");
+        _requireNotPaused(offer.loanToken, Action.BORROW);
+        require(duration <= offer.maxTerm, "Loan duration too long");
+        require(_isAcceptedCollateral(offer.acceptedCollaterals, collateralToken), "Collateral not accepted");
+        
+        offer.amountAvailable -= amount;
+        if (offer.amountAvailable == 0) {
+            offer.isActive = false;
+        }
+        
+        // Transfer collateral from borrower
+        collateralAmount = _pullTokens(collateralToken, msg.sender, collateralAmount);
+        
+        loanId = _storeLoan(_newP2PLoan(
+            msg.sender,
+            offer.lender,
+            offer.loanToken,
+            amount,
+            collateralToken,
+            collateralAmount,
+            offer.interestRate,
+            duration
+        ));
+        
+        // Release the escrowed loan tokens to borrower
+        IERC20(offer.loanToken).safeTransfer(msg.sender, amount);
+    }
+    
+    /**
+     * @dev Post a loan request, escrowing the collateral
+     * @param loanToken Address of token to borrow
+     * @param amount Amount to borrow
+     * @param collateralToken Address of collateral token
+     * @param collateralAmount Amount of collateral to escrow
+     * @param interestRate Maximum fixed annual interest rate in basis points
+     * @param duration Duration of loan in seconds
+     * @return requestId ID of the new request
+     */
+    function createLoanRequest(
+        address loanToken,
+        uint256 amount,
+        address collateralToken,
+        uint256 collateralAmount,
+        uint256 interestRate,
+        uint256 duration
+    ) 
+        external 
+        nonReentrant 
+        tokenEnabled(loanToken) 
+        returns (uint256 requestId) 
+    {
+        require(amount > 0, "Loan amount must be > 0");
+        require(approvedCollaterals[loanToken][collateralToken], "Collateral not approved");
+        require(interestRate <= MAX_INTEREST_RATE, "Interest rate too high");
+        require(duration <= tokenConfigs[loanToken].maxLoanTerm, "Loan duration too long");
+        
+        // Escrow the collateral
+        collateralAmount = _pullTokens(collateralToken, msg.sender, collateralAmount);
+        _checkCollateralRatio(loanToken, collateralToken, amount, collateralAmount);
+        
+        requestId = requestCounter++;
+        loanRequests[requestId] = LoanRequest({
+            borrower: msg.sender,
+            loanToken: loanToken,
+            amount: amount,
+            collateralToken: collateralToken,
+            collateralAmount: collateralAmount,
+            interestRate: interestRate,
+            duration: duration,
+            isActive: true
+        });
+        requestsByToken[loanToken].push(requestId);
+        
+        emit LoanRequestCreated(
+            requestId,
+            msg.sender,
+            loanToken,
+            amount,
+            collateralToken,
+            collateralAmount,
+            interestRate,
+            duration
+        );
+    }
+    
+    /**
+     * @dev Cancel a loan request and return the escrowed collateral
+     * @param requestId ID of request to cancel
+     */
+    function cancelLoanRequest(uint256 requestId) external nonReentrant {
+        LoanRequest storage request = loanRequests[requestId];
+        require(request.borrower == msg.sender, "Not request borrower");
+        require(request.isActive, "Request not active");
+        
+        request.isActive = false;
+        IERC20(request.collateralToken).safeTransfer(msg.sender, request.collateralAmount);
+        
+        emit LoanRequestCancelled(requestId);
+    }
+    
+    /**
+     * @dev Fund a loan request at its interest rate
+     * @param requestId ID of request to fill
+     * @return loanId ID of the new loan
+     */
+    function fillLoanRequest(uint256 requestId) external nonReentrant returns (uint256 loanId) {
+        LoanRequest storage request = loanRequests[requestId];
+        require(request.isActive, "Request not active");
+        _requireNotPaused(request.loanToken, Action.BORROW);
+        
+        request.isActive = false;
+        
+        loanId = _storeLoan(_newP2PLoan(
+            request.borrower,
+            msg.sender,
+            request.loanToken,
+            request.amount,
+            request.collateralToken,
+            request.collateralAmount,
+            request.interestRate,
+            request.duration
+        ));
+        
+        // Transfer loan tokens from lender to borrower
+        IERC20(request.loanToken).safeTransferFrom(msg.sender, request.borrower, request.amount);
+    }
+    
+    /**
+     * @dev Match a loan request with a compatible offer (callable by anyone)
+     * The loan is taken at the offer's rate, which must not exceed the request's rate.
+     * @param offerId ID of offer
+     * @param requestId ID of request
+     * @return loanId ID of the new loan
+     */
+    function matchLoanOffer(uint256 offerId, uint256 requestId) external nonReentrant returns (uint256 loanId) {
+        LoanOffer storage offer = loanOffers[offerId];
+        LoanRequest storage request = loanRequests[requestId];
+        
+        require(offer.isActive, "Offer not active");
+        require(request.isActive, "Request not active");
+        require(offer.loanToken == request.loanToken, "Token mismatch");
+        _requireNotPaused(request.loanToken, Action.BORROW);
+        require(request.amount <= offer.amountAvailable, "Invalid amount");
+        require(offer.interestRate <= request.interestRate, "Rate mismatch");
+        require(request.duration <= offer.maxTerm, "Loan duration too long");
+        require(_isAcceptedCollateral(offer.acceptedCollaterals, request.collateralToken), "Collateral not accepted");
+        
+        offer.amountAvailable -= request.amount;
+        if (offer.amountAvailable == 0) {
+            offer.isActive = false;
+        }
+        request.isActive = false;
+        
+        loanId = _storeLoan(_newP2PLoan(
+            request.borrower,
+            offer.lender,
+            request.loanToken,
+            request.amount,
+            request.collateralToken,
+            request.collateralAmount,
+            offer.interestRate,
+            request.duration
+        ));
+        
+        // Release the escrowed loan tokens to borrower
+        IERC20(request.loanToken).safeTransfer(request.borrower, request.amount);
+    }
+    
+    /**
+     * @dev Borrow against an off-chain offer signed by the lender
+     * The loan tokens are pulled from the lender's wallet, so the lender must have approved this contract.
+     * @param offer Signed offer terms
+     * @param signature Lender's EIP-712 signature over the offer
+     * @param amount Amount to borrow
+     * @param collateralToken Address of collateral token (must be accepted by the offer)
+     * @param collateralAmount Amount of collateral to deposit
+     * @param duration Duration of loan in seconds
+     * @return loanId ID of the new loan
+     */
+    function acceptSignedLoanOffer(
+        SignedLoanOffer calldata offer,
+        bytes calldata signature,
+        uint256 amount,
+        address collateralToken,
+        uint256 collateralAmount,
+        uint256 duration
+    ) external nonReentrant returns (uint256 loanId) {
+        bytes32 offerHash = _verifySignedOffer(offer, signature);
+        _requireNotPaused(offer.loanToken, Action.BORROW);
+        
+        require(amount > 0 && signedOfferFilled[offerHash] + amount <= offer.amount, "Invalid amount");
+        require(duration <= offer.maxTerm, "Loan duration too long");
+        require(_isAcceptedCollateral(offer.acceptedCollaterals, collateralToken), "Collateral not accepted");
+        
+        signedOfferFilled[offerHash] += amount;
+        
+        // Transfer collateral from borrower
+        collateralAmount = _pullTokens(collateralToken, msg.sender, collateralAmount);
+        
+        loanId = _storeLoan(_newP2PLoan(
+            msg.sender,
+            offer.lender,
+            offer.loanToken,
+            amount,
+            collateralToken,
+            collateralAmount,
+            offer.interestRate,
+            duration
+        ));
+        
+        // Transfer loan tokens from lender to borrower
+        IERC20(offer.loanToken).safeTransferFrom(offer.lender, msg.sender, amount);
+    }
+    
+    /**
+     * @dev Invalidate a signed offer nonce
+     * @param nonce Nonce to cancel
+     */
+    function cancelSignedLoanOffer(uint256 nonce) external {
+        cancelledOfferNonces[msg.sender][nonce] = true;
+        emit SignedLoanOfferCancelled(msg.sender, nonce);
+    }
+    
+    /**
+     * @dev Get the EIP-712 digest of a signed offer
+     * @param offer Offer terms
+     * @return Digest the lender signs
+     */
+    function hashLoanOffer(SignedLoanOffer calldata offer) public view returns (bytes32) {
+        return _hashTypedDataV4(keccak256(abi.encode(
+            LOAN_OFFER_TYPEHASH,
+            offer.lender,
+            offer.loanToken,
+            offer.amount,
+            offer.interestRate,
+            offer.maxTerm,
+            keccak256(abi.encodePacked(offer.acceptedCollaterals)),
+            offer.nonce,
+            offer.deadline
+        )));
+    }
+    
+    /**
+     * @dev Check a signed offer's signature, expiry, nonce and terms
+     * @param offer Offer terms
+     * @param signature Lender's signature
+     * @return offerHash EIP-712 digest of the offer
+     */
+    function _verifySignedOffer(
+        SignedLoanOffer calldata offer,
+        bytes calldata signature
+    ) internal view returns (bytes32 offerHash) {
+        require(block.timestamp <= offer.deadline, "Offer expired");
+        require(!cancelledOfferNonces[offer.lender][offer.nonce], "Offer cancelled");
+        
+        offerHash = hashLoanOffer(offer);
+        require(ECDSA.recover(offerHash, signature) == offer.lender, "Invalid signature");
+        
+        _validateOfferTerms(offer.loanToken, offer.interestRate, offer.maxTerm, offer.acceptedCollaterals);
+    }
+    
+    /**
+     * @dev Validate the terms of an offer against the token configuration
+     */
+    function _validateOfferTerms(
+        address loanToken,
+        uint256 interestRate,
+        uint256 maxTerm,
+        address[] calldata acceptedCollaterals
+    ) internal view {
+        require(tokenConfigs[loanToken].enabled, "Token not enabled");
+        require(interestRate <= MAX_INTEREST_RATE, "Interest rate too high");
+        require(maxTerm > 0 && maxTerm <= tokenConfigs[loanToken].maxLoanTerm, "Loan duration too long");
+        require(acceptedCollaterals.length > 0, "No collateral accepted");
+        
+        for (uint256 i = 0; i < acceptedCollaterals.length; i++) {
+            require(approvedCollaterals[loanToken][acceptedCollaterals[i]], "Collateral not approved");
+        }
+    }
+    
+    /**
+     * @dev Check whether a collateral token is in an offer's accepted list
+     */
+    function _isAcceptedCollateral(address[] memory acceptedCollaterals, address collateralToken) internal pure returns (bool) {
+        for (uint256 i = 0; i < acceptedCollaterals.length; i++) {
+            if (acceptedCollaterals[i] == collateralToken) return true;
+        }
+        return false;
+    }
+    
+    /**
+     * @dev Build a fixed-rate P2P loan starting now
+     */
+    function _newP2PLoan(
+        address borrower,
+        address lender,
+        address loanToken,
+        uint256 amount,
+        address collateralToken,
+        uint256 collateralAmount,
+        uint256 interestRate,
+        uint256 duration
+    ) internal view returns (Loan memory) {
+        return Loan({
+            borrower: borrower,
+            lender: lender,
+            amountPrincipal: amount,
+            scaledDebt: amount, // Fixed-rate index starts at INDEX_SCALE
+            collateralAmount: collateralAmount,
+            collateralToken: collateralToken,
+            loanToken: loanToken,
+            interestRate: interestRate,
+            startTime: block.timestamp,
+            dueTime: block.timestamp + duration,
+            isActive: true,
+            isLiquidated: false
+        });
+    }
+    
+    // ============ CROSS-MARGIN ACCOUNTS ============
+    
+    /**
+     * @dev Deposit collateral into the caller's cross-margin account
+     * @param token Address of collateral token (must have a collateral factor)
+     * @param amount Amount to deposit
+     */
+    function depositAccountCollateral(address token, uint256 amount) external nonReentrant {
+        require(collateralFactors[token] > 0, "Collateral not supported");
+        require(amount > 0, "Amount must be > 0");
+        
+        amount = _pullTokens(token, msg.sender, amount);
+        accountCollateral[msg.sender][token] += amount;
+        accountCollateralTokens[msg.sender].add(token);
+        
+        emit AccountCollateralDeposited(msg.sender, token, amount);
+    }
+    
+    /**
+     * @dev Withdraw collateral from the caller's account, the account must stay healthy
+     * @param token Address of collateral token
+     * @param amount Amount to withdraw
+     */
+    function withdrawAccountCollateral(address token, uint256 amount) 
+        external 
+        nonReentrant 
+        whenNotPaused(token, Action.WITHDRAW) 
+    {
+        require(amount > 0 && amount <= accountCollateral[msg.sender][token], "Invalid amount");
+        
+        accountCollateral[msg.sender][token] -= amount;
+        if (accountCollateral[msg.sender][token] == 0) {
+            accountCollateralTokens[msg.sender].remove(token);
+        }
+        
+        (, uint256 shortfall) = getAccountLiquidity(msg.sender);
+        require(shortfall == 0, "Insufficient account liquidity");
+        
+        IERC20(token).safeTransfer(msg.sender, amount);
+        
+        emit AccountCollateralWithdrawn(msg.sender, token, amount);
+    }
+    
+    /**
+     * @dev Borrow from the pool against the caller's whole account
+     * Account debt follows the pool borrow index and has no due date.
+     * @param loanToken Address of token to borrow
+     * @param amount Amount to borrow
+     */
+    function borrowFromAccount(address loanToken, uint256 amount) 
+        external 
+        nonReentrant 
+        whenNotPaused(loanToken, Action.BORROW) 
+        tokenEnabled(loanToken) 
+    {
+        require(amount > 0, "Loan amount must be > 0");
+        
+        accrueInterest(loanToken);
+        require(amount <= _availableLiquidity(loanToken), "Insufficient liquidity");
+        _checkBorrowCap(loanToken, amount);
         
-        // Update borrowed amount
+        accountScaledBorrows[msg.sender][loanToken] += _toScaledDebtRoundUp(amount, borrowIndex[loanToken]);
+        accountBorrowTokens[msg.sender].add(loanToken);
+        totalLiquidity[loanToken] -= amount;
+        totalBorrowed[loanToken] += amount;
+        
+        (, uint256 shortfall) = getAccountLiquidity(msg.sender);
+        require(shortfall == 0, "Insufficient account liquidity");
+        
+        IERC20(loanToken).safeTransfer(msg.sender, amount);
+        
+        emit AccountBorrow(msg.sender, loanToken, amount);
+    }
+    
+    /**
+     * @dev Repay part or all of the caller's account debt in a token
+     * @param loanToken Address of borrowed token
+     * @param amount Amount to repay (type(uint256).max repays the full debt)
+     */
+    function repayAccountBorrow(address loanToken, uint256 amount) 
+        external 
+        nonReentrant 
+        whenNotPaused(loanToken, Action.REPAY) 
+    {
+        accrueInterest(loanToken);
+        
+        uint256 debt = _toDebtRoundUp(accountScaledBorrows[msg.sender][loanToken], borrowIndex[loanToken]);
+        uint256 amountToRepay = amount >= debt ? debt : amount;
+        require(amountToRepay > 0, "Amount must be > 0");
+        
+        amountToRepay = _pullTokens(loanToken, msg.sender, amountToRepay);
+        _reduceAccountDebt(msg.sender, loanToken, amountToRepay, debt);
+        totalLiquidity[loanToken] += amountToRepay;
+        
+        emit AccountRepay(msg.sender, loanToken, amountToRepay);
+    }
+    
+    /**
+     * @dev Liquidate an account in shortfall by repaying one of its debts for one of its collaterals
+     * Repayment is bounded by the close factor. The liquidator receives collateral worth the repaid
+     * debt plus the collateral's bonus, and LIQUIDATION_PENALTY goes to reserves.
+     * @param account Account to liquidate
+     * @param loanToken Borrowed token to repay
+     * @param collateralToken Collateral token to seize
+     * @param repayAmount Amount of debt to repay (capped at the maximum allowed)
+     */
+    function liquidateAccount(
+        address account,
+        address loanToken,
+        address collateralToken,
+        uint256 repayAmount
+    ) external nonReentrant whenNotPaused(loanToken, Action.LIQUIDATE) {
+        require(account != msg.sender, "Cannot liquidate self");
+        
+        accrueInterest(loanToken);
+        
+        (, uint256 shortfall) = getAccountLiquidity(account);
+        require(shortfall > 0, "Account not liquidatable");
+        
+        uint256 debt = _toDebtRoundUp(accountScaledBorrows[account][loanToken], borrowIndex[loanToken]);
+        uint256 maxRepay = (debt * closeFactor) / BASIS_POINTS;
+        uint256 actualRepay = repayAmount > maxRepay ? maxRepay : repayAmount;
+        require(actualRepay > 0, "Amount must be > 0");
+        
+        actualRepay = _pullTokens(loanToken, msg.sender, actualRepay);
+        
+        (uint256 collateralSeized, uint256 penalty) = _calculateSeizeAmounts(
+            loanToken,
+            collateralToken,
+            actualRepay,
+            accountCollateral[account][collateralToken]
+        );
+        
+        _reduceAccountDebt(account, loanToken, actualRepay, debt);
+        totalLiquidity[loanToken] += actualRepay;
+        
+        accountCollateral[account][collateralToken] -= collateralSeized + penalty;
+        if (accountCollateral[account][collateralToken] == 0) {
+            accountCollateralTokens[account].remove(collateralToken);
+        }
+        if (penalty > 0) {
+            _addReserves(collateralToken, penalty);
+        }
+        
+        IERC20(collateralToken).safeTransfer(msg.sender, collateralSeized);
+        
+        emit AccountLiquidated(account, msg.sender, loanToken, collateralToken, actualRepay, collateralSeized, penalty);
+    }
+    
+    /**
+     * @dev Get the borrowing power left in an account, or how far it is underwater
+     * Collateral is valued at its collateral factor, debt at the current borrow index.
+     * @param account Address of account
+     * @return liquidity Excess of weighted collateral value over debt value (18-decimal USD)
+     * @return shortfall Excess of debt value over weighted collateral value (18-decimal USD)
+     */
+    function getAccountLiquidity(address account) public view returns (uint256 liquidity, uint256 shortfall) {
+        uint256 collateralValue = getAccountCollateralValue(account);
+        uint256 borrowValue = getAccountBorrowValue(account);
+        
+        if (collateralValue >= borrowValue) {
+            liquidity = collateralValue - borrowValue;
+        } else {
+            shortfall = borrowValue - collateralValue;
+        }
+    }
+    
+    /**
+     * @dev Get the collateral value of an account weighted by collateral factors
+     * @param account Address of account
+     * @return value Weighted value (18-decimal USD)
+     */
+    function getAccountCollateralValue(address account) public view returns (uint256 value) {
+        EnumerableSet.AddressSet storage tokens = accountCollateralTokens[account];
+        for (uint256 i = 0; i < tokens.length(); i++) {
+            address token = tokens.at(i);
+            value += (_getTokenValue(token, accountCollateral[account][token]) * collateralFactors[token]) / 
+                     BASIS_POINTS;
+        }
+    }
+    
+    /**
+     * @dev Get the value of all debts of an account
+     * @param account Address of account
+     * @return value Debt value (18-decimal USD)
+     */
+    function getAccountBorrowValue(address account) public view returns (uint256 value) {
+        EnumerableSet.AddressSet storage tokens = accountBorrowTokens[account];
+        for (uint256 i = 0; i < tokens.length(); i++) {
+            address token = tokens.at(i);
+            value += _getTokenValue(token, getAccountBorrowBalance(account, token));
+        }
+    }
+    
+    /**
+     * @dev Get the current debt of an account in one token
+     * @param account Address of account
+     * @param loanToken Address of borrowed token
+     * @return Amount owed including interest to date
+     */
+    function getAccountBorrowBalance(address account, address loanToken) public view returns (uint256) {
+        return _toDebtRoundUp(accountScaledBorrows[account][loanToken], getCurrentBorrowIndex(loanToken));
+    }
+    
+    /**
+     * @dev Get the collateral and borrowed tokens of an account
+     * @param account Address of account
+     * @return collateralTokens Tokens with a collateral balance
+     * @return borrowTokens Tokens with outstanding debt
+     */
+    function getAccountTokens(address account) 
+        external 
+        view 
+        returns (address[] memory collateralTokens, address[] memory borrowTokens) 
+    {
+        return (accountCollateralTokens[account].values(), accountBorrowTokens[account].values());
+    }
+    
+    /**
+     * @dev Reduce an account's debt in one token (call after accrueInterest)
+     */
+    function _reduceAccountDebt(address account, address loanToken, uint256 amount, uint256 debt) internal {
+        if (amount == debt) {
+            accountScaledBorrows[account][loanToken] = 0;
+            accountBorrowTokens[account].remove(loanToken);
+        } else {
+            accountScaledBorrows[account][loanToken] -= (amount * INDEX_SCALE) / borrowIndex[loanToken];
+        }
+        
+        uint256 borrowed = totalBorrowed[loanToken];
+        totalBorrowed[loanToken] = amount >= borrowed ? 0 : borrowed - amount;
+    }
+    
+    // ============ CREDIT DELEGATION ============
+    
+    /**
+     * @dev Let a borrower draw uncollateralized credit from the caller's pool position
+     * Drawn amounts are taken out of the delegator's shares, repayments are deposited back into them.
+     * @param token Address of pool token
+     * @param borrower Address allowed to borrow
+     * @param amount Maximum amount the borrower can draw (replaces any previous line)
+     * @param interestRate Fixed annual interest rate in basis points
+     * @param maxTerm Maximum loan duration in seconds
+     */
+    function approveDelegation(
+        address token,
+        address borrower,
+        uint256 amount,
+        uint256 interestRate,
+        uint256 maxTerm
+    ) external tokenEnabled(token) {
+        require(borrower != address(0) && borrower != msg.sender, "Invalid borrower");
+        require(interestRate <= MAX_INTEREST_RATE, "Interest rate too high");
+        require(maxTerm <= tokenConfigs[token].maxLoanTerm, "Loan duration too long");
+        
+        creditDelegations[msg.sender][token][borrower] = CreditDelegation({
+            amount: amount,
+            interestRate: interestRate,
+            maxTerm: maxTerm
+        });
+        
+        emit CreditDelegated(msg.sender, borrower, token, amount, interestRate, maxTerm);
+    }
+    
+    /**
+     * @dev Borrow against a credit line delegated to the caller
+     * @param token Address of token to borrow
+     * @param delegator Address of the depositor backing the loan
+     * @param amount Amount to borrow
+     * @param duration Duration of loan in seconds
+     * @return loanId ID of the new loan
+     */
+    function borrowWithDelegation(
+        address token,
+        address delegator,
+        uint256 amount,
+        uint256 duration
+    ) 
+        external 
+        nonReentrant 
+        whenNotPaused(token, Action.BORROW) 
+        tokenEnabled(token) 
+        returns (uint256 loanId) 
+    {
+        CreditDelegation storage delegation = creditDelegations[delegator][token][msg.sender];
+        require(amount > 0 && amount <= delegation.amount, "Exceeds delegated credit");
+        require(duration <= delegation.maxTerm, "Loan duration too long");
+        
+        accrueInterest(token);
+        require(amount <= _availableLiquidity(token), "Insufficient liquidity");
+        
+        // Take the loan out of the delegator's position
+        uint256 shares = _convertToSharesRoundUp(token, amount);
+        LenderPosition storage position = lenderPositions[token][delegator];
+        require(shares <= position.shares, "Insufficient delegator shares");
+        
+        position.shares -= shares;
+        position.amountDeposited = amount >= position.amountDeposited ? 0 : position.amountDeposited - amount;
+        totalShares[token] -= shares;
+        totalLiquidity[token] -= amount;
+        delegation.amount -= amount;
+        
+        loanId = _recordLoan(Loan({
+            borrower: msg.sender,
+            lender: delegator,
+            amountPrincipal: amount,
+            scaledDebt: amount, // Fixed-rate index starts at INDEX_SCALE
+            collateralAmount: 0,
+            collateralToken: address(0),
+            loanToken: token,
+            interestRate: delegation.interestRate,
+            startTime: block.timestamp,
+            dueTime: block.timestamp + duration,
+            isActive: true,
+            isLiquidated: false
+        }));
+        isDelegatedLoan[loanId] = true;
+        
+        IERC20(token).safeTransfer(msg.sender, amount);
+    }
+    
+    /**
+     * @dev Close an overdue delegated loan as defaulted, the loss stays with the delegator
+     * @param loanId ID of loan
+     */
+    function markDelegatedDefault(uint256 loanId) external nonReentrant loanActive(loanId) {
+        require(isDelegatedLoan[loanId], "Not a delegated loan");
+        Loan storage loan = loans[loanId];
+        require(msg.sender == loan.lender, "Only delegator");
+        require(block.timestamp > loan.dueTime, "Loan not overdue");
+        
+        uint256 debt = _loanDebt(loan);
+        loan.scaledDebt = 0;
+        loan.isActive = false;
+        activeLoanIds.remove(loanId);
+        delegatedDefaults[loan.lender][loan.loanToken] += debt;
+        
+        emit DelegatedLoanDefaulted(loanId, loan.lender, debt);
+    }
+    
+    /**
+     * @dev Get a credit line
+     * @param delegator Address of delegator
+     * @param token Address of token
+     * @param borrower Address of borrower
+     * @return CreditDelegation struct
+     */
+    function getCreditDelegation(
+        address delegator,
+        address token,
+        address borrower
+    ) external view returns (CreditDelegation memory) {
+        return creditDelegations[delegator][token][borrower];
+    }
+    
+    /**
+     * @dev Deposit a delegated loan repayment into the delegator's pool position
+     * Call after accrueInterest and before any other change to the pool's assets.
+     */
+    function _creditDelegator(address token, address delegator, uint256 amount) internal {
+        uint256 shares = convertToShares(token, amount);
+        
+        LenderPosition storage position = lenderPositions[token][delegator];
+        position.shares += shares;
+        position.amountDeposited += amount;
+        
+        totalShares[token] += shares;
+        totalLiquidity[token] += amount;
+        
+        emit LiquidityAdded(delegator, token, amount, shares);
+    }
+    
+    // ============ FLASH LOANS ============
+    
+    /**
+     * @dev Maximum amount of a token available for a flash loan
+     * @param token Address of token
+     * @return Idle pool liquidity, 0 for tokens that are not enabled
+     */
+    function maxFlashLoan(address token) public view override returns (uint256) {
+        if (!tokenConfigs[token].enabled) return 0;
+        return _availableLiquidity(token);
+    }
+    
+    /**
+     * @dev Fee charged for a flash loan
+     * @param token Address of token
+     * @param amount Amount borrowed
+     * @return Fee amount
+     */
+    function flashFee(address token, uint256 amount) public view override returns (uint256) {
+        require(tokenConfigs[token].enabled, "Token not enabled");
+        return (amount * flashLoanFee) / BASIS_POINTS;
+    }
+    
+    /**
+     * @dev Lend idle pool liquidity for the duration of one call
+     * The receiver must approve amount + fee, which is pulled back after the callback. The fee is added to
+     * pool liquidity so it accrues to lenders.
+     * @param receiver Contract receiving the tokens and the callback
+     * @param token Address of token
+     * @param amount Amount to borrow
+     * @param data Arbitrary data forwarded to the receiver
+     * @return True on success
+     */
+    function flashLoan(
+        IERC3156FlashBorrower receiver,
+        address token,
+        uint256 amount,
+        bytes calldata data
+    ) external override nonReentrant whenNotPaused(token, Action.BORROW) returns (bool) {
+        require(amount > 0, "Amount must be > 0");
+        require(amount <= maxFlashLoan(token), "Insufficient liquidity");
+        
+        uint256 fee = flashFee(token, amount);
+        uint256 balanceBefore = IERC20(token).balanceOf(address(this));
+        
+        accrueInterest(token);
+        
+        IERC20(token).safeTransfer(address(receiver), amount);
+        require(
+            receiver.onFlashLoan(msg.sender, token, amount, fee, data) == FLASH_CALLBACK_SUCCESS,
+            "Invalid flash loan callback"
+        );
+        IERC20(token).safeTransferFrom(address(receiver), address(this), amount + fee);
+        require(IERC20(token).balanceOf(address(this)) >= balanceBefore + fee, "Flash loan not repaid");
+        
+        totalLiquidity[token] += fee;
+        
+        emit FlashLoan(address(receiver), token, amount, fee);
+        return true;
+    }
+    
+    // ============ LIQUIDATION FUNCTIONS ============
+    
+    /**
+     * @dev Liquidate an undercollateralized or overdue loan, fully or partially
+     * Healthy-but-undercollateralized loans can be repaid up to the close factor per call,
+     * overdue loans can be closed in full. The liquidator receives collateral worth the
+     * repaid debt plus the collateral's liquidation bonus, and LIQUIDATION_PENALTY of the
+     * repaid value is seized on top into the collateral token's reserves.
+     * @param loanId ID of loan to liquidate
+     * @param repayAmount Amount of debt to repay (capped at the maximum allowed)
+     */
+    function liquidateLoan(uint256 loanId, uint256 repayAmount) 
+        external 
+        nonReentrant 
+        loanActive(loanId) 
+    {
+        Loan storage loan = loans[loanId];
+        require(!isDelegatedLoan[loanId], "Delegated loan");
+        _requireNotPaused(loan.loanToken, Action.LIQUIDATE);
+        
+        accrueInterest(loan.loanToken);
+        
+        // Check if loan is overdue or undercollateralized
+        bool isOverdue = block.timestamp > loan.dueTime;
+        bool isUndercollateralized = _isUndercollateralized(loanId);
+        
+        require(isOverdue || isUndercollateralized, "Loan not liquidatable");
+        
+        // Bound the repayment by the close factor
+        uint256 debt = _loanDebt(loan);
+        uint256 maxRepay = isOverdue ? debt : (debt * closeFactor) / BASIS_POINTS;
+        uint256 actualRepay = repayAmount > maxRepay ? maxRepay : repayAmount;
+        require(actualRepay > 0, "Amount must be > 0");
+        
+        // Transfer repayment from liquidator, only what arrived counts towards the debt
+        actualRepay = _pullTokens(loan.loanToken, msg.sender, actualRepay);
+        
+        // Collateral worth the repaid debt plus the bonus, and the protocol penalty on top
+        (uint256 collateralSeized, uint256 penalty) = _calculateSeizeAmounts(
+            loan.loanToken,
+            loan.collateralToken,
+            actualRepay,
+            loan.collateralAmount
+        );
+        
+        // Credit the repayment to the pool/lender
         if (loan.lender == address(0)) {
-            totalBorrowed[loan.loanToken] -= loan.amountPrincipal;
+            // Loan was from pool
+            totalLiquidity[loan.loanToken] += actualRepay;
+        } else {
+            // P2P loan
+            IERC20(loan.loanToken).safeTransfer(loan.lender, actualRepay);
+        }
+        
+        // Update loan state
+        _reduceDebt(loan, actualRepay, debt);
+        loan.collateralAmount -= collateralSeized + penalty;
+        
+        // Penalty stays in the contract as reserves of the collateral token
+        if (penalty > 0) {
+            _addReserves(loan.collateralToken, penalty);
+            emit LiquidationPenaltyCollected(loanId, loan.collateralToken, penalty);
+        }
+        
+        // Transfer seized collateral to liquidator
+        IERC20(loan.collateralToken).safeTransfer(msg.sender, collateralSeized);
+        
+        if (actualRepay == debt) {
+            // Debt fully cleared, close the loan and return leftover collateral
+            uint256 leftover = loan.collateralAmount;
+            loan.collateralAmount = 0;
+            loan.isActive = false;
+            loan.isLiquidated = true;
+            activeLoanIds.remove(loanId);
+            
+            if (leftover > 0) {
+                IERC20(loan.collateralToken).safeTransfer(loan.borrower, leftover);
+            }
         }
         
         emit LoanLiquidated(
             loanId,
             msg.sender,
             loan.borrower,
-            repaymentRequired,
-            loan.collateralAmount
+            actualRepay,
+            collateralSeized
         );
     }
     
+    /**
+     * @dev Calculate collateral owed to a liquidator and to reserves for a repayment
+     * @param loanToken Address of repaid token
+     * @param collateralToken Address of seized collateral token
+     * @param repayAmount Amount of debt repaid
+     * @param availableCollateral Collateral backing the debt
+     * @return seized Collateral for the liquidator (repaid value plus bonus)
+     * @return penalty Collateral for reserves, capped at what the liquidator leaves
+     */
+    function _calculateSeizeAmounts(
+        address loanToken,
+        address collateralToken,
+        uint256 repayAmount,
+        uint256 availableCollateral
+    ) internal view returns (uint256 seized, uint256 penalty) {
+        uint256 repayValue = _getTokenValue(loanToken, repayAmount);
+        
+        seized = _getTokenAmount(
+            collateralToken,
+            (repayValue * (BASIS_POINTS + liquidationBonus[collateralToken])) / BASIS_POINTS
+        );
+        require(seized <= availableCollateral, "Insufficient collateral for liquidation");
+        
+        penalty = _getTokenAmount(collateralToken, (repayValue * LIQUIDATION_PENALTY) / BASIS_POINTS);
+        if (penalty > availableCollateral - seized) {
+            penalty = availableCollateral - seized;
+        }
+    }
+    
+    /**
+     * @dev Book tokens already held by the contract as protocol reserves
+     * @param token Address of token
+     * @param amount Amount to add
+     */
+    function _addReserves(address token, uint256 amount) internal {
+        totalLiquidity[token] += amount;
+        totalReserves[token] += amount;
+        isPoolToken[token] = true;
+    }
+    
+    /**
+     * @dev Revert if an action is paused for a token
+     * @param token Address of token
+     * @param action Action to check
+     */
+    function _requireNotPaused(address token, Action action) internal view {
+        require(!actionPaused[token][action], "Action paused");
+    }
+    
+    /**
+     * @dev Revert if new pool borrows would exceed the token's borrow cap (call after accrueInterest)
+     * @param token Address of loan token
+     * @param amount Amount about to be borrowed
+     */
+    function _checkBorrowCap(address token, uint256 amount) internal view {
+        require(borrowCaps[token] == 0 || totalBorrowed[token] + amount <= borrowCaps[token], "Borrow cap reached");
+    }
+    
+    /**
+     * @dev Pull tokens from an account and measure what actually arrived
+     * Fee-on-transfer tokens deliver less than the requested amount, so callers credit the result.
+     * @param token Address of token
+     * @param from Account to pull from
+     * @param amount Amount requested
+     * @return received Amount received by this contract
+     */
+    function _pullTokens(address token, address from, uint256 amount) internal returns (uint256 received) {
+        uint256 balanceBefore = IERC20(token).balanceOf(address(this));
+        IERC20(token).safeTransferFrom(from, address(this), amount);
+        received = IERC20(token).balanceOf(address(this)) - balanceBefore;
+        require(received > 0, "Nothing received");
+    }
+    
+    // ============ BAD DEBT ============
+    
+    /**
+     * @dev Add first-loss capital for a token's pool
+     * @param token Address of token
+     * @param amount Amount to add
+     */
+    function fundInsurance(address token, uint256 amount) external nonReentrant {
+        require(amount > 0, "Amount must be > 0");
+        
+        amount = _pullTokens(token, msg.sender, amount);
+        insuranceFund[token] += amount;
+        isPoolToken[token] = true;
+        
+        emit InsuranceFunded(token, msg.sender, amount);
+    }
+    
+    /**
+     * @dev Close a loan whose collateral is worth less than its debt
+     * For pool loans the caller buys the remaining collateral at its oracle value less writeOffDiscount,
+     * and the payment goes to the pool. The rest of the debt is covered by the insurance fund first, then by reserves,
+     * and what remains is lost by lenders pro-rata through a lower share price. P2P lenders receive
+     * the collateral in place of the debt.
+     * @param loanId ID of loan to write off
+     */
+    function writeOffBadDebt(uint256 loanId) external nonReentrant loanActive(loanId) {
+        require(!isDelegatedLoan[loanId], "Delegated loan");
+        Loan storage loan = loans[loanId];
+        _requireNotPaused(loan.loanToken, Action.LIQUIDATE);
+        
+        accrueInterest(loan.loanToken);
+        
+        uint256 debt = _loanDebt(loan);
+        uint256 collateralValue = _getTokenValue(loan.collateralToken, loan.collateralAmount);
+        require(collateralValue < _getTokenValue(loan.loanToken, debt), "Loan not underwater");
+        
+        _reduceDebt(loan, debt, debt);
+        
+        uint256 collateral = loan.collateralAmount;
+        loan.collateralAmount = 0;
+        loan.isActive = false;
+        loan.isLiquidated = true;
+        activeLoanIds.remove(loanId);
+        
+        uint256 recovered;
+        uint256 fromInsurance = 0;
+        uint256 fromReserves = 0;
+        uint256 socialized;
+        if (loan.lender == address(0)) {
+            recovered = _getTokenAmount(loan.loanToken, _discountWriteOff(collateralValue));
+            if (recovered > 0) {
+                recovered = _pullTokens(loan.loanToken, msg.sender, recovered);
+                totalLiquidity[loan.loanToken] += recovered;
+            }
+            (fromInsurance, fromReserves, socialized) = _absorbBadDebt(loan.loanToken, debt, recovered);
+            
+            if (collateral > 0) {
+                IERC20(loan.collateralToken).safeTransfer(msg.sender, collateral);
+            }
+        } else {
+            recovered = _getTokenAmount(loan.loanToken, collateralValue);
+            socialized = debt - recovered;
+            
+            if (collateral > 0) {
+                IERC20(loan.collateralToken).safeTransfer(loan.lender, collateral);
+            }
+        }
+        
+        emit BadDebtWrittenOff(loanId, loan.loanToken, debt, recovered, fromInsurance, fromReserves, socialized);
+    }
+    
+    /**
+     * @dev Write off all debts of an account whose collateral is worth less than its debt
+     * The caller buys all of the account's collateral at its oracle value less writeOffDiscount, paying
+     * every debt the same share of its amount, and the rest of each debt is absorbed like a pool loan write-off.
+     * @param account Address of account
+     */
+    function writeOffAccountBadDebt(address account) external nonReentrant {
+        EnumerableSet.AddressSet storage collateralTokens = accountCollateralTokens[account];
+        EnumerableSet.AddressSet storage borrowTokens = accountBorrowTokens[account];
+        require(borrowTokens.length() > 0, "No account debt");
+        
+        uint256 collateralValue = 0;
+        for (uint256 i = 0; i < collateralTokens.length(); i++) {
+            address token = collateralTokens.at(i);
+            collateralValue += _getTokenValue(token, accountCollateral[account][token]);
+        }
+        uint256 borrowValue = getAccountBorrowValue(account);
+        require(collateralValue < borrowValue, "Account not underwater");
+        uint256 purchaseValue = _discountWriteOff(collateralValue);
+        
+        while (collateralTokens.length() > 0) {
+            address token = collateralTokens.at(collateralTokens.length() - 1);
+            uint256 amount = accountCollateral[account][token];
+            accountCollateral[account][token] = 0;
+            collateralTokens.remove(token);
+            
+            if (amount > 0) {
+                IERC20(token).safeTransfer(msg.sender, amount);
+            }
+        }
+        
+        while (borrowTokens.length() > 0) {
+            address token = borrowTokens.at(borrowTokens.length() - 1);
+            _requireNotPaused(token, Action.LIQUIDATE);
+            accrueInterest(token);
+            
+            uint256 debt = _toDebtRoundUp(accountScaledBorrows[account][token], borrowIndex[token]);
+            _reduceAccountDebt(account, token, debt, debt);
+            
+            uint256 recovered = (debt * purchaseValue) / borrowValue;
+            if (recovered > 0) {
+                recovered = _pullTokens(token, msg.sender, recovered);
+                totalLiquidity[token] += recovered;
+            }
+            
+            (uint256 fromInsurance, uint256 fromReserves, uint256 socialized) = _absorbBadDebt(token, debt, recovered);
+            emit AccountBadDebtWrittenOff(account, token, debt, recovered, fromInsurance, fromReserves, socialized);
+        }
+    }
+    
+    /**
+     * @dev Price paid for written-off collateral, discounted so keepers are paid to close bad debt
+     * @param collateralValue Oracle value of the collateral (USD, 18 decimals)
+     * @return Value the caller pays (USD, 18 decimals)
+     */
+    function _discountWriteOff(uint256 collateralValue) internal view returns (uint256) {
+        return (collateralValue * (BASIS_POINTS - writeOffDiscount)) / BASIS_POINTS;
+    }
+    
+    /**
+     * @dev Cover written-off pool debt (already removed from totalBorrowed) from insurance, then reserves
+     * @param token Address of loan token
+     * @param debt Debt written off
+     * @param recovered Part of the debt already paid into pool liquidity for the collateral
+     * @return fromInsurance Amount moved from the insurance fund into pool liquidity
+     * @return fromReserves Amount of reserves released to lenders
+     * @return socialized Remaining loss borne by lenders
+     */
+    function _absorbBadDebt(address token, uint256 debt, uint256 recovered) 
+        internal 
+        returns (uint256 fromInsurance, uint256 fromReserves, uint256 socialized) 
+    {
+        uint256 remaining = debt - recovered;
+        fromInsurance = remaining < insuranceFund[token] ? remaining : insuranceFund[token];
+        insuranceFund[token] -= fromInsurance;
+        totalLiquidity[token] += fromInsurance;
+        
+        remaining -= fromInsurance;
+        fromReserves = remaining < totalReserves[token] ? remaining : totalReserves[token];
+        totalReserves[token] -= fromReserves;
+        
+        socialized = remaining - fromReserves;
+        
+        BadDebtStats storage stats = badDebtStats[token];
+        stats.totalWrittenOff += debt;
+        stats.coveredByCollateral += recovered;
+        stats.coveredByInsurance += fromInsurance;
+        stats.coveredByReserves += fromReserves;
+        stats.socializedLoss += socialized;
+    }
+    
     // ============ INTEREST FUNCTIONS ============
     
     /**
-     * @dev Accrue interest for a lender
-     * @param lender Address of lender
-     * @param token Address of token
+     * @dev Apply interest accrued since the last update to the borrow index and total borrows
+     * @param token Address of loan token
      */
-    function _accrueInterest(address lender, address token) internal {
-        LenderPosition storage position = lenderPositions[lender];
-        
-        if (position.amountLent == 0) return;
+    function accrueInterest(address token) public {
+        if (lastAccrualTime[token] == block.timestamp) return;
         
-        uint256 timeElapsed = block.timestamp - position.lastInterestUpdate;
-        if (timeElapsed == 0) return;
+        uint256 storedIndex = borrowIndex[token];
+        uint256 newIndex = getCurrentBorrowIndex(token);
+        lastAccrualTime[token] = block.timestamp;
         
-        // Calculate interest based on utilization rate
-        uint256 utilizationRate = totalBorrowed[token] * BASIS_POINTS / 
-                                 (totalLiquidity[token] + totalBorrowed[token]);
-        
-        // Base rate + utilization premium
-        uint256 currentRate = tokenConfigs[token].baseInterestRate + 
-                            (utilizationRate * 100 / BASIS_POINTS); // 1% premium per 100% utilization
+        if (storedIndex == 0) {
+            // First interaction with this token
+            borrowIndex[token] = newIndex;
+            return;
+        }
+        if (newIndex == storedIndex) return;
         
-        // Calculate interest
-        uint256 interest = (position.amountLent * currentRate * timeElapsed) / 
-                         (BASIS_POINTS * SECONDS_PER_YEAR);
+        uint256 interest = (totalBorrowed[token] * (newIndex - storedIndex)) / storedIndex;
         
-        position.accumulatedInterest += interest;
-        position.lastInterestUpdate = block.timestamp;
+        borrowIndex[token] = newIndex;
+        totalBorrowed[token] += interest;
+        totalReserves[token] += (interest * tokenConfigs[token].reserveFactor) / BASIS_POINTS;
         
-        emit InterestAccrued(lender, token, interest);
+        emit InterestAccrued(token, interest, newIndex, totalBorrowed[token]);
     }
     
     /**
-     * @dev Claim accumulated interest
-     * @param token Address of token
+     * @dev Get the borrow index including interest not yet accrued
+     * @param token Address of loan token
+     * @return Borrow index scaled by INDEX_SCALE
      */
-    function claimInterest(address token) external nonReentrant {
-        _accrueInterest(msg.sender, token);
+    function getCurrentBorrowIndex(address token) public view returns (uint256) {
+        uint256 storedIndex = borrowIndex[token];
+        if (storedIndex == 0) return INDEX_SCALE;
         
-        LenderPosition storage position = lenderPositions[msg.sender];
-        uint256 interest = position.accumulatedInterest;
+        uint256 timeElapsed = block.timestamp - lastAccrualTime[token];
+        if (timeElapsed == 0 || totalBorrowed[token] == 0) return storedIndex;
         
-        require(interest > 0, "No interest to claim");
-        require(interest <= totalLiquidity[token], "Insufficient liquidity");
+        return storedIndex + (storedIndex * getBorrowRate(token) * timeElapsed) / 
+                            (BASIS_POINTS * SECONDS_PER_YEAR);
+    }
+    
+    /**
+     * @dev Get the current debt of a loan (principal + interest to date)
+     * @param loanId ID of loan
+     * @return Amount owed
+     */
+    function getLoanDebt(uint256 loanId) public view returns (uint256) {
+        Loan storage loan = loans[loanId];
+        uint256 index = loan.lender == address(0) ? getCurrentBorrowIndex(loan.loanToken) : _fixedRateIndex(loan);
+        return _toDebtRoundUp(loan.scaledDebt, index);
+    }
+    
+    /**
+     * @dev Get the debt of a loan against the stored index (call after accrueInterest)
+     * @param loan Loan to value
+     * @return Amount owed
+     */
+    function _loanDebt(Loan storage loan) internal view returns (uint256) {
+        return _toDebtRoundUp(loan.scaledDebt, _loanIndex(loan));
+    }
+    
+    /**
+     * @dev Get the index a loan's scaled debt is measured against
+     * @param loan Loan to value
+     * @return Stored pool borrow index for pool loans, fixed-rate index for P2P loans
+     */
+    function _loanIndex(Loan storage loan) internal view returns (uint256) {
+        if (loan.lender == address(0)) return borrowIndex[loan.loanToken];
+        return _fixedRateIndex(loan);
+    }
+    
+    /**
+     * @dev Simple-interest index of a fixed-rate loan since its start
+     * @param loan Loan to value
+     * @return Index scaled by INDEX_SCALE
+     */
+    function _fixedRateIndex(Loan storage loan) internal view returns (uint256) {
+        return INDEX_SCALE + (INDEX_SCALE * loan.interestRate * (block.timestamp - loan.startTime)) / 
+                            (BASIS_POINTS * SECONDS_PER_YEAR);
+    }
+    
+    /**
+     * @dev Reduce a loan's debt and the pool's total borrows
+     * @param loan Loan being repaid
+     * @param amount Amount of debt repaid
+     * @param debt Current debt of the loan
+     */
+    function _reduceDebt(Loan storage loan, uint256 amount, uint256 debt) internal {
+        if (amount == debt) {
+            loan.scaledDebt = 0;
+        } else {
+            // Round the scaled reduction down so partial repayments never overpay the index
+            loan.scaledDebt -= (amount * INDEX_SCALE) / _loanIndex(loan);
+        }
         
-        position.accumulatedInterest = 0;
-        totalLiquidity[token] -= interest;
+        if (loan.lender == address(0)) {
+            // Per-loan rounding can leave totals a few wei apart
+            uint256 borrowed = totalBorrowed[loan.loanToken];
+            totalBorrowed[loan.loanToken] = amount >= borrowed ? 0 : borrowed - amount;
+        }
+    }
+    
+    function _toScaledDebtRoundUp(uint256 amount, uint256 index) internal pure returns (uint256) {
+        return (amount * INDEX_SCALE + index - 1) / index;
+    }
+    
+    function _toDebtRoundUp(uint256 scaledDebt, uint256 index) internal pure returns (uint256) {
+        return (scaledDebt * index + INDEX_SCALE - 1) / INDEX_SCALE;
+    }
+    
+    // ============ SHARE ACCOUNTING ============
+    
+    /**
+     * @dev Get total assets owned by a token pool's lenders
+     * @param token Address of token
+     * @return Idle liquidity plus outstanding borrows minus reserves (including interest not yet accrued)
+     */
+    function totalPoolAssets(address token) public view returns (uint256) {
+        uint256 pendingInterest = 0;
+        uint256 storedIndex = borrowIndex[token];
+        if (storedIndex != 0) {
+            pendingInterest = (totalBorrowed[token] * (getCurrentBorrowIndex(token) - storedIndex)) / storedIndex;
+        }
+        uint256 pendingReserves = (pendingInterest * tokenConfigs[token].reserveFactor) / BASIS_POINTS;
         
-        IERC20(token).transfer(msg.sender, interest);
+        return totalLiquidity[token] + totalBorrowed[token] + pendingInterest - 
+               totalReserves[token] - pendingReserves;
+    }
+    
+    /**
+     * @dev Convert an amount of tokens to pool shares (rounded down)
+     * @param token Address of token
+     * @param amount Amount of tokens
+     * @return Amount of shares
+     */
+    function convertToShares(address token, uint256 amount) public view returns (uint256) {
+        // One virtual share and asset keep the first depositor from inflating the rate
+        return (amount * (totalShares[token] + 1)) / (totalPoolAssets(token) + 1);
+    }
+    
+    /**
+     * @dev Convert pool shares to an amount of tokens (rounded down)
+     * @param token Address of token
+     * @param shares Amount of shares
+     * @return Amount of tokens
+     */
+    function convertToAssets(address token, uint256 shares) public view returns (uint256) {
+        return (shares * (totalPoolAssets(token) + 1)) / (totalShares[token] + 1);
+    }
+    
+    /**
+     * @dev Convert an amount of tokens to pool shares (rounded up)
+     * @param token Address of token
+     * @param amount Amount of tokens
+     * @return Amount of shares
+     */
+    function _convertToSharesRoundUp(address token, uint256 amount) internal view returns (uint256) {
+        uint256 numerator = amount * (totalShares[token] + 1);
+        uint256 denominator = totalPoolAssets(token) + 1;
+        return (numerator + denominator - 1) / denominator;
     }
     
     // ============ VIEW FUNCTIONS ============
@@ -581,7 +2276,7 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
      * @return Utilization rate in basis points
      */
     function getUtilizationRate(address token) public view returns (uint256) {
-        uint256 totalSupply = totalLiquidity[token] + totalBorrowed[token];
+        uint256 totalSupply = totalLiquidity[token] + totalBorrowed[token] - totalReserves[token];
         if (totalSupply == 0) return 0;
         return (totalBorrowed[token] * BASIS_POINTS) / totalSupply;
     }
@@ -592,51 +2287,205 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
      * @return True if undercollateralized
      */
     function _isUndercollateralized(uint256 loanId) internal view returns (bool) {
+        return healthFactor(loanId) < HEALTH_FACTOR_ONE;
+    }
+    
+    /**
+     * @dev Get the health factor of a loan
+     * Collateral value divided by the collateral required to avoid liquidation (debt at the
+     * minimum collateral ratio scaled down by LIQUIDATION_THRESHOLD), scaled by 1e18. Loans opened
+     * at the minimum ratio start above HEALTH_FACTOR_ONE.
+     * @param loanId ID of loan
+     * @return Health factor (below HEALTH_FACTOR_ONE is liquidatable, max uint for loans without debt)
+     */
+    function healthFactor(uint256 loanId) public view returns (uint256) {
         Loan storage loan = loans[loanId];
+        if (isDelegatedLoan[loanId]) return type(uint256).max; // Backed by the delegator, not collateral
         
         uint256 collateralValue = _getTokenValue(loan.collateralToken, loan.collateralAmount);
-        uint256 loanValue = _getTokenValue(loan.loanToken, loan.amountOwed);
+        uint256 loanValue = _getTokenValue(loan.loanToken, getLoanDebt(loanId));
+        
+        uint256 requiredCollateral = (loanValue * tokenConfigs[loan.loanToken].minCollateralRatio * LIQUIDATION_THRESHOLD) / 
+                                    (BASIS_POINTS * BASIS_POINTS);
+        if (requiredCollateral == 0) return type(uint256).max;
+        
+        return (collateralValue * HEALTH_FACTOR_ONE) / requiredCollateral;
+    }
+    
+    /**
+     * @dev Check whether a loan can be liquidated right now
+     * @param loanId ID of loan
+     * @return True if the loan is active and overdue or undercollateralized
+     */
+    function isLiquidatable(uint256 loanId) public view returns (bool) {
+        if (!loans[loanId].isActive || isDelegatedLoan[loanId]) return false;
+        return block.timestamp > loans[loanId].dueTime || _isUndercollateralized(loanId);
+    }
+    
+    /**
+     * @dev Preview the outcome of liquidating a loan with the maximum repayment
+     * Reverts like liquidateLoan when the collateral can't cover the seize amount.
+     * @param loanId ID of loan
+     * @return repayAmount Maximum debt repayable now
+     * @return collateralSeized Collateral sent to the liquidator for that repayment
+     * @return penalty Collateral booked to reserves
+     */
+    function previewLiquidation(uint256 loanId) 
+        external 
+        view 
+        returns (uint256 repayAmount, uint256 collateralSeized, uint256 penalty) 
+    {
+        if (!isLiquidatable(loanId)) return (0, 0, 0);
+        
+        Loan storage loan = loans[loanId];
+        uint256 debt = getLoanDebt(loanId);
+        repayAmount = block.timestamp > loan.dueTime ? debt : (debt * closeFactor) / BASIS_POINTS;
+        
+        (collateralSeized, penalty) = _calculateSeizeAmounts(
+            loan.loanToken,
+            loan.collateralToken,
+            repayAmount,
+            loan.collateralAmount
+        );
+    }
+    
+    /**
+     * @dev Scan a page of active loans for liquidation candidates
+     * @param offset Index in the active loan set to start from
+     * @param limit Maximum number of active loans to scan
+     * @return liquidatable IDs of liquidatable loans in the page
+     * @return nextOffset Offset of the next page (equals getActiveLoanCount() when done)
+     */
+    function getLiquidatableLoans(uint256 offset, uint256 limit) 
+        external 
+        view 
+        returns (uint256[] memory liquidatable, uint256 nextOffset) 
+    {
+        uint256 total = activeLoanIds.length();
+        nextOffset = offset + limit > total ? total : offset + limit;
+        
+        uint256[] memory candidates = new uint256[](nextOffset > offset ? nextOffset - offset : 0);
+        uint256 count = 0;
+        for (uint256 i = offset; i < nextOffset; i++) {
+            uint256 loanId = activeLoanIds.at(i);
+            if (isLiquidatable(loanId)) {
+                candidates[count++] = loanId;
+            }
+        }
         
-        // Apply safety margin (10%)
-        uint256 requiredCollateral = (loanValue * tokenConfigs[loan.loanToken].minCollateralRatio * 11000) / 
-                                    (BASIS_POINTS * 10000);
+        liquidatable = new uint256[](count);
+        for (uint256 i = 0; i < count; i++) {
+            liquidatable[i] = candidates[i];
+        }
+    }
+    
+    /**
+     * @dev Get the number of active loans
+     * @return Number of active loans
+     */
+    function getActiveLoanCount() external view returns (uint256) {
+        return activeLoanIds.length();
+    }
+    
+    /**
+     * @dev Get a page of active loan IDs
+     * @param offset Index in the active loan set to start from
+     * @param limit Maximum number of IDs to return
+     * @return loanIds Active loan IDs
+     */
+    function getActiveLoans(uint256 offset, uint256 limit) external view returns (uint256[] memory loanIds) {
+        uint256 total = activeLoanIds.length();
+        uint256 end = offset + limit > total ? total : offset + limit;
         
-        return collateralValue < requiredCollateral;
+        loanIds = new uint256[](end > offset ? end - offset : 0);
+        for (uint256 i = offset; i < end; i++) {
+            loanIds[i - offset] = activeLoanIds.at(i);
+        }
     }
     
     /**
-     * @dev Calculate dynamic interest rate
+     * @dev Get the current annual borrow rate from the token's rate model
      * @param token Address of token
-     * @param amount Amount to borrow
      * @return Interest rate in basis points
      */
-    function _calculateInterestRate(address token, uint256 amount) internal view returns (uint256) {
-        TokenConfig storage config = tokenConfigs[token];
-        uint256 utilizationBefore = getUtilizationRate(token);
-        
-        // Calculate new utilization if this loan is taken
-        uint256 newBorrowed = totalBorrowed[token] + amount;
-        uint256 newTotal = totalLiquidity[token] + newBorrowed;
-        uint256 utilizationAfter = (newBorrowed * BASIS_POINTS) / newTotal;
+    function getBorrowRate(address token) public view returns (uint256) {
+        address model = tokenConfigs[token].interestRateModel;
+        if (model == address(0)) return 0;
         
-        // Base rate + utilization premium
-        uint256 rate = config.baseInterestRate + 
-                      (utilizationAfter * 200 / BASIS_POINTS); // 2% premium per 100% utilization
+        return IInterestRateModel(model).getBorrowRate(
+            totalLiquidity[token],
+            totalBorrowed[token],
+            totalReserves[token]
+        );
+    }
+    
+    /**
+     * @dev Get the current annual rate earned by lenders, from the same model as the borrow rate
+     * @param token Address of token
+     * @return Interest rate in basis points
+     */
+    function getSupplyRate(address token) public view returns (uint256) {
+        TokenConfig storage config = tokenConfigs[token];
+        if (config.interestRateModel == address(0)) return 0;
         
-        return rate > MAX_INTEREST_RATE ? MAX_INTEREST_RATE : rate;
+        return IInterestRateModel(config.interestRateModel).getSupplyRate(
+            totalLiquidity[token],
+            totalBorrowed[token],
+            totalReserves[token],
+            config.reserveFactor
+        );
     }
     
     /**
-     * @dev Get value of tokens (simplified - in production use oracle)
+     * @dev Get value of tokens from the registered price feed
      * @param token Address of token
      * @param amount Amount of tokens
-     * @return Value in USD (simplified as 1:1 for same token type)
+     * @return Value in USD (18 decimals)
+     */
+    function _getTokenValue(address token, uint256 amount) internal view returns (uint256) {
+        return (amount * getTokenPrice(token)) / (10 ** priceFeeds[token].tokenDecimals);
+    }
+    
+    /**
+     * @dev Idle liquidity minus reserves, which only the treasury can withdraw
+     * @param token Address of token
+     * @return Amount lenders can withdraw and borrowers can borrow
+     */
+    function _availableLiquidity(address token) internal view returns (uint256) {
+        uint256 reserves = totalReserves[token];
+        return totalLiquidity[token] > reserves ? totalLiquidity[token] - reserves : 0;
+    }
+    
+    /**
+     * @dev Convert a USD value to an amount of tokens at the registered price
+     * @param token Address of token
+     * @param value Value in USD (18 decimals)
+     * @return Amount of tokens
+     */
+    function _getTokenAmount(address token, uint256 value) internal view returns (uint256) {
+        return (value * (10 ** priceFeeds[token].tokenDecimals)) / getTokenPrice(token);
+    }
+    
+    /**
+     * @dev Get the validated oracle price of a token
+     * @param token Address of token
+     * @return Price of one whole token in USD (18 decimals)
      */
-    function _getTokenValue(address token, uint256 amount) internal pure returns (uint256) {
-        // In production, use Chainlink oracles or TWAP
-        // This is a simplified version assuming 1 token = 1 USD for demo
-        // Different tokens would have different prices in reality
-        return amount;
+    function getTokenPrice(address token) public view returns (uint256) {
+        PriceFeed storage feed = priceFeeds[token];
+        require(feed.primaryOracle != address(0), "No price feed");
+        
+        uint256 price = IPriceOracle(feed.primaryOracle).getPrice(token);
+        require(price > 0, "Invalid price");
+        
+        // Cross-check against the secondary oracle if configured
+        if (feed.secondaryOracle != address(0)) {
+            uint256 secondaryPrice = IPriceOracle(feed.secondaryOracle).getPrice(token);
+            uint256 deviation = price > secondaryPrice ? price - secondaryPrice : secondaryPrice - price;
+            require(deviation * BASIS_POINTS <= secondaryPrice * feed.maxDeviation, "Price deviation too high");
+        }
+        
+        return price;
     }
     
     // ============ ADMIN FUNCTIONS ============
@@ -647,26 +2496,39 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
      * @param enabled Whether token is enabled
      * @param minCollateralRatio Minimum collateral ratio in basis points
      * @param maxLoanTerm Maximum loan term in seconds
-     * @param baseInterestRate Base interest rate in basis points
+     * @param interestRateModel Address of the interest rate model
+     * @param reserveFactor Share of interest kept as reserves in basis points
      */
     function configureToken(
         address token,
         bool enabled,
         uint256 minCollateralRatio,
         uint256 maxLoanTerm,
-        uint256 baseInterestRate
+        address interestRateModel,
+        uint256 reserveFactor
     ) external onlyOwner {
         require(minCollateralRatio >= 11000, "Collateral ratio too low"); // Min 110%
-        require(baseInterestRate <= MAX_INTEREST_RATE, "Interest rate too high");
+        require(interestRateModel != address(0), "Invalid rate model");
+        require(reserveFactor <= MAX_RESERVE_FACTOR, "Reserve factor too high");
+        // The rate at 100% utilization is the highest the model can charge
+        require(
+            IInterestRateModel(interestRateModel).getBorrowRate(0, 1, 0) <= MAX_INTEREST_RATE,
+            "Interest rate too high"
+        );
+        
+        // Settle interest under the old model before switching
+        accrueInterest(token);
         
         tokenConfigs[token] = TokenConfig({
             enabled: enabled,
             minCollateralRatio: minCollateralRatio,
             maxLoanTerm: maxLoanTerm,
-            baseInterestRate: baseInterestRate
+            interestRateModel: interestRateModel,
+            reserveFactor: reserveFactor
         });
+        isPoolToken[token] = true;
         
-        emit TokenConfigUpdated(token, enabled, minCollateralRatio, maxLoanTerm, baseInterestRate);
+        emit TokenConfigUpdated(token, enabled, minCollateralRatio, maxLoanTerm, interestRateModel, reserveFactor);
     }
     
     /**
@@ -681,16 +2543,182 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
         bool approved
     ) external onlyOwner {
         approvedCollaterals[loanToken][collateralToken] = approved;
+        if (approved) {
+            isPoolToken[collateralToken] = true;
+        }
         emit CollateralApproved(loanToken, collateralToken, approved);
     }
     
     /**
-     * @dev Emergency withdraw tokens (admin only)
+     * @dev Register the price feed of a token
+     * @param token Address of token
+     * @param primaryOracle Oracle used for valuation
+     * @param secondaryOracle Optional oracle to cross-check the primary (0 to disable)
+     * @param maxDeviation Maximum deviation between both oracles in basis points
+     */
+    function setPriceFeed(
+        address token,
+        address primaryOracle,
+        address secondaryOracle,
+        uint256 maxDeviation
+    ) external onlyOwner {
+        require(primaryOracle != address(0), "Invalid oracle");
+        require(maxDeviation <= BASIS_POINTS, "Deviation too high");
+        require(secondaryOracle == address(0) || maxDeviation > 0, "Deviation required");
+        
+        priceFeeds[token] = PriceFeed({
+            primaryOracle: primaryOracle,
+            secondaryOracle: secondaryOracle,
+            maxDeviation: maxDeviation,
+            tokenDecimals: IERC20Metadata(token).decimals()
+        });
+        
+        emit PriceFeedUpdated(token, primaryOracle, secondaryOracle, maxDeviation);
+    }
+    
+    /**
+     * @dev Set the share of a loan's debt that can be repaid in one liquidation
+     * @param newCloseFactor Close factor in basis points
+     */
+    function setCloseFactor(uint256 newCloseFactor) external onlyOwner {
+        require(newCloseFactor >= 500 && newCloseFactor <= BASIS_POINTS, "Invalid close factor"); // 5% - 100%
+        closeFactor = newCloseFactor;
+        emit CloseFactorUpdated(newCloseFactor);
+    }
+    
+    /**
+     * @dev Set the penalty charged on repayments after the due date
+     * @param newLateFee Late fee in basis points
+     */
+    function setLateFee(uint256 newLateFee) external onlyOwner {
+        require(newLateFee <= MAX_LATE_FEE, "Late fee too high");
+        lateFee = newLateFee;
+        emit LateFeeUpdated(newLateFee);
+    }
+    
+    /**
+     * @dev Set the fee charged on flash loans
+     * @param newFlashLoanFee Flash loan fee in basis points
+     */
+    function setFlashLoanFee(uint256 newFlashLoanFee) external onlyOwner {
+        require(newFlashLoanFee <= MAX_FLASH_FEE, "Flash loan fee too high");
+        flashLoanFee = newFlashLoanFee;
+        emit FlashLoanFeeUpdated(newFlashLoanFee);
+    }
+    
+    /**
+     * @dev Set the discount keepers get on collateral bought in bad debt write-offs
+     * @param newWriteOffDiscount Discount in basis points
+     */
+    function setWriteOffDiscount(uint256 newWriteOffDiscount) external onlyOwner {
+        require(newWriteOffDiscount <= MAX_WRITE_OFF_DISCOUNT, "Write-off discount too high");
+        writeOffDiscount = newWriteOffDiscount;
+        emit WriteOffDiscountUpdated(newWriteOffDiscount);
+    }
+    
+    /**
+     * @dev Set the liquidation bonus paid in a collateral token
+     * @param collateralToken Address of collateral token
+     * @param bonus Bonus in basis points
+     */
+    function setLiquidationBonus(address collateralToken, uint256 bonus) external onlyOwner {
+        require(bonus <= MAX_LIQUIDATION_BONUS, "Bonus too high");
+        liquidationBonus[collateralToken] = bonus;
+        emit LiquidationBonusUpdated(collateralToken, bonus);
+    }
+    
+    /**
+     * @dev Set the borrowing power of a collateral token in cross-margin accounts
+     * @param collateralToken Address of collateral token
+     * @param collateralFactor Collateral factor in basis points (0 disables new deposits)
+     */
+    function setCollateralFactor(address collateralToken, uint256 collateralFactor) external onlyOwner {
+        require(collateralFactor <= MAX_COLLATERAL_FACTOR, "Collateral factor too high");
+        collateralFactors[collateralToken] = collateralFactor;
+        isPoolToken[collateralToken] = true;
+        emit CollateralFactorUpdated(collateralToken, collateralFactor);
+    }
+    
+    /**
+     * @dev Set the pause guardian
+     * @param newGuardian Address of guardian (0 to remove)
+     */
+    function setGuardian(address newGuardian) external onlyOwner {
+        guardian = newGuardian;
+        emit GuardianUpdated(newGuardian);
+    }
+    
+    /**
+     * @dev Pause or unpause an action for a token
+     * The guardian can only pause, unpausing is reserved to the owner.
+     * @param token Address of token
+     * @param action Action to update
+     * @param paused Whether the action is paused
+     */
+    function setActionPaused(address token, Action action, bool paused) external {
+        require(msg.sender == owner() || (msg.sender == guardian && paused), "Not authorized");
+        actionPaused[token][action] = paused;
+        emit ActionPaused(token, action, paused);
+    }
+    
+    /**
+     * @dev Set the maximum total pool borrows of a token
+     * @param token Address of token
+     * @param borrowCap Cap in token units (0 = no cap)
+     */
+    function setBorrowCap(address token, uint256 borrowCap) external onlyOwner {
+        borrowCaps[token] = borrowCap;
+        emit BorrowCapUpdated(token, borrowCap);
+    }
+    
+    /**
+     * @dev Set the maximum total pool assets of a token
+     * @param token Address of token
+     * @param supplyCap Cap in token units (0 = no cap)
+     */
+    function setSupplyCap(address token, uint256 supplyCap) external onlyOwner {
+        supplyCaps[token] = supplyCap;
+        emit SupplyCapUpdated(token, supplyCap);
+    }
+    
+    /**
+     * @dev Set the address receiving withdrawn reserves
+     * @param newTreasury Address of treasury
+     */
+    function setTreasury(address newTreasury) external onlyOwner {
+        require(newTreasury != address(0), "Invalid treasury");
+        treasury = newTreasury;
+        emit TreasuryUpdated(newTreasury);
+    }
+    
+    /**
+     * @dev Send reserves to the treasury
+     * @param token Address of token
+     * @param amount Amount of reserves to withdraw
+     */
+    function withdrawReserves(address token, uint256 amount) external onlyOwner nonReentrant {
+        accrueInterest(token);
+        
+        require(amount <= totalReserves[token], "Insufficient reserves");
+        require(amount <= totalLiquidity[token], "Insufficient liquidity");
+        
+        totalReserves[token] -= amount;
+        totalLiquidity[token] -= amount;
+        
+        IERC20(token).safeTransfer(treasury, amount);
+        
+        emit ReservesWithdrawn(token, treasury, amount);
+    }
+    
+    /**
+     * @dev Emergency withdraw tokens sent to the contract by mistake (admin only)
+     * Tokens used by pools or as collateral can't be withdrawn this way.
      * @param token Address of token
      * @param amount Amount to withdraw
      */
     function emergencyWithdraw(address token, uint256 amount) external onlyOwner {
-        IERC20(token).transfer(owner(), amount);
+        require(!isPoolToken[token], "Token is part of pool accounting");
+        IERC20(token).safeTransfer(owner(), amount);
     }
     
     /**
@@ -711,12 +2739,107 @@ contract ERC20TokenLoan is Ownable, ReentrancyGuard {
         return userLoans[user];
     }
     
+    /**
+     * @dev Get the value of a lender's shares
+     * @param token Address of token
+     * @param lender Address of lender
+     * @return Amount of tokens the lender's shares are worth
+     */
+    function balanceOfUnderlying(address token, address lender) external view returns (uint256) {
+        return convertToAssets(token, lenderPositions[token][lender].shares);
+    }
+    
+    /**
+     * @dev Get the exchange rate of a token pool
+     * @param token Address of token
+     * @return Tokens per 1e18 shares
+     */
+    function getExchangeRate(address token) external view returns (uint256) {
+        return convertToAssets(token, 1e18);
+    }
+    
+    /**
+     * @dev Get a loan offer including its accepted collaterals
+     * @param offerId ID of offer
+     * @return LoanOffer struct
+     */
+    function getLoanOffer(uint256 offerId) external view returns (LoanOffer memory) {
+        return loanOffers[offerId];
+    }
+    
+    /**
+     * @dev Get the active offers for a loan token
+     * @param loanToken Address of loan token
+     * @return activeOffers Array of offer IDs
+     */
+    function getActiveLoanOffers(address loanToken) external view returns (uint256[] memory activeOffers) {
+        uint256[] storage offerIds = offersByToken[loanToken];
+        uint256 activeCount = 0;
+        
+        for (uint256 i = 0; i < offerIds.length; i++) {
+            if (loanOffers[offerIds[i]].isActive) activeCount++;
+        }
+        
+        activeOffers = new uint256[](activeCount);
+        uint256 index = 0;
+        for (uint256 i = 0; i < offerIds.length; i++) {
+            if (loanOffers[offerIds[i]].isActive) activeOffers[index++] = offerIds[i];
+        }
+    }
+    
+    /**
+     * @dev Get the active requests for a loan token
+     * @param loanToken Address of loan token
+     * @return activeRequests Array of request IDs
+     */
+    function getActiveLoanRequests(address loanToken) external view returns (uint256[] memory activeRequests) {
+        uint256[] storage requestIds = requestsByToken[loanToken];
+        uint256 activeCount = 0;
+        
+        for (uint256 i = 0; i < requestIds.length; i++) {
+            if (loanRequests[requestIds[i]].isActive) activeCount++;
+        }
+        
+        activeRequests = new uint256[](activeCount);
+        uint256 index = 0;
+        for (uint256 i = 0; i < requestIds.length; i++) {
+            if (loanRequests[requestIds[i]].isActive) activeRequests[index++] = requestIds[i];
+        }
+    }
+    
+    /**
+     * @dev Get the pause state and caps of a token
+     * @param token Address of token
+     * @return paused Pause flag per Action, indexed by the enum value
+     * @return borrowCap Max total pool borrows (0 = no cap)
+     * @return supplyCap Max total pool assets (0 = no cap)
+     */
+    function getMarketLimits(address token) 
+        external 
+        view 
+        returns (bool[5] memory paused, uint256 borrowCap, uint256 supplyCap) 
+    {
+        for (uint256 i = 0; i < 5; i++) {
+            paused[i] = actionPaused[token][Action(i)];
+        }
+        return (paused, borrowCaps[token], supplyCaps[token]);
+    }
+    
+    /**
+     * @dev Get the bad debt written off for a token and how it was absorbed
+     * @param token Address of token
+     * @return BadDebtStats struct
+     */
+    function getBadDebtStats(address token) external view returns (BadDebtStats memory) {
+        return badDebtStats[token];
+    }
+    
     /**
      * @dev Get available liquidity for a token
      * @param token Address of token
-     * @return Available liquidity
+     * @return Idle liquidity lenders and borrowers can take, excluding reserves
      */
     function getAvailableLiquidity(address token) external view returns (uint256) {
-        return totalLiquidity[token];
+        return _availableLiquidity(token);
     }
 }
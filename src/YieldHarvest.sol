@@ -2,24 +2,34 @@
 pragma solidity ^0.8.19;
 
 import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
-import "@openzeppelin/contracts/security/ReentrancyGuard.sol";
+import "@openzeppelin/contracts/token/ERC20/utils/SafeERC20.sol";
+import "@openzeppelin/contracts/utils/ReentrancyGuard.sol";
 import "@openzeppelin/contracts/access/Ownable.sol";
-import "@openzeppelin/contracts/utils/math/SafeMath.sol";
+import "@openzeppelin/contracts/governance/TimelockController.sol";
+import "@openzeppelin/contracts/utils/math/SafeCast.sol";
+import "./boost/BoostCard.sol";
 
 /**
  * @title YieldHarvest - Advanced ERC20 Staking & Yield Farming Protocol
  * @dev Features:
  * - Multiple stake types (Flexible, Locked, Boosted)
  * - Dynamic APR based on pool utilization
+ * - Accumulated-reward-per-share accounting, with lock and boost multipliers as weighted shares
+ * - Rewards paid in a per-pool reward token from a funded reserve, never from staked principal
  * - Referral system with rewards
  * - Auto-compounding interest
- * - NFT-based boost cards
- * - Vesting schedules
+ * - ERC-721 boost cards, held by the contract while they boost a stake
+ * - Multi-token vesting schedules, several per beneficiary, revocable and transferable
  * - Penalty-free early withdrawals with conditions
- * - Governance voting rights for stakers
+ * - Executable governance: staker proposals with on-chain actions, queued through a timelock that owns the contract
+ * - Vote-escrowed voting power that decays with the remaining lock, extendable locks and top-ups
+ * - Voting power checkpoints with delegation, proposals vote at their creation snapshot
+ * - Weekly gauge votes splitting per-reward-token emission budgets across pools
+ * - ERC-4626 auto-compounding vault over FLEXIBLE stakes (see YieldHarvestVault)
+ * - Upgradeable deployment behind a UUPS proxy (see YieldHarvestUpgradeable)
  */
 contract YieldHarvest is ReentrancyGuard, Ownable {
-    using SafeMath for uint256;
+    using SafeERC20 for IERC20;
     
     // ============ ENUMS ============
     enum StakeType {
@@ -36,6 +46,15 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         PLATINUM     // 4: 100% APR boost
     }
     
+    enum ProposalState {
+        Active,      // 0: Voting open
+        Defeated,    // 1: Quorum or majority not reached
+        Succeeded,   // 2: Passed, waiting to be queued
+        Queued,      // 3: Scheduled in the timelock
+        Executed,    // 4: Actions executed
+        Canceled     // 5: Canceled by the creator
+    }
+    
     // ============ STRUCTS ============
     
     /**
@@ -55,6 +74,8 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         BoostCardTier boostTier;
         bool isActive;
         uint256 penaltyPaid; // Track penalties for stats
+        uint256 weightedAmount; // Amount scaled by the lock/boost multiplier, the stake's reward shares
+        uint256 boostCardId;    // Boost card held for this stake (0 = none)
     }
     
     /**
@@ -72,6 +93,12 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         uint256 performanceFee;    // Fee on rewards (basis points)
         uint256 earlyWithdrawFee;  // Fee for early withdrawal (basis points)
         uint256 poolCap;           // Maximum total staked in pool
+        address rewardToken;       // Token rewards are paid in
+        uint256 rewardReserve;     // Funded rewards not yet emitted
+        uint256 accRewardPerShare; // Rewards per weighted share, scaled by ACC_PRECISION
+        uint256 lastRewardTime;    // Last time accRewardPerShare was checkpointed
+        uint256 totalWeightedStake; // Sum of the active stakes' weighted amounts
+        uint256 rewardRate;        // Rewards per second allocated by the gauges
     }
     
     /**
@@ -89,12 +116,45 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
      * @dev Vesting schedule
      */
     struct VestingSchedule {
-        uint256 totalAmount;
+        address beneficiary;
+        address token;          // Token being vested
+        uint256 totalAmount;    // Reduced to the vested amount on revocation
         uint256 claimedAmount;
         uint256 startTime;
         uint256 cliff;          // Cliff period (seconds)
         uint256 duration;       // Total vesting duration (seconds)
         uint256 slicePeriod;    // Time between vesting slices (seconds)
+        bool revocable;
+        bool revoked;
+    }
+    
+    /**
+     * @dev Voting power checkpoint, decaying by `slope` per second from `timestamp`
+     */
+    struct VotePoint {
+        uint128 bias;
+        uint128 slope;
+        uint64 timestamp;
+    }
+    
+    /**
+     * @dev Voting power history of a delegatee (or the total)
+     */
+    struct VoteHistory {
+        VotePoint[] points;
+        mapping(uint256 => uint256) slopeChanges; // week => slope of the locks ending then
+    }
+    
+    /**
+     * @dev Gauge votes and resulting allocations of an epoch
+     */
+    struct GaugeEpoch {
+        uint256 totalVotes;
+        bool finalized;
+        mapping(address => uint256) budgets;     // reward token => emission budget split at finalization
+        mapping(address => uint256) votes;       // pool => votes
+        mapping(address => uint256) allocations; // pool => rewards for the following epoch
+        mapping(address => bool) hasVoted;
     }
     
     // ============ CONSTANTS ============
@@ -104,6 +164,13 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
     uint256 public constant MAX_EARLY_WITHDRAW_FEE = 1000; // 10%
     uint256 public constant MIN_LOCK_PERIOD = 7 days;
     uint256 public constant MAX_LOCK_PERIOD = 365 days;
+    uint256 public constant ACC_PRECISION = 1e18;
+    uint256 public constant MAX_PROPOSAL_ACTIONS = 10;
+    uint256 public constant LOCKED_MULTIPLIER = 15000; // 50% more shares for LOCKED stakes
+    uint256 public constant MAX_VE_BOOST = 5000; // Extra shares for a full MAX_LOCK_PERIOD of remaining lock
+    uint256 public constant WEEK = 7 days; // Lock ends are rounded down to weeks for voting power
+    uint256 public constant VE_PRECISION = 1e9; // Scale of stored lock slopes, keeps small and 6-decimal locks decaying
+    uint256 public constant EPOCH_DURATION = 7 days; // Gauge voting period
     
     // Boost card multipliers (basis points)
     uint256 public constant BRONZE_BOOST = 11000;   // 10%
@@ -122,13 +189,36 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
     mapping(address => uint256[]) public userStakes;
     mapping(address => PoolConfig) public pools;
     mapping(address => ReferralData) public referrals;
-    mapping(address => VestingSchedule) public vestingSchedules;
+    mapping(address => VestingSchedule) private __deprecatedVestingSchedules; // Formerly one schedule per beneficiary, slot kept for proxies
     mapping(address => mapping(address => uint256)) public userPoolStakes; // user => token => amount
     mapping(address => uint256) public userTotalRewards;
     mapping(address => bool) public whitelistedTokens;
-    mapping(address => BoostCardTier) public boostCards; // NFT holders boost tiers
+    mapping(address => BoostCardTier) private __deprecatedBoostCards; // Formerly owner-assigned tiers, slot kept for proxies
     mapping(uint256 => GovernanceProposal) public governanceProposals;
-    mapping(address => uint256) public userVotingPower; // Based on staked amount
+    mapping(address => uint256) private __deprecatedUserVotingPower; // Formerly 1 vote per staked token, slot kept for proxies
+    BoostCard public boostCard; // Collection accepted for BOOSTED stakes
+    uint256 public vestingScheduleCount;
+    mapping(uint256 => VestingSchedule) public vestingSchedules;
+    mapping(address => uint256[]) internal beneficiarySchedules; // beneficiary => schedule IDs
+    TimelockController public governanceTimelock; // Queues passed proposals, expected to own this contract
+    uint256 public quorumBps; // Share of total voting power that must vote on a proposal (basis points)
+    uint256 private __deprecatedTotalVotingPower; // Slot kept for proxies
+    mapping(address => address) internal _delegates; // account => delegatee (unset = self)
+    mapping(address => uint256) private __deprecatedVoteCheckpoints; // Formerly per-block vote checkpoints, slot kept for proxies
+    uint256 private __deprecatedTotalVotingCheckpoints; // Slot kept for proxies
+    mapping(address => VoteHistory) internal _voteHistory; // delegatee => decaying votes
+    VoteHistory internal _totalVoteHistory;
+    mapping(address => uint256) public emissionBudgets; // reward token => rewards split each epoch across the gauges paying it
+    bool public gaugeEmissionsActive;   // Set by the owner, pools then emit at their gauge rate instead of their APR
+    address[] public gaugePools;
+    mapping(address => bool) public isGauge;
+    mapping(uint256 => GaugeEpoch) internal gaugeEpochs;
+    uint256 public ratesEpoch; // Latest finalized epoch whose allocations set the gauge rates
+    mapping(address => uint256[]) internal _openLocks; // user => IDs of stakes whose lock may still carry votes
+    mapping(uint256 => uint256) internal _openLockPositions; // stakeId => index in _openLocks + 1 (0 = not tracked)
+    
+    // Reserved storage so new state can be appended ahead of proxy-only variables
+    uint256[29] private __gap;
     
     // ============ EVENTS ============
     event StakeCreated(
@@ -160,6 +250,7 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
     
     event PoolConfigured(
         address indexed token,
+        address indexed rewardToken,
         uint256 baseAPR,
         uint256 lockPeriod,
         uint256 minStakeAmount,
@@ -167,6 +258,13 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         uint256 poolCap
     );
     
+    event RewardAdded(
+        address indexed token,
+        address indexed rewardToken,
+        uint256 amount,
+        uint256 rewardReserve
+    );
+    
     event ReferralReward(
         address indexed referrer,
         address indexed referredUser,
@@ -174,17 +272,43 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         uint256 rewardAmount
     );
     
-    event BoostCardAssigned(
-        address indexed user,
+    event BoostCardStaked(
+        uint256 indexed stakeId,
+        uint256 indexed cardId,
         BoostCardTier tier
     );
     
+    event BoostCardWithdrawn(
+        uint256 indexed stakeId,
+        uint256 indexed cardId
+    );
+    
+    event BoostCardCollectionUpdated(address indexed boostCard);
+    
     event RewardsCompounded(
         uint256 indexed stakeId,
         address indexed user,
         uint256 compoundedAmount
     );
     
+    event LockExtended(uint256 indexed stakeId, address indexed user, uint256 lockEndTime);
+    
+    event StakeKicked(uint256 indexed stakeId, address indexed caller, uint256 weightedAmount);
+    
+    event StakeIncreased(
+        uint256 indexed stakeId,
+        address indexed user,
+        uint256 amount,
+        uint256 newAmount
+    );
+    
+    event StakeDecreased(
+        uint256 indexed stakeId,
+        address indexed user,
+        uint256 amount,
+        uint256 newAmount
+    );
+    
     event GovernanceProposalCreated(
         uint256 indexed proposalId,
         address indexed creator,
@@ -192,6 +316,28 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         uint256 votingEndTime
     );
     
+    event ProposalQueued(uint256 indexed proposalId, uint256 eta);
+    
+    event ProposalExecuted(uint256 indexed proposalId);
+    
+    event ProposalCanceled(uint256 indexed proposalId);
+    
+    event GovernanceConfigured(address indexed timelock, uint256 quorumBps);
+    
+    event DelegateChanged(address indexed delegator, address indexed fromDelegate, address indexed toDelegate);
+    
+    event DelegateVotesChanged(address indexed delegate, uint256 previousVotes, uint256 newVotes);
+    
+    event GaugeAdded(address indexed token);
+    
+    event GaugeVoted(uint256 indexed epoch, address indexed voter, address indexed token, uint256 votes);
+    
+    event EpochFinalized(uint256 indexed epoch, uint256 totalVotes);
+    
+    event EmissionBudgetUpdated(address indexed rewardToken, uint256 budget);
+    
+    event GaugeEmissionsToggled(bool active);
+    
     event Voted(
         uint256 indexed proposalId,
         address indexed voter,
@@ -200,18 +346,34 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
     );
     
     event VestingCreated(
+        uint256 indexed scheduleId,
         address indexed beneficiary,
+        address indexed token,
         uint256 totalAmount,
         uint256 cliff,
-        uint256 duration
+        uint256 duration,
+        bool revocable
     );
     
     event VestingClaimed(
+        uint256 indexed scheduleId,
         address indexed beneficiary,
         uint256 amount,
         uint256 remaining
     );
     
+    event VestingRevoked(
+        uint256 indexed scheduleId,
+        uint256 vestedAmount,
+        uint256 unvestedReturned
+    );
+    
+    event VestingTransferred(
+        uint256 indexed scheduleId,
+        address indexed previousBeneficiary,
+        address indexed newBeneficiary
+    );
+    
     // Governance Proposal Structure
     struct GovernanceProposal {
         uint256 proposalId;
@@ -223,6 +385,13 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         uint256 againstVotes;
         bool executed;
         mapping(address => bool) hasVoted;
+        address[] targets;
+        uint256[] values;
+        bytes[] calldatas;
+        uint256 quorumVotes;    // Votes needed, fixed at creation
+        uint256 eta;            // Earliest execution time once queued
+        bool canceled;
+        uint256 snapshotTime;   // Voting power is read as of this timestamp
     }
     
     // ============ MODIFIERS ============
@@ -256,7 +425,7 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
     }
     
     // ============ CONSTRUCTOR ============
-    constructor() {
+    constructor() Ownable(msg.sender) {
         totalStakes = 0;
         totalValueLocked = 0;
         totalRewardsDistributed = 0;
@@ -268,7 +437,7 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
      * @dev Create a new stake with optional referral
      * @param token Token to stake
      * @param amount Amount to stake
-     * @param stakeType Type of stake (0: Flexible, 1: Locked, 2: Boosted)
+     * @param stakeType Type of stake (0: Flexible, 1: Locked), BOOSTED stakes use createBoostedStake
      * @param referrer Optional referrer address
      */
     function createStake(
@@ -277,6 +446,33 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         StakeType stakeType,
         address referrer
     ) external nonReentrant poolExists(token) returns (uint256) {
+        require(stakeType != StakeType.BOOSTED, "Boosted stakes need a card");
+        return _createStake(token, amount, stakeType, referrer, 0);
+    }
+    
+    /**
+     * @dev Create a BOOSTED stake, locking a boost card in the contract
+     * @param token Token to stake
+     * @param amount Amount to stake
+     * @param cardId Boost card to lock (approval to this contract required)
+     * @param referrer Optional referrer address
+     */
+    function createBoostedStake(
+        address token,
+        uint256 amount,
+        uint256 cardId,
+        address referrer
+    ) external nonReentrant poolExists(token) returns (uint256) {
+        return _createStake(token, amount, StakeType.BOOSTED, referrer, cardId);
+    }
+    
+    function _createStake(
+        address token,
+        uint256 amount,
+        StakeType stakeType,
+        address referrer,
+        uint256 cardId
+    ) internal returns (uint256) {
         PoolConfig storage pool = pools[token];
         
         // Validate inputs
@@ -284,8 +480,11 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         require(amount <= pool.maxStakeAmount, "Above maximum stake");
         require(pool.totalStaked + amount <= pool.poolCap, "Pool capacity reached");
         
-        // Transfer tokens from user
-        IERC20(token).transferFrom(msg.sender, address(this), amount);
+        // Transfer tokens from user, staking only what arrived
+        amount = _pullTokens(token, msg.sender, amount);
+        
+        // Checkpoint emissions before the pool's shares change
+        _updatePool(token);
         
         // Calculate lock end time
         uint256 lockEndTime = 0;
@@ -293,14 +492,15 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
             lockEndTime = block.timestamp + pool.lockPeriod;
         }
         
-        // Check boost card if applicable
+        // Lock the boost card if applicable
         BoostCardTier boostTier = BoostCardTier.NONE;
         if (stakeType == StakeType.BOOSTED) {
-            boostTier = boostCards[msg.sender];
-            require(boostTier != BoostCardTier.NONE, "No boost card");
+            boostTier = _lockBoostCard(cardId);
         }
         
         // Create stake position
+        uint256 multiplier = _stakeMultiplier(stakeType, boostTier) + _veBoost(amount, lockEndTime);
+        uint256 weightedAmount = (amount * multiplier) / BASIS_POINTS;
         uint256 stakeId = totalStakes++;
         stakes[stakeId] = StakePosition({
             stakeId: stakeId,
@@ -308,14 +508,16 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
             token: token,
             stakeType: stakeType,
             amount: amount,
-            rewardDebt: 0,
+            rewardDebt: (weightedAmount * pool.accRewardPerShare) / ACC_PRECISION,
             startTime: block.timestamp,
             lockEndTime: lockEndTime,
             lastHarvestTime: block.timestamp,
             totalHarvested: 0,
             boostTier: boostTier,
             isActive: true,
-            penaltyPaid: 0
+            penaltyPaid: 0,
+            weightedAmount: weightedAmount,
+            boostCardId: cardId
         });
         
         // Update user stakes
@@ -324,6 +526,7 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         
         // Update pool totals
         pool.totalStaked += amount;
+        pool.totalWeightedStake += weightedAmount;
         totalValueLocked += amount;
         
         // Handle referral
@@ -332,8 +535,14 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         }
         
         // Update voting power
-        _updateVotingPower(msg.sender);
+        _checkpointLock(msg.sender, 0, 0, amount, lockEndTime);
+        if (lockEndTime != 0) {
+            _trackLock(msg.sender, stakeId);
+        }
         
+        if (cardId != 0) {
+            emit BoostCardStaked(stakeId, cardId, boostTier);
+        }
         emit StakeCreated(
             stakeId,
             msg.sender,
@@ -362,46 +571,42 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
     {
         StakePosition storage stake = stakes[stakeId];
         PoolConfig storage pool = pools[stake.token];
+        _updatePool(stake.token);
         
         // Calculate pending rewards
         uint256 pending = _calculateRewards(stakeId);
         require(pending > 0, "No rewards to harvest");
+        require(!compound || pool.rewardToken == stake.token, "Reward token differs from stake");
         
-        // Calculate performance fee
-        uint256 feeAmount = (pending * pool.performanceFee) / BASIS_POINTS;
-        uint256 netReward = pending - feeAmount;
-        
-        // Update stake
-        stake.lastHarvestTime = block.timestamp;
-        stake.totalHarvested += pending;
-        stake.rewardDebt += pending;
-        
-        // Update totals
-        pool.totalRewardsPaid += pending;
-        totalRewardsDistributed += pending;
-        userTotalRewards[msg.sender] += pending;
-        
+        uint256 feeAmount;
         if (compound) {
+            // Calculate performance fee
+            feeAmount = (pending * pool.performanceFee) / BASIS_POINTS;
+            uint256 netReward = pending - feeAmount;
+            require(stake.amount + netReward <= pool.maxStakeAmount, "Above maximum stake");
+            require(pool.totalStaked + netReward <= pool.poolCap, "Pool capacity reached");
+            _recordHarvest(stake, pool, pending);
+            
+            // Transfer fee to fee collector
+            if (feeAmount > 0) {
+                IERC20(pool.rewardToken).safeTransfer(owner(), feeAmount);
+            }
+            
             // Compound rewards back into stake
+            _checkpointLock(msg.sender, stake.amount, stake.lockEndTime, stake.amount + netReward, stake.lockEndTime);
             stake.amount += netReward;
             pool.totalStaked += netReward;
             totalValueLocked += netReward;
+            userPoolStakes[msg.sender][stake.token] += netReward;
             
             emit RewardsCompounded(stakeId, msg.sender, netReward);
         } else {
-            // Transfer net reward to user
-            IERC20(stake.token).transfer(msg.sender, netReward);
-            
-            // Transfer fee to fee collector
-            if (feeAmount > 0) {
-                IERC20(stake.token).transfer(owner(), feeAmount);
-            }
+            feeAmount = _payRewards(stake, pool, pending);
         }
         
-        // Update voting power if compounding
-        if (compound) {
-            _updateVotingPower(msg.sender);
-        }
+        // Settle the stake against its shares, re-weighted for the decayed ve boost
+        _refreshWeight(stake, pool);
+        stake.rewardDebt = (stake.weightedAmount * pool.accRewardPerShare) / ACC_PRECISION;
         
         emit Harvested(stakeId, msg.sender, pending, feeAmount);
     }
@@ -419,6 +624,7 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
     {
         StakePosition storage stake = stakes[stakeId];
         PoolConfig storage pool = pools[stake.token];
+        _updatePool(stake.token);
         
         bool isEarly = false;
         uint256 earlyWithdrawFee = 0;
@@ -432,13 +638,14 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         }
         
         // Calculate pending rewards (no rewards for early withdrawal)
+        uint256 accruedRewards = _calculateRewards(stakeId);
         uint256 pendingRewards = 0;
         if (!isEarly) {
-            pendingRewards = _calculateRewards(stakeId);
+            pendingRewards = accruedRewards;
         }
         
-        // Total amount to transfer
-        uint256 totalTransfer = stake.amount - penaltyAmount + pendingRewards;
+        // Principal to return, rewards are paid separately in the reward token
+        uint256 totalTransfer = stake.amount - penaltyAmount;
         
         // Update stake as inactive
         stake.isActive = false;
@@ -446,8 +653,12 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         
         // Update pool totals
         pool.totalStaked -= stake.amount;
+        pool.totalWeightedStake -= stake.weightedAmount;
         totalValueLocked -= stake.amount;
-        if (pendingRewards > 0) {
+        if (isEarly) {
+            // Forfeited rewards go back to the reserve for the remaining stakers
+            pool.rewardReserve += accruedRewards;
+        } else if (pendingRewards > 0) {
             pool.totalRewardsPaid += pendingRewards;
             totalRewardsDistributed += pendingRewards;
             userTotalRewards[msg.sender] += pendingRewards;
@@ -457,18 +668,24 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         userPoolStakes[msg.sender][stake.token] -= stake.amount;
         
         // Transfer tokens
-        require(
-            IERC20(stake.token).transfer(msg.sender, totalTransfer),
-            "Transfer failed"
-        );
+        IERC20(stake.token).safeTransfer(msg.sender, totalTransfer);
+        if (pendingRewards > 0) {
+            IERC20(pool.rewardToken).safeTransfer(msg.sender, pendingRewards);
+        }
         
         // Transfer penalty to fee collector
         if (penaltyAmount > 0) {
-            IERC20(stake.token).transfer(owner(), penaltyAmount);
+            IERC20(stake.token).safeTransfer(owner(), penaltyAmount);
+        }
+        
+        // Return the boost card
+        if (stake.boostCardId != 0) {
+            _releaseBoostCard(stakeId);
         }
         
         // Update voting power
-        _updateVotingPower(msg.sender);
+        _checkpointLock(msg.sender, stake.amount, stake.lockEndTime, 0, 0);
+        _untrackLock(msg.sender, stakeId);
         
         emit Unstaked(
             stakeId,
@@ -480,6 +697,40 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         );
     }
     
+    /**
+     * @dev Withdraw part of a FLEXIBLE stake, keeping the position open
+     * Pending rewards are paid out before the stake is re-weighted.
+     * @param stakeId ID of the stake
+     * @param amount Amount to withdraw, the rest must stay above the pool minimum
+     */
+    function withdrawFromStake(uint256 stakeId, uint256 amount) 
+        external 
+        nonReentrant 
+        stakeExists(stakeId) 
+        stakeActive(stakeId) 
+        onlyStakeOwner(stakeId) 
+    {
+        StakePosition storage stake = stakes[stakeId];
+        PoolConfig storage pool = pools[stake.token];
+        require(stake.stakeType == StakeType.FLEXIBLE, "Not a flexible stake");
+        require(amount > 0 && amount < stake.amount, "Invalid amount");
+        require(stake.amount - amount >= pool.minStakeAmount, "Below minimum stake");
+        
+        // Checkpoint emissions before utilization changes
+        _updatePool(stake.token);
+        
+        _checkpointLock(msg.sender, stake.amount, stake.lockEndTime, stake.amount - amount, stake.lockEndTime);
+        stake.amount -= amount;
+        pool.totalStaked -= amount;
+        totalValueLocked -= amount;
+        userPoolStakes[msg.sender][stake.token] -= amount;
+        _reweightStake(stakeId, stake.boostTier);
+        
+        IERC20(stake.token).safeTransfer(msg.sender, amount);
+        
+        emit StakeDecreased(stakeId, msg.sender, amount, stake.amount);
+    }
+    
     // ============ REWARD CALCULATION ============
     
     /**
@@ -502,48 +753,135 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         
         PoolConfig storage pool = pools[stake.token];
         
-        uint256 timeStaked = block.timestamp - stake.lastHarvestTime;
-        if (timeStaked == 0) return 0;
-        
-        // Calculate base APR with dynamic adjustments
-        uint256 effectiveAPR = _getEffectiveAPR(pool, stake);
+        // Include emissions since the last checkpoint
+        uint256 accRewardPerShare = pool.accRewardPerShare;
+        if (block.timestamp > pool.lastRewardTime && pool.totalWeightedStake > 0) {
+            accRewardPerShare += (_pendingPoolRewards(pool) * ACC_PRECISION) / pool.totalWeightedStake;
+        }
         
-        // Calculate rewards
-        uint256 rewards = (stake.amount * effectiveAPR * timeStaked) / 
-                         (BASIS_POINTS * SECONDS_PER_YEAR);
+        return (stake.weightedAmount * accRewardPerShare) / ACC_PRECISION - stake.rewardDebt;
+    }
+    
+    /**
+     * @dev Record a harvest of `pending` rewards against a stake and the totals
+     */
+    function _recordHarvest(StakePosition storage stake, PoolConfig storage pool, uint256 pending) internal {
+        stake.lastHarvestTime = block.timestamp;
+        stake.totalHarvested += pending;
         
-        return rewards;
+        pool.totalRewardsPaid += pending;
+        totalRewardsDistributed += pending;
+        userTotalRewards[stake.user] += pending;
     }
     
     /**
-     * @dev Get effective APR for a stake
-     * @param pool Pool configuration
-     * @param stake Stake position
-     * @return Effective APR in basis points
+     * @dev Pay out harvested rewards in the reward token, net of the performance fee
+     * @return feeAmount Performance fee sent to the fee collector
      */
-    function _getEffectiveAPR(PoolConfig storage pool, StakePosition storage stake) 
+    function _payRewards(StakePosition storage stake, PoolConfig storage pool, uint256 pending) 
         internal 
-        view 
-        returns (uint256) 
+        returns (uint256 feeAmount) 
     {
-        uint256 baseAPR = pool.baseAPR;
+        feeAmount = (pending * pool.performanceFee) / BASIS_POINTS;
+        _recordHarvest(stake, pool, pending);
         
-        // Apply stake type multiplier
-        if (stake.stakeType == StakeType.LOCKED) {
-            baseAPR = (baseAPR * 15000) / BASIS_POINTS; // 50% boost
-        } else if (stake.stakeType == StakeType.BOOSTED) {
-            // Apply NFT boost
-            uint256 boostMultiplier = _getBoostMultiplier(stake.boostTier);
-            baseAPR = (baseAPR * boostMultiplier) / BASIS_POINTS;
+        // Transfer net reward to user
+        IERC20(pool.rewardToken).safeTransfer(stake.user, pending - feeAmount);
+        
+        // Transfer fee to fee collector
+        if (feeAmount > 0) {
+            IERC20(pool.rewardToken).safeTransfer(owner(), feeAmount);
+        }
+    }
+    
+    /**
+     * @dev Checkpoint a pool's accRewardPerShare, moving emitted rewards out of the reserve
+     * Must run before any change to the pool's weighted stake or APR.
+     * @param token Staked token of the pool
+     */
+    function _updatePool(address token) internal {
+        PoolConfig storage pool = pools[token];
+        if (block.timestamp <= pool.lastRewardTime) return;
+        
+        if (pool.totalWeightedStake > 0) {
+            uint256 rewards = _pendingPoolRewards(pool);
+            if (rewards > 0) {
+                pool.rewardReserve -= rewards;
+                pool.accRewardPerShare += (rewards * ACC_PRECISION) / pool.totalWeightedStake;
+            }
         }
         
-        // Apply pool utilization multiplier (higher utilization = higher APR)
+        pool.lastRewardTime = block.timestamp;
+    }
+    
+    /**
+     * @dev Rewards emitted since the last checkpoint, capped by the funded reserve
+     * Emission stops once the reserve is exhausted.
+     */
+    function _pendingPoolRewards(PoolConfig storage pool) internal view returns (uint256 rewards) {
+        rewards = _emission(pool, block.timestamp - pool.lastRewardTime);
+        
+        if (rewards > pool.rewardReserve) {
+            rewards = pool.rewardReserve;
+        }
+    }
+    
+    /**
+     * @dev Uncapped rewards a pool emits over a duration
+     * The gauge rate once gauge emissions are active, otherwise the APR on the weighted stake.
+     */
+    function _emission(PoolConfig storage pool, uint256 duration) internal view returns (uint256) {
+        if (gaugeEmissionsActive) {
+            return pool.rewardRate * duration;
+        }
+        return (pool.totalWeightedStake * _applyUtilization(pool, pool.baseAPR) * duration) /
+               (BASIS_POINTS * SECONDS_PER_YEAR);
+    }
+    
+    /**
+     * @dev Apply the pool utilization multiplier (higher utilization = higher APR)
+     */
+    function _applyUtilization(PoolConfig storage pool, uint256 apr) internal view returns (uint256) {
         uint256 utilization = (pool.totalStaked * BASIS_POINTS) / pool.poolCap;
         if (utilization > 8000) { // >80% utilization
-            baseAPR = (baseAPR * (10000 + (utilization - 8000) / 2)) / BASIS_POINTS;
+            apr = (apr * (10000 + (utilization - 8000) / 2)) / BASIS_POINTS;
         }
+        return apr;
+    }
+    
+    /**
+     * @dev Share multiplier for a stake type (basis points)
+     * @param stakeType Type of stake
+     * @param boostTier Boost card tier, used by BOOSTED stakes
+     */
+    function _stakeMultiplier(StakeType stakeType, BoostCardTier boostTier) internal pure returns (uint256) {
+        if (stakeType == StakeType.LOCKED) return LOCKED_MULTIPLIER;
+        if (stakeType == StakeType.BOOSTED) return _getBoostMultiplier(boostTier);
+        return BASIS_POINTS;
+    }
+    
+    /**
+     * @dev Extra share multiplier from a lock's decayed voting power (basis points)
+     * Reaches MAX_VE_BOOST with a full MAX_LOCK_PERIOD left and is re-read whenever the stake is
+     * re-weighted, which anyone can trigger through kick.
+     * @param amount Staked amount
+     * @param lockEndTime Unlock time of the stake (0 for FLEXIBLE stakes)
+     */
+    function _veBoost(uint256 amount, uint256 lockEndTime) internal view returns (uint256) {
+        if (amount == 0) return 0;
+        return (_lockedVotes(amount, lockEndTime, block.timestamp) * MAX_VE_BOOST) / amount;
+    }
+    
+    /**
+     * @dev Re-weight a stake's shares at its current multiplier
+     * Callers settle pending rewards first and reset the reward debt afterwards.
+     */
+    function _refreshWeight(StakePosition storage stake, PoolConfig storage pool) internal {
+        uint256 multiplier = _stakeMultiplier(stake.stakeType, stake.boostTier) + _veBoost(stake.amount, stake.lockEndTime);
+        uint256 newWeight = (stake.amount * multiplier) / BASIS_POINTS;
         
-        return baseAPR;
+        pool.totalWeightedStake = pool.totalWeightedStake - stake.weightedAmount + newWeight;
+        stake.weightedAmount = newWeight;
     }
     
     function _getBoostMultiplier(BoostCardTier tier) internal pure returns (uint256) {
@@ -554,146 +892,388 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         return BASIS_POINTS; // 100%
     }
     
-    // ============ REFERRAL SYSTEM ============
+    // ============ BOOST CARDS ============
     
-    function _processReferral(address referrer, address referredUser, uint256 stakeId, uint256 amount) internal {
-        ReferralData storage refData = referrals[referrer];
-        
-        if (refData.referrer == address(0)) {
-            refData.referrer = referrer;
-        }
-        
-        refData.totalReferred += 1;
-        refData.referredStakes.push(stakeId);
+    /**
+     * @dev Stake a boost card into a BOOSTED position that has none
+     * Pending rewards are paid out before the boost applies.
+     * @param stakeId ID of the stake
+     * @param cardId Boost card to lock (approval to this contract required)
+     */
+    function attachBoostCard(uint256 stakeId, uint256 cardId) 
+        external 
+        nonReentrant 
+        stakeExists(stakeId) 
+        stakeActive(stakeId) 
+        onlyStakeOwner(stakeId) 
+    {
+        StakePosition storage stake = stakes[stakeId];
+        require(stake.stakeType == StakeType.BOOSTED, "Not a boosted stake");
+        require(stake.boostCardId == 0, "Boost card already staked");
         
-        // Calculate referral reward (1% of stake amount)
-        uint256 rewardAmount = (amount * 100) / BASIS_POINTS;
+        BoostCardTier tier = _lockBoostCard(cardId);
+        stake.boostCardId = cardId;
+        _reweightStake(stakeId, tier);
         
-        // Transfer reward if contract has balance
-        StakePosition storage stake = stakes[stakeId];
-        if (IERC20(stake.token).balanceOf(address(this)) >= rewardAmount) {
-            IERC20(stake.token).transfer(referrer, rewardAmount);
-            refData.referralRewards += rewardAmount;
-            
-            emit ReferralReward(referrer, referredUser, stakeId, rewardAmount);
-        }
+        emit BoostCardStaked(stakeId, cardId, tier);
     }
     
     /**
-     * @dev Get referral stats for a user
+     * @dev Withdraw the boost card from a stake, removing its boost
+     * Pending rewards are paid out at the boosted rate first. The stake stays locked.
+     * @param stakeId ID of the stake
      */
-    function getReferralStats(address user) 
+    function withdrawBoostCard(uint256 stakeId) 
         external 
-        view 
-        returns (
-            uint256 totalReferred,
-            uint256 totalRewards,
-            uint256[] memory referredStakes
-        ) 
+        nonReentrant 
+        stakeExists(stakeId) 
+        stakeActive(stakeId) 
+        onlyStakeOwner(stakeId) 
     {
-        ReferralData storage refData = referrals[user];
-        return (
-            refData.totalReferred,
-            refData.referralRewards,
-            refData.referredStakes
-        );
+        require(stakes[stakeId].boostCardId != 0, "No boost card staked");
+        
+        _reweightStake(stakeId, BoostCardTier.NONE);
+        _releaseBoostCard(stakeId);
     }
     
-    // ============ VESTING SYSTEM ============
-    
     /**
-     * @dev Create vesting schedule for team/advisors
-     * @param beneficiary Address to vest tokens to
-     * @param amount Total amount to vest
-     * @param cliffDuration Cliff period in seconds
-     * @param vestingDuration Total vesting duration in seconds
-     * @param slicePeriod Seconds between vesting slices
+     * @dev Take custody of a boost card from the caller
+     * @return tier Tier of the card
      */
-    function createVestingSchedule(
-        address beneficiary,
-        address token,
-        uint256 amount,
-        uint256 cliffDuration,
-        uint256 vestingDuration,
-        uint256 slicePeriod
-    ) external onlyOwner {
-        require(beneficiary != address(0), "Invalid beneficiary");
-        require(amount > 0, "Amount must be > 0");
-        require(cliffDuration <= vestingDuration, "Cliff > duration");
-        require(slicePeriod > 0, "Slice period must be > 0");
-        
-        // Transfer tokens to contract
-        IERC20(token).transferFrom(msg.sender, address(this), amount);
+    function _lockBoostCard(uint256 cardId) internal returns (BoostCardTier tier) {
+        require(address(boostCard) != address(0), "Boost cards not enabled");
         
-        vestingSchedules[beneficiary] = VestingSchedule({
-            totalAmount: amount,
-            claimedAmount: 0,
-            startTime: block.timestamp,
-            cliff: cliffDuration,
-            duration: vestingDuration,
-            slicePeriod: slicePeriod
-        });
+        tier = BoostCardTier(boostCard.tierOf(cardId));
+        require(tier != BoostCardTier.NONE, "No boost card");
         
-        emit VestingCreated(beneficiary, amount, cliffDuration, vestingDuration);
+        boostCard.transferFrom(msg.sender, address(this), cardId);
     }
     
     /**
-     * @dev Claim vested tokens
+     * @dev Return a stake's boost card to the stake owner
      */
-    function claimVestedTokens() external nonReentrant {
-        VestingSchedule storage schedule = vestingSchedules[msg.sender];
-        require(schedule.totalAmount > 0, "No vesting schedule");
-        
-        uint256 claimable = _calculateVestedAmount(msg.sender);
-        require(claimable > 0, "No tokens to claim");
-        
-        // Update claimed amount
-        schedule.claimedAmount += claimable;
+    function _releaseBoostCard(uint256 stakeId) internal {
+        StakePosition storage stake = stakes[stakeId];
+        uint256 cardId = stake.boostCardId;
+        stake.boostCardId = 0;
         
-        // Transfer tokens
-        // Note: Token address should be stored in a mapping for production
-        // For simplicity, using a known token address
-        IERC20(address(this)).transfer(msg.sender, claimable);
+        boostCard.transferFrom(address(this), stake.user, cardId);
         
-        emit VestingClaimed(
-            msg.sender,
-            claimable,
-            schedule.totalAmount - schedule.claimedAmount
-        );
+        emit BoostCardWithdrawn(stakeId, cardId);
     }
     
-    function _calculateVestedAmount(address beneficiary) internal view returns (uint256) {
-        VestingSchedule storage schedule = vestingSchedules[beneficiary];
+    /**
+     * @dev Settle a stake's rewards and re-weight its shares for a new boost tier
+     */
+    function _reweightStake(uint256 stakeId, BoostCardTier newTier) internal {
+        StakePosition storage stake = stakes[stakeId];
+        PoolConfig storage pool = pools[stake.token];
+        _updatePool(stake.token);
         
-        if (block.timestamp < schedule.startTime + schedule.cliff) {
+        uint256 pending = _calculateRewards(stakeId);
+        if (pending > 0) {
+            uint256 feeAmount = _payRewards(stake, pool, pending);
+            emit Harvested(stakeId, stake.user, pending, feeAmount);
+        }
+        
+        stake.boostTier = newTier;
+        _refreshWeight(stake, pool);
+        stake.rewardDebt = (stake.weightedAmount * pool.accRewardPerShare) / ACC_PRECISION;
+    }
+    
+    // ============ VOTE ESCROW ============
+    
+    /**
+     * @dev Extend the lock of a LOCKED or BOOSTED stake, raising its voting power and ve boost
+     * Pending rewards are paid out before the stake is re-weighted.
+     * @param stakeId ID of the stake
+     * @param newLockEndTime New unlock time, at most MAX_LOCK_PERIOD from now
+     */
+    function extendLock(uint256 stakeId, uint256 newLockEndTime) 
+        external 
+        nonReentrant 
+        stakeExists(stakeId) 
+        stakeActive(stakeId) 
+        onlyStakeOwner(stakeId) 
+    {
+        StakePosition storage stake = stakes[stakeId];
+        require(stake.stakeType != StakeType.FLEXIBLE, "Not a locked stake");
+        require(newLockEndTime > stake.lockEndTime && newLockEndTime > block.timestamp, "Lock not extended");
+        require(newLockEndTime <= block.timestamp + MAX_LOCK_PERIOD, "Lock too long");
+        
+        _checkpointLock(msg.sender, stake.amount, stake.lockEndTime, stake.amount, newLockEndTime);
+        _trackLock(msg.sender, stakeId);
+        stake.lockEndTime = newLockEndTime;
+        _reweightStake(stakeId, stake.boostTier);
+        
+        emit LockExtended(stakeId, msg.sender, newLockEndTime);
+    }
+    
+    /**
+     * @dev Add tokens to a stake, keeping its unlock time (locked stakes must not have expired)
+     * Pending rewards are paid out before the stake is re-weighted.
+     * @param stakeId ID of the stake
+     * @param amount Amount to add
+     */
+    function increaseStakeAmount(uint256 stakeId, uint256 amount) 
+        external 
+        nonReentrant 
+        stakeExists(stakeId) 
+        stakeActive(stakeId) 
+        onlyStakeOwner(stakeId) 
+    {
+        StakePosition storage stake = stakes[stakeId];
+        PoolConfig storage pool = pools[stake.token];
+        require(stake.stakeType == StakeType.FLEXIBLE || block.timestamp < stake.lockEndTime, "Lock expired");
+        require(stake.amount + amount <= pool.maxStakeAmount, "Above maximum stake");
+        require(pool.totalStaked + amount <= pool.poolCap, "Pool capacity reached");
+        
+        amount = _pullTokens(stake.token, msg.sender, amount);
+        
+        // Checkpoint emissions before utilization changes
+        _updatePool(stake.token);
+        
+        _checkpointLock(msg.sender, stake.amount, stake.lockEndTime, stake.amount + amount, stake.lockEndTime);
+        stake.amount += amount;
+        pool.totalStaked += amount;
+        totalValueLocked += amount;
+        userPoolStakes[msg.sender][stake.token] += amount;
+        _reweightStake(stakeId, stake.boostTier);
+        
+        emit StakeIncreased(stakeId, msg.sender, amount, stake.amount);
+    }
+    
+    /**
+     * @dev Re-weight any stake at its decayed ve boost, paying its pending rewards to the owner
+     * Permissionless so idle locks don't keep the boost they had when last touched.
+     * @param stakeId ID of the stake
+     */
+    function kick(uint256 stakeId) external nonReentrant stakeExists(stakeId) stakeActive(stakeId) {
+        StakePosition storage stake = stakes[stakeId];
+        _reweightStake(stakeId, stake.boostTier);
+        
+        emit StakeKicked(stakeId, msg.sender, stake.weightedAmount);
+    }
+    
+    // ============ REFERRAL SYSTEM ============
+    
+    function _processReferral(address referrer, address referredUser, uint256 stakeId, uint256 amount) internal {
+        ReferralData storage refData = referrals[referrer];
+        
+        if (refData.referrer == address(0)) {
+            refData.referrer = referrer;
+        }
+        
+        refData.totalReferred += 1;
+        refData.referredStakes.push(stakeId);
+        
+        // Calculate referral reward (1% of stake amount)
+        uint256 rewardAmount = (amount * 100) / BASIS_POINTS;
+        
+        // Pay the reward from the pool's funded reserve if it can cover it
+        PoolConfig storage pool = pools[stakes[stakeId].token];
+        if (rewardAmount > 0 && pool.rewardReserve >= rewardAmount) {
+            pool.rewardReserve -= rewardAmount;
+            IERC20(pool.rewardToken).safeTransfer(referrer, rewardAmount);
+            refData.referralRewards += rewardAmount;
+            
+            emit ReferralReward(referrer, referredUser, stakeId, rewardAmount);
+        }
+    }
+    
+    /**
+     * @dev Get referral stats for a user
+     */
+    function getReferralStats(address user) 
+        external 
+        view 
+        returns (
+            uint256 totalReferred,
+            uint256 totalRewards,
+            uint256[] memory referredStakes
+        ) 
+    {
+        ReferralData storage refData = referrals[user];
+        return (
+            refData.totalReferred,
+            refData.referralRewards,
+            refData.referredStakes
+        );
+    }
+    
+    // ============ VESTING SYSTEM ============
+    
+    /**
+     * @dev Create vesting schedule for team/advisors
+     * @param beneficiary Address to vest tokens to
+     * @param token Token to vest
+     * @param amount Total amount to vest
+     * @param cliffDuration Cliff period in seconds
+     * @param vestingDuration Total vesting duration in seconds
+     * @param slicePeriod Seconds between vesting slices
+     * @param revocable Whether the owner can revoke the unvested part
+     * @return scheduleId ID of the new schedule
+     */
+    function createVestingSchedule(
+        address beneficiary,
+        address token,
+        uint256 amount,
+        uint256 cliffDuration,
+        uint256 vestingDuration,
+        uint256 slicePeriod,
+        bool revocable
+    ) external onlyOwner returns (uint256 scheduleId) {
+        require(beneficiary != address(0), "Invalid beneficiary");
+        require(amount > 0, "Amount must be > 0");
+        require(cliffDuration <= vestingDuration, "Cliff > duration");
+        require(slicePeriod > 0, "Slice period must be > 0");
+        require(slicePeriod <= vestingDuration, "Slice period > duration");
+        
+        // Transfer tokens to contract
+        amount = _pullTokens(token, msg.sender, amount);
+        
+        scheduleId = vestingScheduleCount++;
+        vestingSchedules[scheduleId] = VestingSchedule({
+            beneficiary: beneficiary,
+            token: token,
+            totalAmount: amount,
+            claimedAmount: 0,
+            startTime: block.timestamp,
+            cliff: cliffDuration,
+            duration: vestingDuration,
+            slicePeriod: slicePeriod,
+            revocable: revocable,
+            revoked: false
+        });
+        beneficiarySchedules[beneficiary].push(scheduleId);
+        
+        emit VestingCreated(scheduleId, beneficiary, token, amount, cliffDuration, vestingDuration, revocable);
+    }
+    
+    /**
+     * @dev Claim vested tokens
+     * @param scheduleId ID of the schedule
+     */
+    function claimVestedTokens(uint256 scheduleId) external nonReentrant {
+        VestingSchedule storage schedule = vestingSchedules[scheduleId];
+        require(schedule.beneficiary == msg.sender, "Not beneficiary");
+        
+        uint256 claimable = _calculateVestedAmount(scheduleId) - schedule.claimedAmount;
+        require(claimable > 0, "No tokens to claim");
+        
+        // Update claimed amount
+        schedule.claimedAmount += claimable;
+        
+        // Transfer tokens
+        IERC20(schedule.token).safeTransfer(msg.sender, claimable);
+        
+        emit VestingClaimed(
+            scheduleId,
+            msg.sender,
+            claimable,
+            schedule.totalAmount - schedule.claimedAmount
+        );
+    }
+    
+    /**
+     * @dev Revoke a schedule, returning the unvested tokens to the owner
+     * Tokens vested so far stay claimable by the beneficiary.
+     * @param scheduleId ID of the schedule
+     */
+    function revokeVestingSchedule(uint256 scheduleId) external onlyOwner nonReentrant {
+        VestingSchedule storage schedule = vestingSchedules[scheduleId];
+        require(schedule.revocable, "Not revocable");
+        require(!schedule.revoked, "Already revoked");
+        
+        uint256 vested = _calculateVestedAmount(scheduleId);
+        uint256 unvested = schedule.totalAmount - vested;
+        
+        schedule.totalAmount = vested;
+        schedule.revoked = true;
+        
+        if (unvested > 0) {
+            IERC20(schedule.token).safeTransfer(owner(), unvested);
+        }
+        
+        emit VestingRevoked(scheduleId, vested, unvested);
+    }
+    
+    /**
+     * @dev Hand a schedule over to a new beneficiary
+     * @param scheduleId ID of the schedule
+     * @param newBeneficiary Address receiving future claims
+     */
+    function transferVestingSchedule(uint256 scheduleId, address newBeneficiary) external {
+        VestingSchedule storage schedule = vestingSchedules[scheduleId];
+        require(schedule.beneficiary == msg.sender, "Not beneficiary");
+        require(newBeneficiary != address(0) && newBeneficiary != msg.sender, "Invalid beneficiary");
+        
+        // Swap and pop the ID out of the previous beneficiary's list
+        uint256[] storage ids = beneficiarySchedules[msg.sender];
+        for (uint256 i = 0; i < ids.length; i++) {
+            if (ids[i] == scheduleId) {
+                ids[i] = ids[ids.length - 1];
+                ids.pop();
+                break;
+            }
+        }
+        
+        schedule.beneficiary = newBeneficiary;
+        beneficiarySchedules[newBeneficiary].push(scheduleId);
+        
+        emit VestingTransferred(scheduleId, msg.sender, newBeneficiary);
+    }
+    
+    /**
+     * @dev Total amount vested to date, claimed or not
+     */
+    function _calculateVestedAmount(uint256 scheduleId) internal view returns (uint256) {
+        VestingSchedule storage schedule = vestingSchedules[scheduleId];
+        
+        // Revocation fixes the total at what had vested
+        if (schedule.revoked) {
+            return schedule.totalAmount;
+        }
+        
+        if (block.timestamp < schedule.startTime + schedule.cliff) {
             return 0;
         }
         
         if (block.timestamp >= schedule.startTime + schedule.duration) {
-            return schedule.totalAmount - schedule.claimedAmount;
+            return schedule.totalAmount;
         }
         
         uint256 timeSinceStart = block.timestamp - schedule.startTime;
         uint256 totalSlices = schedule.duration / schedule.slicePeriod;
         uint256 slicesVested = timeSinceStart / schedule.slicePeriod;
         
-        uint256 vestedAmount = (schedule.totalAmount * slicesVested) / totalSlices;
-        return vestedAmount - schedule.claimedAmount;
+        return (schedule.totalAmount * slicesVested) / totalSlices;
     }
     
     // ============ GOVERNANCE FUNCTIONS ============
     
     /**
      * @dev Create governance proposal
+     * Actions are calls made by the governance timelock, e.g. into configurePool or updatePoolAPR.
+     * @param targets Contracts called by the proposal
+     * @param values ETH sent with each call
+     * @param calldatas Encoded calls
      * @param description Proposal description
      * @param votingPeriod Voting period in seconds
      */
-    function createProposal(string memory description, uint256 votingPeriod) 
-        external 
-        returns (uint256) 
-    {
-        require(userVotingPower[msg.sender] > 0, "No voting power");
+    function createProposal(
+        address[] memory targets,
+        uint256[] memory values,
+        bytes[] memory calldatas,
+        string memory description,
+        uint256 votingPeriod
+    ) external returns (uint256) {
+        // Power as of the previous second, so stake-and-propose in one transaction doesn't count
+        uint256 snapshotTime = block.timestamp - 1;
+        require(getPastVotes(msg.sender, snapshotTime) > 0, "No voting power");
         require(votingPeriod >= 3 days && votingPeriod <= 30 days, "Invalid voting period");
+        require(targets.length > 0 && targets.length <= MAX_PROPOSAL_ACTIONS, "Invalid action count");
+        require(targets.length == values.length && targets.length == calldatas.length, "Action length mismatch");
         
         uint256 proposalId = governanceProposalCount++;
         
@@ -704,6 +1284,14 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         proposal.createTime = block.timestamp;
         proposal.votingEndTime = block.timestamp + votingPeriod;
         proposal.executed = false;
+        proposal.snapshotTime = snapshotTime;
+        proposal.quorumVotes = (getPastTotalVotingPower(snapshotTime) * quorumBps) / BASIS_POINTS;
+        
+        for (uint256 i = 0; i < targets.length; i++) {
+            proposal.targets.push(targets[i]);
+            proposal.values.push(values[i]);
+            proposal.calldatas.push(calldatas[i]);
+        }
         
         emit GovernanceProposalCreated(
             proposalId,
@@ -716,7 +1304,7 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
     }
     
     /**
-     * @dev Vote on a proposal
+     * @dev Vote on a proposal with the voting power delegated to the caller at the proposal snapshot
      * @param proposalId Proposal ID
      * @param support True for yes, false for no
      */
@@ -724,41 +1312,554 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         GovernanceProposal storage proposal = governanceProposals[proposalId];
         
         require(block.timestamp <= proposal.votingEndTime, "Voting ended");
+        require(!proposal.canceled, "Proposal canceled");
         require(!proposal.hasVoted[msg.sender], "Already voted");
-        require(userVotingPower[msg.sender] > 0, "No voting power");
+        
+        uint256 weight = getPastVotes(msg.sender, proposal.snapshotTime);
+        require(weight > 0, "No voting power");
         
         proposal.hasVoted[msg.sender] = true;
         
         if (support) {
-            proposal.forVotes += userVotingPower[msg.sender];
+            proposal.forVotes += weight;
         } else {
-            proposal.againstVotes += userVotingPower[msg.sender];
+            proposal.againstVotes += weight;
+        }
+        
+        emit Voted(proposalId, msg.sender, support, weight);
+    }
+    
+    /**
+     * @dev Schedule a passed proposal's actions in the governance timelock
+     * @param proposalId Proposal ID
+     */
+    function queueProposal(uint256 proposalId) external {
+        require(getProposalState(proposalId) == ProposalState.Succeeded, "Proposal not successful");
+        require(address(governanceTimelock) != address(0), "Governance not configured");
+        
+        GovernanceProposal storage proposal = governanceProposals[proposalId];
+        uint256 delay = governanceTimelock.getMinDelay();
+        proposal.eta = block.timestamp + delay;
+        
+        governanceTimelock.scheduleBatch(
+            proposal.targets,
+            proposal.values,
+            proposal.calldatas,
+            bytes32(0),
+            bytes32(proposalId),
+            delay
+        );
+        
+        emit ProposalQueued(proposalId, proposal.eta);
+    }
+    
+    /**
+     * @dev Execute a queued proposal once its timelock delay has passed
+     * @param proposalId Proposal ID
+     */
+    function executeProposal(uint256 proposalId) external payable {
+        require(getProposalState(proposalId) == ProposalState.Queued, "Proposal not queued");
+        
+        GovernanceProposal storage proposal = governanceProposals[proposalId];
+        proposal.executed = true;
+        
+        governanceTimelock.executeBatch{value: msg.value}(
+            proposal.targets,
+            proposal.values,
+            proposal.calldatas,
+            bytes32(0),
+            bytes32(proposalId)
+        );
+        
+        emit ProposalExecuted(proposalId);
+    }
+    
+    /**
+     * @dev Cancel a proposal that has not been executed (creator only)
+     * @param proposalId Proposal ID
+     */
+    function cancelProposal(uint256 proposalId) external {
+        GovernanceProposal storage proposal = governanceProposals[proposalId];
+        require(proposal.creator == msg.sender, "Not proposal creator");
+        
+        ProposalState state = getProposalState(proposalId);
+        require(state != ProposalState.Executed && state != ProposalState.Canceled, "Proposal finalized");
+        
+        proposal.canceled = true;
+        if (state == ProposalState.Queued) {
+            governanceTimelock.cancel(
+                governanceTimelock.hashOperationBatch(
+                    proposal.targets,
+                    proposal.values,
+                    proposal.calldatas,
+                    bytes32(0),
+                    bytes32(proposalId)
+                )
+            );
         }
         
-        emit Voted(proposalId, msg.sender, support, userVotingPower[msg.sender]);
+        emit ProposalCanceled(proposalId);
     }
     
-    function _updateVotingPower(address user) internal {
-        uint256 totalStakeValue = 0;
+    /**
+     * @dev Current state of a proposal
+     * Passing needs more for than against votes, and for + against reaching the quorum.
+     * @param proposalId Proposal ID
+     */
+    function getProposalState(uint256 proposalId) public view returns (ProposalState) {
+        require(proposalId < governanceProposalCount, "Proposal does not exist");
+        GovernanceProposal storage proposal = governanceProposals[proposalId];
         
-        // Calculate total staked value across all pools
-        for (uint256 i = 0; i < userStakes[user].length; i++) {
-            uint256 stakeId = userStakes[user][i];
-            if (stakes[stakeId].isActive) {
-                totalStakeValue += stakes[stakeId].amount;
+        if (proposal.canceled) return ProposalState.Canceled;
+        if (proposal.executed) return ProposalState.Executed;
+        if (proposal.eta != 0) return ProposalState.Queued;
+        if (block.timestamp <= proposal.votingEndTime) return ProposalState.Active;
+        
+        bool quorumReached = proposal.forVotes + proposal.againstVotes >= proposal.quorumVotes;
+        if (quorumReached && proposal.forVotes > proposal.againstVotes) {
+            return ProposalState.Succeeded;
+        }
+        return ProposalState.Defeated;
+    }
+    
+    /**
+     * @dev Get the actions of a proposal
+     */
+    function getProposalActions(uint256 proposalId) 
+        external 
+        view 
+        returns (address[] memory targets, uint256[] memory values, bytes[] memory calldatas) 
+    {
+        GovernanceProposal storage proposal = governanceProposals[proposalId];
+        return (proposal.targets, proposal.values, proposal.calldatas);
+    }
+    
+    /**
+     * @dev Delegate the caller's staked voting power
+     * @param delegatee Account receiving the votes (the caller to take them back)
+     */
+    function delegate(address delegatee) external {
+        require(delegatee != address(0), "Invalid delegatee");
+        
+        address previous = delegates(msg.sender);
+        _delegates[msg.sender] = delegatee;
+        
+        emit DelegateChanged(msg.sender, previous, delegatee);
+        if (previous == delegatee) return;
+        
+        // Move every open lock, so each keeps decaying towards its own end, and drop expired ones
+        uint256[] storage ids = _openLocks[msg.sender];
+        for (uint256 i = ids.length; i > 0; i--) {
+            uint256 stakeId = ids[i - 1];
+            StakePosition storage stake = stakes[stakeId];
+            if (stake.lockEndTime <= block.timestamp) {
+                _untrackLock(msg.sender, stakeId);
+                continue;
+            }
+            
+            _writeVotes(previous, stake.amount, stake.lockEndTime, 0, 0);
+            _writeVotes(delegatee, 0, 0, stake.amount, stake.lockEndTime);
+        }
+    }
+    
+    /**
+     * @dev Account holding a user's votes, the user itself unless delegated
+     */
+    function delegates(address account) public view returns (address) {
+        address delegatee = _delegates[account];
+        return delegatee == address(0) ? account : delegatee;
+    }
+    
+    /**
+     * @dev Current votes delegated to an account
+     */
+    function getVotes(address account) external view returns (uint256) {
+        return _votes(_voteHistory[account], block.timestamp);
+    }
+    
+    /**
+     * @dev Votes delegated to an account at a past timestamp
+     */
+    function getPastVotes(address account, uint256 timepoint) public view returns (uint256) {
+        require(timepoint < block.timestamp, "Future lookup");
+        return _votes(_voteHistory[account], timepoint);
+    }
+    
+    /**
+     * @dev Total voting power at a past timestamp
+     */
+    function getPastTotalVotingPower(uint256 timepoint) public view returns (uint256) {
+        require(timepoint < block.timestamp, "Future lookup");
+        return _votes(_totalVoteHistory, timepoint);
+    }
+    
+    /**
+     * @dev Current total voting power
+     */
+    function totalVotingPower() external view returns (uint256) {
+        return _votes(_totalVoteHistory, block.timestamp);
+    }
+    
+    /**
+     * @dev Current voting power of a user's own locks, wherever it is delegated
+     */
+    function userVotingPower(address user) external view returns (uint256 votes) {
+        uint256[] storage ids = _openLocks[user];
+        for (uint256 i = 0; i < ids.length; i++) {
+            StakePosition storage stake = stakes[ids[i]];
+            votes += _lockedVotes(stake.amount, stake.lockEndTime, block.timestamp);
+        }
+    }
+    
+    /**
+     * @dev IDs of a user's stakes whose lock may still carry votes (expired ones are dropped on delegation)
+     */
+    function getOpenLocks(address user) external view returns (uint256[] memory) {
+        return _openLocks[user];
+    }
+    
+    /**
+     * @dev Voting power of a lock: amount scaled by the remaining time over MAX_LOCK_PERIOD
+     * FLEXIBLE stakes have no lock end and no voting power.
+     */
+    function _lockedVotes(uint256 amount, uint256 lockEndTime, uint256 timestamp) internal pure returns (uint256) {
+        (uint256 slope, uint256 end) = _veLock(amount, lockEndTime);
+        return end > timestamp ? (slope * (end - timestamp)) / VE_PRECISION : 0;
+    }
+    
+    /**
+     * @dev Decay rate (scaled by VE_PRECISION) and week-rounded end of a lock's voting power
+     */
+    function _veLock(uint256 amount, uint256 lockEndTime) internal pure returns (uint256 slope, uint256 end) {
+        slope = (amount * VE_PRECISION) / MAX_LOCK_PERIOD;
+        end = (lockEndTime / WEEK) * WEEK;
+    }
+    
+    /**
+     * @dev Track a stake's lock so delegation moves its votes, without looping over closed stakes
+     */
+    function _trackLock(address user, uint256 stakeId) internal {
+        if (_openLockPositions[stakeId] != 0) return;
+        
+        _openLocks[user].push(stakeId);
+        _openLockPositions[stakeId] = _openLocks[user].length;
+    }
+    
+    /**
+     * @dev Stop tracking a stake's lock, swapping the last tracked lock into its place
+     */
+    function _untrackLock(address user, uint256 stakeId) internal {
+        uint256 position = _openLockPositions[stakeId];
+        if (position == 0) return;
+        
+        uint256[] storage ids = _openLocks[user];
+        uint256 lastId = ids[ids.length - 1];
+        ids[position - 1] = lastId;
+        _openLockPositions[lastId] = position;
+        ids.pop();
+        delete _openLockPositions[stakeId];
+    }
+    
+    /**
+     * @dev Replace a lock in its owner's delegatee history and in the total
+     * @param user Owner of the stake
+     * @param oldAmount Amount of the lock being replaced (0 for a new stake)
+     * @param oldLockEnd Unlock time of the lock being replaced
+     * @param newAmount Amount of the new lock (0 when unstaking)
+     * @param newLockEnd Unlock time of the new lock
+     */
+    function _checkpointLock(
+        address user,
+        uint256 oldAmount,
+        uint256 oldLockEnd,
+        uint256 newAmount,
+        uint256 newLockEnd
+    ) internal {
+        _writeVotes(delegates(user), oldAmount, oldLockEnd, newAmount, newLockEnd);
+        _writeLock(_totalVoteHistory, oldAmount, oldLockEnd, newAmount, newLockEnd);
+    }
+    
+    function _writeVotes(
+        address delegatee,
+        uint256 oldAmount,
+        uint256 oldLockEnd,
+        uint256 newAmount,
+        uint256 newLockEnd
+    ) internal {
+        (uint256 previousVotes, uint256 newVotes) = _writeLock(
+            _voteHistory[delegatee],
+            oldAmount,
+            oldLockEnd,
+            newAmount,
+            newLockEnd
+        );
+        if (previousVotes != newVotes) {
+            emit DelegateVotesChanged(delegatee, previousVotes, newVotes);
+        }
+    }
+    
+    /**
+     * @dev Checkpoint a vote history at the current time with one lock replaced by another
+     * Subtractions saturate, stakes opened before ve accounting have no lock recorded.
+     * @return previousVotes Votes before the change
+     * @return newVotes Votes after the change
+     */
+    function _writeLock(
+        VoteHistory storage history,
+        uint256 oldAmount,
+        uint256 oldLockEnd,
+        uint256 newAmount,
+        uint256 newLockEnd
+    ) internal returns (uint256 previousVotes, uint256 newVotes) {
+        (uint256 bias, uint256 slope) = _votesAt(history, block.timestamp);
+        previousVotes = bias / VE_PRECISION;
+        
+        (uint256 oldSlope, uint256 oldEnd) = _veLock(oldAmount, oldLockEnd);
+        if (oldEnd > block.timestamp) {
+            bias = _subFloor(bias, oldSlope * (oldEnd - block.timestamp));
+            slope = _subFloor(slope, oldSlope);
+            history.slopeChanges[oldEnd] = _subFloor(history.slopeChanges[oldEnd], oldSlope);
+        }
+        
+        (uint256 newSlope, uint256 newEnd) = _veLock(newAmount, newLockEnd);
+        if (newEnd > block.timestamp) {
+            bias += newSlope * (newEnd - block.timestamp);
+            slope += newSlope;
+            history.slopeChanges[newEnd] += newSlope;
+        }
+        newVotes = bias / VE_PRECISION;
+        
+        VotePoint memory point = VotePoint({
+            bias: SafeCast.toUint128(bias),
+            slope: SafeCast.toUint128(slope),
+            timestamp: SafeCast.toUint64(block.timestamp)
+        });
+        uint256 length = history.points.length;
+        if (length > 0 && history.points[length - 1].timestamp == block.timestamp) {
+            history.points[length - 1] = point;
+        } else {
+            history.points.push(point);
+        }
+    }
+    
+    /**
+     * @dev Voting power of a history at a timestamp
+     */
+    function _votes(VoteHistory storage history, uint256 timestamp) internal view returns (uint256) {
+        (uint256 bias,) = _votesAt(history, timestamp);
+        return bias / VE_PRECISION;
+    }
+    
+    /**
+     * @dev Voting power and decay rate of a history at a timestamp, both scaled by VE_PRECISION
+     * Walks week by week from the latest checkpoint at or before `timestamp`, dropping expired locks.
+     */
+    function _votesAt(VoteHistory storage history, uint256 timestamp) 
+        internal 
+        view 
+        returns (uint256 bias, uint256 slope) 
+    {
+        VotePoint[] storage points = history.points;
+        uint256 low = 0;
+        uint256 high = points.length;
+        while (low < high) {
+            uint256 mid = (low + high) / 2;
+            if (points[mid].timestamp > timestamp) {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        if (high == 0) return (0, 0);
+        
+        VotePoint memory point = points[high - 1];
+        bias = point.bias;
+        slope = point.slope;
+        uint256 time = point.timestamp;
+        if (time == timestamp) return (bias, slope);
+        
+        uint256 weekTime = (time / WEEK) * WEEK;
+        for (uint256 i = 0; i <= MAX_LOCK_PERIOD / WEEK + 1; i++) {
+            weekTime += WEEK;
+            uint256 slopeChange = 0;
+            if (weekTime > timestamp) {
+                weekTime = timestamp;
+            } else {
+                slopeChange = history.slopeChanges[weekTime];
+            }
+            
+            bias = _subFloor(bias, slope * (weekTime - time));
+            slope = _subFloor(slope, slopeChange);
+            time = weekTime;
+            if (time == timestamp) return (bias, slope);
+        }
+        
+        // Every lock recorded before the checkpoint has expired
+        return (0, 0);
+    }
+    
+    function _subFloor(uint256 a, uint256 b) internal pure returns (uint256) {
+        return a > b ? a - b : 0;
+    }
+    
+    // ============ GAUGES ============
+    
+    /**
+     * @dev Current gauge epoch, votes cast in it allocate the next epoch's emissions
+     */
+    function currentEpoch() public view returns (uint256) {
+        return block.timestamp / EPOCH_DURATION;
+    }
+    
+    /**
+     * @dev Split the caller's voting power across gauges for the current epoch
+     * Power is read at the start of the epoch, each account votes once per epoch.
+     * @param tokens Gauge pools to vote for
+     * @param weights Share of the voting power given to each pool (basis points, at most 100% in total)
+     */
+    function voteForGauges(address[] calldata tokens, uint256[] calldata weights) external {
+        require(tokens.length > 0 && tokens.length == weights.length, "Length mismatch");
+        
+        uint256 epoch = currentEpoch();
+        GaugeEpoch storage gaugeEpoch = gaugeEpochs[epoch];
+        require(!gaugeEpoch.hasVoted[msg.sender], "Already voted");
+        
+        uint256 power = getPastVotes(msg.sender, epoch * EPOCH_DURATION - 1);
+        require(power > 0, "No voting power");
+        gaugeEpoch.hasVoted[msg.sender] = true;
+        
+        uint256 totalWeight = 0;
+        for (uint256 i = 0; i < tokens.length; i++) {
+            require(isGauge[tokens[i]], "Not a gauge");
+            totalWeight += weights[i];
+            
+            uint256 votes = (power * weights[i]) / BASIS_POINTS;
+            gaugeEpoch.votes[tokens[i]] += votes;
+            gaugeEpoch.totalVotes += votes;
+            
+            emit GaugeVoted(epoch, msg.sender, tokens[i], votes);
+        }
+        require(totalWeight <= BASIS_POINTS, "Weights exceed 100%");
+    }
+    
+    /**
+     * @dev Close a past epoch, splitting each reward token's budget across the gauges paying it by votes
+     * Epochs can be finalized late and in any order. Pool reward rates switch to the allocations of the
+     * latest finalized epoch immediately, older epochs only record theirs. Reward tokens whose gauges
+     * got no votes keep their pools' previous rates.
+     * @param epoch Epoch to finalize, must have ended
+     */
+    function finalizeEpoch(uint256 epoch) external {
+        require(epoch < currentEpoch(), "Epoch not finalizable");
+        
+        GaugeEpoch storage gaugeEpoch = gaugeEpochs[epoch];
+        require(!gaugeEpoch.finalized, "Epoch already finalized");
+        gaugeEpoch.finalized = true;
+        
+        bool setRates = gaugeEpoch.totalVotes > 0 && epoch >= ratesEpoch;
+        if (setRates) {
+            ratesEpoch = epoch;
+        }
+        
+        for (uint256 i = 0; i < gaugePools.length; i++) {
+            address token = gaugePools[i];
+            address rewardToken = pools[token].rewardToken;
+            gaugeEpoch.budgets[rewardToken] = emissionBudgets[rewardToken];
+            
+            uint256 rewardTokenVotes = _rewardTokenVotes(gaugeEpoch, rewardToken);
+            if (rewardTokenVotes == 0) continue;
+            
+            uint256 allocation = (gaugeEpoch.budgets[rewardToken] * gaugeEpoch.votes[token]) / rewardTokenVotes;
+            gaugeEpoch.allocations[token] = allocation;
+            
+            if (setRates) {
+                // Accrue at the old rate (or APR) before switching
+                _updatePool(token);
+                pools[token].rewardRate = allocation / EPOCH_DURATION;
             }
         }
         
-        userVotingPower[user] = totalStakeValue / 1e18; // 1 voting power per token
+        emit EpochFinalized(epoch, gaugeEpoch.totalVotes);
+    }
+    
+    /**
+     * @dev Votes an epoch gave to the gauges paying a reward token
+     */
+    function _rewardTokenVotes(GaugeEpoch storage gaugeEpoch, address rewardToken) internal view returns (uint256 votes) {
+        for (uint256 i = 0; i < gaugePools.length; i++) {
+            if (pools[gaugePools[i]].rewardToken == rewardToken) {
+                votes += gaugeEpoch.votes[gaugePools[i]];
+            }
+        }
+    }
+    
+    /**
+     * @dev Get an epoch's vote total and status
+     */
+    function getGaugeEpoch(uint256 epoch) external view returns (uint256 totalVotes, bool finalized) {
+        GaugeEpoch storage gaugeEpoch = gaugeEpochs[epoch];
+        return (gaugeEpoch.totalVotes, gaugeEpoch.finalized);
+    }
+    
+    /**
+     * @dev Get the budget of a reward token split in an epoch (0 until finalized)
+     */
+    function getEpochBudget(uint256 epoch, address rewardToken) external view returns (uint256) {
+        return gaugeEpochs[epoch].budgets[rewardToken];
+    }
+    
+    /**
+     * @dev Get the votes and allocations of every gauge in an epoch
+     * @return tokens Gauge pools
+     * @return votes Votes received by each pool
+     * @return allocations Rewards allocated to each pool for the following epoch (0 until finalized)
+     */
+    function getEpochAllocations(uint256 epoch) 
+        external 
+        view 
+        returns (address[] memory tokens, uint256[] memory votes, uint256[] memory allocations) 
+    {
+        GaugeEpoch storage gaugeEpoch = gaugeEpochs[epoch];
+        tokens = gaugePools;
+        votes = new uint256[](tokens.length);
+        allocations = new uint256[](tokens.length);
+        
+        for (uint256 i = 0; i < tokens.length; i++) {
+            votes[i] = gaugeEpoch.votes[tokens[i]];
+            allocations[i] = gaugeEpoch.allocations[tokens[i]];
+        }
+    }
+    
+    /**
+     * @dev Check if an account has voted on the gauges in an epoch
+     */
+    function hasVotedGauges(uint256 epoch, address account) external view returns (bool) {
+        return gaugeEpochs[epoch].hasVoted[account];
+    }
+    
+    // ============ TOKEN TRANSFERS ============
+    
+    /**
+     * @dev Pull tokens from an account and return the amount actually received
+     * Fee-on-transfer tokens deliver less than requested, so stakes are credited with the balance change.
+     */
+    function _pullTokens(address token, address from, uint256 amount) internal returns (uint256 received) {
+        uint256 balanceBefore = IERC20(token).balanceOf(address(this));
+        IERC20(token).safeTransferFrom(from, address(this), amount);
+        received = IERC20(token).balanceOf(address(this)) - balanceBefore;
+        require(received > 0, "Nothing received");
     }
     
     // ============ ADMIN FUNCTIONS ============
     
     /**
      * @dev Configure a new staking pool
+     * @param token Token staked in the pool
+     * @param rewardToken Token rewards are paid in, funded through notifyRewardAmount
      */
     function configurePool(
         address token,
+        address rewardToken,
         uint256 baseAPR,
         uint256 lockPeriod,
         uint256 minStakeAmount,
@@ -771,25 +1872,40 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         require(lockPeriod >= MIN_LOCK_PERIOD && lockPeriod <= MAX_LOCK_PERIOD, "Invalid lock period");
         require(performanceFee <= MAX_PERFORMANCE_FEE, "Fee too high");
         require(earlyWithdrawFee <= MAX_EARLY_WITHDRAW_FEE, "Fee too high");
+        require(rewardToken != address(0), "Invalid reward token");
         
-        pools[token] = PoolConfig({
-            token: token,
-            isActive: true,
-            baseAPR: baseAPR,
-            lockPeriod: lockPeriod,
-            minStakeAmount: minStakeAmount,
-            maxStakeAmount: maxStakeAmount,
-            totalStaked: 0,
-            totalRewardsPaid: 0,
-            performanceFee: performanceFee,
-            earlyWithdrawFee: earlyWithdrawFee,
-            poolCap: poolCap
-        });
+        PoolConfig storage pool = pools[token];
+        
+        // A funded reserve or owed rewards stay with their reward token
+        require(
+            (pool.rewardReserve == 0 && pool.totalWeightedStake == 0) || pool.rewardToken == rewardToken,
+            "Reward token in use"
+        );
+        
+        // Reconfiguring keeps the pool's stakes and reward index
+        _updatePool(token);
+        
+        pool.token = token;
+        pool.isActive = true;
+        pool.baseAPR = baseAPR;
+        pool.lockPeriod = lockPeriod;
+        pool.minStakeAmount = minStakeAmount;
+        pool.maxStakeAmount = maxStakeAmount;
+        pool.performanceFee = performanceFee;
+        pool.earlyWithdrawFee = earlyWithdrawFee;
+        pool.poolCap = poolCap;
+        pool.rewardToken = rewardToken;
         
         whitelistedTokens[token] = true;
+        if (!isGauge[token]) {
+            isGauge[token] = true;
+            gaugePools.push(token);
+            emit GaugeAdded(token);
+        }
         
         emit PoolConfigured(
             token,
+            rewardToken,
             baseAPR,
             lockPeriod,
             minStakeAmount,
@@ -799,11 +1915,31 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
     }
     
     /**
-     * @dev Assign boost card to user (simulated NFT)
+     * @dev Fund a pool's reward reserve
+     * @param token Staked token of the pool
+     * @param amount Amount of the pool's reward token to add
      */
-    function assignBoostCard(address user, BoostCardTier tier) external onlyOwner {
-        boostCards[user] = tier;
-        emit BoostCardAssigned(user, tier);
+    function notifyRewardAmount(address token, uint256 amount) external onlyOwner poolExists(token) {
+        PoolConfig storage pool = pools[token];
+        
+        // Close the current emission period so new funds don't back-pay a dry spell
+        _updatePool(token);
+        
+        uint256 received = _pullTokens(pool.rewardToken, msg.sender, amount);
+        pool.rewardReserve += received;
+        
+        emit RewardAdded(token, pool.rewardToken, received, pool.rewardReserve);
+    }
+    
+    /**
+     * @dev Set the boost card collection accepted for BOOSTED stakes (once, cards in custody depend on it)
+     */
+    function setBoostCard(address _boostCard) external onlyOwner {
+        require(_boostCard != address(0), "Invalid boost card");
+        require(address(boostCard) == address(0), "Boost card already set");
+        
+        boostCard = BoostCard(_boostCard);
+        emit BoostCardCollectionUpdated(_boostCard);
     }
     
     /**
@@ -811,15 +1947,60 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
      */
     function emergencyWithdraw(address token, uint256 amount) external onlyOwner {
         require(IERC20(token).balanceOf(address(this)) >= amount, "Insufficient balance");
-        IERC20(token).transfer(owner(), amount);
+        IERC20(token).safeTransfer(owner(), amount);
+    }
+    
+    /**
+     * @dev Set the governance timelock and quorum, and transfer ownership to the timelock
+     * The timelock needs this contract as proposer, executor and canceller. Admin functions then
+     * only run through passed proposals.
+     * @param timelock Timelock that queues and executes proposals
+     * @param _quorumBps Share of total voting power needed for a proposal to pass (basis points)
+     */
+    function setGovernance(address timelock, uint256 _quorumBps) external onlyOwner {
+        require(timelock != address(0), "Invalid timelock");
+        require(_quorumBps <= BASIS_POINTS, "Invalid quorum");
+        
+        governanceTimelock = TimelockController(payable(timelock));
+        quorumBps = _quorumBps;
+        _transferOwnership(timelock);
+        
+        emit GovernanceConfigured(timelock, _quorumBps);
+    }
+    
+    /**
+     * @dev Set the rewards of a reward token split across its gauges each epoch, used from the next finalization
+     * @param rewardToken Reward token of the gauges sharing the budget
+     * @param budget Rewards per epoch, each pool paying its share from its own reserve
+     */
+    function setEmissionBudget(address rewardToken, uint256 budget) external onlyOwner {
+        emissionBudgets[rewardToken] = budget;
+        emit EmissionBudgetUpdated(rewardToken, budget);
     }
     
     /**
-     * @dev Update pool parameters
+     * @dev Hand pool emissions over to the gauge rates, or back to the owner-set APRs
+     * Pools accrue at their previous mode up to now.
+     * @param active Whether pools emit at their gauge rate
+     */
+    function setGaugeEmissionsActive(bool active) external onlyOwner {
+        for (uint256 i = 0; i < gaugePools.length; i++) {
+            _updatePool(gaugePools[i]);
+        }
+        gaugeEmissionsActive = active;
+        emit GaugeEmissionsToggled(active);
+    }
+    
+    /**
+     * @dev Update pool parameters, only while emissions follow APRs
      */
     function updatePoolAPR(address token, uint256 newAPR) external onlyOwner {
         require(pools[token].isActive, "Pool not active");
+        require(!gaugeEmissionsActive, "Emissions set by gauges");
         require(newAPR <= 50000, "APR too high");
+        
+        // Rewards accrued so far keep the old rate
+        _updatePool(token);
         pools[token].baseAPR = newAPR;
     }
     
@@ -857,6 +2038,13 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         return activeStakes;
     }
     
+    /**
+     * @dev Get the full configuration and accounting of a pool
+     */
+    function getPoolConfig(address token) external view returns (PoolConfig memory) {
+        return pools[token];
+    }
+    
     /**
      * @dev Get pool statistics
      */
@@ -879,6 +2067,30 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         return (totalStaked, totalRewardsPaid, utilizationRate, currentAPR);
     }
     
+    /**
+     * @dev Estimate how long a pool's reward reserve lasts at the current emission
+     * Referral rewards are taken from the reserve as referred stakes are created, so they
+     * shorten the runway as soon as they are paid.
+     * @param token Staked token of the pool
+     * @return daysLeft Whole days of emission left (type(uint256).max if nothing is emitted)
+     */
+    function getRewardRunway(address token) external view returns (uint256 daysLeft) {
+        PoolConfig storage pool = pools[token];
+        require(pool.isActive, "Pool not active");
+        
+        uint256 dailyEmission = pool.totalWeightedStake > 0 ? _emission(pool, 1 days) : 0;
+        if (dailyEmission == 0) {
+            return type(uint256).max;
+        }
+        
+        uint256 reserve = pool.rewardReserve;
+        if (block.timestamp > pool.lastRewardTime) {
+            reserve -= _pendingPoolRewards(pool);
+        }
+        
+        return reserve / dailyEmission;
+    }
+    
     /**
      * @dev Calculate APR for a specific stake type
      */
@@ -890,17 +2102,22 @@ contract YieldHarvest is ReentrancyGuard, Ownable {
         PoolConfig storage pool = pools[token];
         require(pool.isActive, "Pool not active");
         
-        uint256 baseAPR = pool.baseAPR;
-        
         // Apply stake type multiplier
-        if (stakeType == StakeType.LOCKED) {
-            baseAPR = (baseAPR * 15000) / BASIS_POINTS;
-        } else if (stakeType == StakeType.BOOSTED) {
-            uint256 boostMultiplier = _getBoostMultiplier(boostTier);
-            baseAPR = (baseAPR * boostMultiplier) / BASIS_POINTS;
-        }
-        
-        return baseAPR;
+        return (pool.baseAPR * _stakeMultiplier(stakeType, boostTier)) / BASIS_POINTS;
+    }
+    
+    /**
+     * @dev Get the IDs of a beneficiary's vesting schedules
+     */
+    function getBeneficiarySchedules(address beneficiary) external view returns (uint256[] memory) {
+        return beneficiarySchedules[beneficiary];
+    }
+    
+    /**
+     * @dev Get the amount of a vesting schedule claimable now
+     */
+    function getClaimableVestedAmount(uint256 scheduleId) external view returns (uint256) {
+        return _calculateVestedAmount(scheduleId) - vestingSchedules[scheduleId].claimedAmount;
     }
     
     /**
@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import "@openzeppelin/contracts/token/ERC721/ERC721.sol";
+import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
+import "@openzeppelin/contracts/token/ERC20/utils/SafeERC20.sol";
+import "@openzeppelin/contracts/access/Ownable.sol";
+import "@openzeppelin/contracts/utils/Base64.sol";
+import "@openzeppelin/contracts/utils/Strings.sol";
+
+/**
+ * @title BoostCard
+ * @dev ERC-721 boost cards for YieldHarvest BOOSTED stakes
+ * Features:
+ * - Each card carries a tier (1: Bronze, 2: Silver, 3: Gold, 4: Platinum)
+ * - Owner mints cards, holders upgrade one tier at a time for a fee
+ * - Fully on-chain metadata (JSON and SVG, base64 encoded)
+ */
+contract BoostCard is ERC721, Ownable {
+    using SafeERC20 for IERC20;
+    using Strings for uint256;
+    
+    // ============ CONSTANTS ============
+    uint8 public constant MIN_TIER = 1; // Bronze
+    uint8 public constant MAX_TIER = 4; // Platinum
+    
+    // ============ STATE VARIABLES ============
+    uint256 public nextTokenId = 1;
+    IERC20 public paymentToken; // Token upgrade fees are paid in
+    address public treasury;    // Receiver of upgrade fees
+    
+    // Mappings
+    mapping(uint256 => uint8) public tierOf; // tokenId => tier
+    mapping(uint8 => uint256) public upgradePrices; // current tier => price to reach the next tier (0 = disabled)
+    
+    // ============ EVENTS ============
+    event CardMinted(uint256 indexed tokenId, address indexed to, uint8 tier);
+    event TierUpgraded(uint256 indexed tokenId, address indexed holder, uint8 newTier, uint256 price);
+    event UpgradePriceUpdated(uint8 indexed tier, uint256 price);
+    event PaymentConfigUpdated(address paymentToken, address treasury);
+    
+    // ============ CONSTRUCTOR ============
+    
+    /**
+     * @param _paymentToken Token upgrade fees are paid in
+     * @param _treasury Receiver of upgrade fees
+     */
+    constructor(address _paymentToken, address _treasury) ERC721("YieldHarvest Boost Card", "BOOST") Ownable(msg.sender) {
+        require(_paymentToken != address(0), "Invalid payment token");
+        require(_treasury != address(0), "Invalid treasury");
+        
+        paymentToken = IERC20(_paymentToken);
+        treasury = _treasury;
+    }
+    
+    // ============ MINT AND UPGRADE ============
+    
+    /**
+     * @dev Mint a new card
+     * @param to Receiver of the card
+     * @param tier Tier of the card
+     * @return tokenId ID of the minted card
+     */
+    function mint(address to, uint8 tier) external onlyOwner returns (uint256 tokenId) {
+        require(tier >= MIN_TIER && tier <= MAX_TIER, "Invalid tier");
+        
+        tokenId = nextTokenId++;
+        tierOf[tokenId] = tier;
+        _safeMint(to, tokenId);
+        
+        emit CardMinted(tokenId, to, tier);
+    }
+    
+    /**
+     * @dev Upgrade a card by one tier, paying the upgrade price to the treasury
+     * Cards staked in YieldHarvest must be withdrawn before upgrading.
+     * @param tokenId ID of the card
+     */
+    function upgradeTier(uint256 tokenId) external {
+        require(ownerOf(tokenId) == msg.sender, "Not card owner");
+        
+        uint8 tier = tierOf[tokenId];
+        require(tier < MAX_TIER, "Max tier reached");
+        uint256 price = upgradePrices[tier];
+        require(price > 0, "Upgrade not available");
+        
+        tierOf[tokenId] = tier + 1;
+        paymentToken.safeTransferFrom(msg.sender, treasury, price);
+        
+        emit TierUpgraded(tokenId, msg.sender, tier + 1, price);
+    }
+    
+    // ============ ADMIN FUNCTIONS ============
+    
+    /**
+     * @dev Set the price to upgrade from a tier to the next one
+     * @param tier Current tier of the card
+     * @param price Upgrade price in the payment token (0 disables the upgrade)
+     */
+    function setUpgradePrice(uint8 tier, uint256 price) external onlyOwner {
+        require(tier >= MIN_TIER && tier < MAX_TIER, "Invalid tier");
+        upgradePrices[tier] = price;
+        emit UpgradePriceUpdated(tier, price);
+    }
+    
+    /**
+     * @dev Update the payment token and fee receiver
+     */
+    function setPaymentConfig(address _paymentToken, address _treasury) external onlyOwner {
+        require(_paymentToken != address(0), "Invalid payment token");
+        require(_treasury != address(0), "Invalid treasury");
+        
+        paymentToken = IERC20(_paymentToken);
+        treasury = _treasury;
+        emit PaymentConfigUpdated(_paymentToken, _treasury);
+    }
+    
+    // ============ METADATA ============
+    
+    /**
+     * @dev On-chain metadata with the tier and boost as attributes
+     */
+    function tokenURI(uint256 tokenId) public view override returns (string memory) {
+        _requireOwned(tokenId);
+        
+        uint8 tier = tierOf[tokenId];
+        string memory json = string.concat(
+            '{"name":"Boost Card #', tokenId.toString(),
+            '","description":"Boosts the APR of a YieldHarvest BOOSTED stake while staked.",',
+            '"image":"data:image/svg+xml;base64,', Base64.encode(bytes(_svg(tier))),
+            '","attributes":[{"trait_type":"Tier","value":"', tierName(tier),
+            '"},{"display_type":"boost_percentage","trait_type":"APR Boost","value":', boostPercent(tier).toString(),
+            '}]}'
+        );
+        
+        return string.concat("data:application/json;base64,", Base64.encode(bytes(json)));
+    }
+    
+    /**
+     * @dev Display name of a tier
+     */
+    function tierName(uint8 tier) public pure returns (string memory) {
+        if (tier == 1) return "Bronze";
+        if (tier == 2) return "Silver";
+        if (tier == 3) return "Gold";
+        if (tier == 4) return "Platinum";
+        return "None";
+    }
+    
+    /**
+     * @dev APR boost of a tier in percent, matching the YieldHarvest multipliers
+     */
+    function boostPercent(uint8 tier) public pure returns (uint256) {
+        if (tier == 1) return 10;
+        if (tier == 2) return 25;
+        if (tier == 3) return 50;
+        if (tier == 4) return 100;
+        return 0;
+    }
+    
+    function _svg(uint8 tier) internal pure returns (string memory) {
+        string memory color = "#cd7f32";
+        if (tier == 2) color = "#c0c0c0";
+        if (tier == 3) color = "#ffd700";
+        if (tier == 4) color = "#e5e4e2";
+        
+        return string.concat(
+            '<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 300 420">',
+            '<rect width="300" height="420" rx="24" fill="', color, '"/>',
+            '<text x="150" y="190" text-anchor="middle" font-size="32" font-family="sans-serif">', tierName(tier), '</text>',
+            '<text x="150" y="240" text-anchor="middle" font-size="24" font-family="sans-serif">+', boostPercent(tier).toString(), '% APR</text>',
+            '</svg>'
+        );
+    }
+}
@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+import "@openzeppelin/contracts/interfaces/IERC3156FlashBorrower.sol";
+import "@openzeppelin/contracts/interfaces/IERC3156FlashLender.sol";
+import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
+import "@openzeppelin/contracts/token/ERC20/utils/SafeERC20.sol";
+
+/**
+ * @title FlashLoanBorrower
+ * @dev Reference ERC-3156 borrower for a single trusted lender
+ * Features:
+ * - Only accepts callbacks from the lender for loans it initiated itself
+ * - Approves amount + fee back to the lender after the operation runs
+ * - Override _executeOperation to put the borrowed funds to work
+ * The fee is paid from this contract's own balance, so fund it before borrowing.
+ */
+contract FlashLoanBorrower is IERC3156FlashBorrower {
+    using SafeERC20 for IERC20;
+    
+    // ============ STATE VARIABLES ============
+    IERC3156FlashLender public immutable lender;
+    address public immutable owner;
+    
+    // ============ CONSTRUCTOR ============
+    
+    /**
+     * @param _lender Address of the flash lender
+     */
+    constructor(address _lender) {
+        require(_lender != address(0), "Invalid lender");
+        lender = IERC3156FlashLender(_lender);
+        owner = msg.sender;
+    }
+    
+    // ============ FLASH LOAN FUNCTIONS ============
+    
+    /**
+     * @dev Start a flash loan
+     * @param token Address of token to borrow
+     * @param amount Amount to borrow
+     * @param data Data passed through to _executeOperation
+     */
+    function flashBorrow(address token, uint256 amount, bytes calldata data) external {
+        require(msg.sender == owner, "Only owner");
+        lender.flashLoan(this, token, amount, data);
+    }
+    
+    /**
+     * @dev ERC-3156 callback, runs the operation and approves the repayment
+     */
+    function onFlashLoan(
+        address initiator,
+        address token,
+        uint256 amount,
+        uint256 fee,
+        bytes calldata data
+    ) external override returns (bytes32) {
+        require(msg.sender == address(lender), "Untrusted lender");
+        require(initiator == address(this), "Untrusted initiator");
+        
+        _executeOperation(token, amount, fee, data);
+        
+        IERC20(token).forceApprove(address(lender), amount + fee);
+        return keccak256("ERC3156FlashBorrower.onFlashLoan");
+    }
+    
+    /**
+     * @dev Operation run while holding the borrowed funds, no-op by default
+     */
+    function _executeOperation(
+        address token,
+        uint256 amount,
+        uint256 fee,
+        bytes calldata data
+    ) internal virtual {
+        // Silence unused-parameter warnings in the default implementation
+        (token, amount, fee, data);
+    }
+    
+    /**
+     * @dev Recover tokens held by this contract
+     * @param token Address of token
+     * @param amount Amount to send to the owner
+     */
+    function withdraw(address token, uint256 amount) external {
+        require(msg.sender == owner, "Only owner");
+        IERC20(token).safeTransfer(owner, amount);
+    }
+}
@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/**
+ * @title AggregatorV3Interface
+ * @dev Minimal Chainlink aggregator interface (only the calls used by the adapters)
+ */
+interface AggregatorV3Interface {
+    function decimals() external view returns (uint8);
+    
+    function latestRoundData()
+        external
+        view
+        returns (
+            uint80 roundId,
+            int256 answer,
+            uint256 startedAt,
+            uint256 updatedAt,
+            uint80 answeredInRound
+        );
+}
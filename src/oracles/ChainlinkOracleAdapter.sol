@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+import "./IPriceOracle.sol";
+import "./AggregatorV3Interface.sol";
+
+/**
+ * @title ChainlinkOracleAdapter
+ * @dev Wraps a Chainlink-style aggregator for a single asset
+ * Features:
+ * - Rejects non-positive answers and incomplete rounds
+ * - Staleness check against a maximum answer age
+ * - Normalizes the aggregator decimals to 18
+ */
+contract ChainlinkOracleAdapter is IPriceOracle {
+    
+    // ============ STATE VARIABLES ============
+    address public immutable asset;
+    AggregatorV3Interface public immutable aggregator;
+    uint256 public immutable maxStaleness; // in seconds
+    uint8 public immutable aggregatorDecimals;
+    
+    // ============ CONSTRUCTOR ============
+    
+    /**
+     * @param _asset Token priced by the aggregator
+     * @param _aggregator Address of the Chainlink aggregator (USD quoted)
+     * @param _maxStaleness Maximum age of an answer in seconds
+     */
+    constructor(address _asset, address _aggregator, uint256 _maxStaleness) {
+        require(_asset != address(0), "Invalid asset");
+        require(_aggregator != address(0), "Invalid aggregator");
+        require(_maxStaleness > 0, "Invalid staleness");
+        
+        asset = _asset;
+        aggregator = AggregatorV3Interface(_aggregator);
+        maxStaleness = _maxStaleness;
+        aggregatorDecimals = AggregatorV3Interface(_aggregator).decimals();
+    }
+    
+    // ============ VIEW FUNCTIONS ============
+    
+    /**
+     * @dev Get the latest aggregator answer normalized to 18 decimals
+     * @param token Address of token (must be the adapter asset)
+     * @return price Price of one whole token in USD (18 decimals)
+     */
+    function getPrice(address token) external view override returns (uint256 price) {
+        require(token == asset, "Unsupported token");
+        
+        (uint80 roundId, int256 answer, , uint256 updatedAt, uint80 answeredInRound) =
+            aggregator.latestRoundData();
+        
+        require(answer > 0, "Invalid price");
+        require(updatedAt != 0 && answeredInRound >= roundId, "Incomplete round");
+        require(block.timestamp - updatedAt <= maxStaleness, "Stale price");
+        
+        price = uint256(answer);
+        if (aggregatorDecimals < 18) {
+            price = price * 10 ** (18 - aggregatorDecimals);
+        } else if (aggregatorDecimals > 18) {
+            price = price / 10 ** (aggregatorDecimals - 18);
+        }
+    }
+}
@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/**
+ * @title IPriceOracle
+ * @dev Common interface for price sources used by ERC20TokenLoan.
+ * Prices are always returned as the USD value of one whole token,
+ * scaled to 18 decimals, regardless of the underlying feed format.
+ */
+interface IPriceOracle {
+    /**
+     * @dev Get the USD price of a token
+     * @param token Address of token to price
+     * @return price Price of one whole token in USD (18 decimals)
+     */
+    function getPrice(address token) external view returns (uint256 price);
+}
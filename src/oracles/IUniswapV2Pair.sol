@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/**
+ * @title IUniswapV2Pair
+ * @dev Minimal Uniswap V2 pair interface (only the calls used by the TWAP adapter)
+ */
+interface IUniswapV2Pair {
+    function token0() external view returns (address);
+    
+    function token1() external view returns (address);
+    
+    function price0CumulativeLast() external view returns (uint256);
+    
+    function price1CumulativeLast() external view returns (uint256);
+    
+    function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+}
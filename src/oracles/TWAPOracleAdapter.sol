@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+import "@openzeppelin/contracts/token/ERC20/extensions/IERC20Metadata.sol";
+import "@openzeppelin/contracts/utils/math/Math.sol";
+import "./IPriceOracle.sol";
+import "./IUniswapV2Pair.sol";
+
+/**
+ * @title TWAPOracleAdapter
+ * @dev Time-weighted average price of an asset from a Uniswap V2 style pair
+ * Features:
+ * - Fixed-window TWAP, refreshed by anyone through `update`
+ * - Staleness check on the last computed average
+ * - Optional quote oracle when the pair is not quoted in a USD stablecoin
+ * - Normalizes both pair tokens' decimals to an 18-decimal USD price
+ */
+contract TWAPOracleAdapter is IPriceOracle {
+    
+    // ============ CONSTANTS ============
+    uint256 private constant Q112 = 2 ** 112;
+    
+    // ============ STATE VARIABLES ============
+    IUniswapV2Pair public immutable pair;
+    address public immutable asset;
+    address public immutable quoteToken;
+    IPriceOracle public immutable quoteOracle; // 0 if quote token is a USD stablecoin
+    uint256 public immutable period; // TWAP window in seconds
+    uint256 public immutable maxStaleness; // Max age of the last average in seconds
+    bool public immutable assetIsToken0;
+    uint8 public immutable assetDecimals;
+    uint8 public immutable quoteDecimals;
+    
+    uint256 public priceCumulativeLast;
+    uint32 public blockTimestampLast;
+    uint256 public priceAverage; // UQ112x112, quote units per asset unit
+    uint256 public lastUpdateTime;
+    
+    // ============ EVENTS ============
+    event TWAPUpdated(uint256 priceAverage, uint256 timeElapsed);
+    
+    // ============ CONSTRUCTOR ============
+    
+    /**
+     * @param _pair Address of the Uniswap V2 pair
+     * @param _asset Token priced by this adapter (must be one side of the pair)
+     * @param _quoteOracle Oracle for the other side of the pair (0 for USD stablecoins)
+     * @param _period Minimum window between two updates in seconds
+     * @param _maxStaleness Maximum age of the average in seconds
+     */
+    constructor(
+        address _pair,
+        address _asset,
+        address _quoteOracle,
+        uint256 _period,
+        uint256 _maxStaleness
+    ) {
+        require(_period > 0, "Invalid period");
+        require(_maxStaleness >= _period, "Invalid staleness");
+        
+        address token0 = IUniswapV2Pair(_pair).token0();
+        address token1 = IUniswapV2Pair(_pair).token1();
+        require(_asset == token0 || _asset == token1, "Asset not in pair");
+        
+        bool isToken0 = _asset == token0;
+        address quote = isToken0 ? token1 : token0;
+        
+        pair = IUniswapV2Pair(_pair);
+        asset = _asset;
+        assetIsToken0 = isToken0;
+        quoteToken = quote;
+        quoteOracle = IPriceOracle(_quoteOracle);
+        period = _period;
+        maxStaleness = _maxStaleness;
+        assetDecimals = IERC20Metadata(_asset).decimals();
+        quoteDecimals = IERC20Metadata(quote).decimals();
+        
+        (priceCumulativeLast, blockTimestampLast) = _currentCumulativePrice(IUniswapV2Pair(_pair), isToken0);
+    }
+    
+    // ============ UPDATE FUNCTIONS ============
+    
+    /**
+     * @dev Recompute the average over the window since the last update
+     */
+    function update() external {
+        (uint256 priceCumulative, uint32 blockTimestamp) = _currentCumulativePrice(pair, assetIsToken0);
+        
+        uint256 timeElapsed;
+        uint256 average;
+        unchecked {
+            // Overflow is desired, cumulative prices and timestamps wrap around
+            timeElapsed = uint32(blockTimestamp - blockTimestampLast);
+            require(timeElapsed >= period, "Period not elapsed");
+            average = (priceCumulative - priceCumulativeLast) / timeElapsed;
+        }
+        
+        priceAverage = average;
+        priceCumulativeLast = priceCumulative;
+        blockTimestampLast = blockTimestamp;
+        lastUpdateTime = block.timestamp;
+        
+        emit TWAPUpdated(average, timeElapsed);
+    }
+    
+    // ============ VIEW FUNCTIONS ============
+    
+    /**
+     * @dev Get the time-weighted average price normalized to 18 decimals
+     * @param token Address of token (must be the adapter asset)
+     * @return price Price of one whole token in USD (18 decimals)
+     */
+    function getPrice(address token) external view override returns (uint256 price) {
+        require(token == asset, "Unsupported token");
+        require(lastUpdateTime != 0, "TWAP not initialized");
+        require(block.timestamp - lastUpdateTime <= maxStaleness, "Stale price");
+        
+        // priceAverage is (quote raw units / asset raw unit) * 2^112
+        price = Math.mulDiv(priceAverage, 10 ** (uint256(assetDecimals) + 18), Q112 * 10 ** uint256(quoteDecimals));
+        
+        if (address(quoteOracle) != address(0)) {
+            price = (price * quoteOracle.getPrice(quoteToken)) / 1e18;
+        }
+        
+        require(price > 0, "Invalid price");
+    }
+    
+    // ============ INTERNAL FUNCTIONS ============
+    
+    /**
+     * @dev Cumulative price of the asset including the time since the pair's last sync
+     * @param _pair Pair to read (passed in so the constructor can use it)
+     * @param _assetIsToken0 Whether the asset is token0 of the pair
+     */
+    function _currentCumulativePrice(IUniswapV2Pair _pair, bool _assetIsToken0)
+        internal
+        view
+        returns (uint256 priceCumulative, uint32 blockTimestamp)
+    {
+        blockTimestamp = uint32(block.timestamp % 2 ** 32);
+        priceCumulative = _assetIsToken0 ? _pair.price0CumulativeLast() : _pair.price1CumulativeLast();
+        
+        (uint112 reserve0, uint112 reserve1, uint32 pairTimestamp) = _pair.getReserves();
+        require(reserve0 > 0 && reserve1 > 0, "No reserves");
+        
+        if (pairTimestamp != blockTimestamp) {
+            unchecked {
+                uint32 timeElapsed = blockTimestamp - pairTimestamp;
+                if (_assetIsToken0) {
+                    priceCumulative += ((uint256(reserve1) << 112) / reserve0) * timeElapsed;
+                } else {
+                    priceCumulative += ((uint256(reserve0) << 112) / reserve1) * timeElapsed;
+                }
+            }
+        }
+    }
+}
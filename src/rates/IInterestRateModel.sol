@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/**
+ * @title IInterestRateModel
+ * @dev Interest rate model used by ERC20TokenLoan pools.
+ * All rates are annual and expressed in basis points (100 = 1%).
+ * `cash` is idle pool liquidity (reserves included), `borrows` is
+ * outstanding debt and `reserves` is the protocol's share of both.
+ */
+interface IInterestRateModel {
+    /**
+     * @dev Get the annual borrow rate
+     * @param cash Idle pool liquidity
+     * @param borrows Outstanding borrows
+     * @param reserves Protocol reserves
+     * @return Borrow rate in basis points
+     */
+    function getBorrowRate(uint256 cash, uint256 borrows, uint256 reserves) external view returns (uint256);
+    
+    /**
+     * @dev Get the annual rate earned by suppliers
+     * @param cash Idle pool liquidity
+     * @param borrows Outstanding borrows
+     * @param reserves Protocol reserves
+     * @param reserveFactor Share of interest kept as reserves (in basis points)
+     * @return Supply rate in basis points
+     */
+    function getSupplyRate(
+        uint256 cash,
+        uint256 borrows,
+        uint256 reserves,
+        uint256 reserveFactor
+    ) external view returns (uint256);
+}
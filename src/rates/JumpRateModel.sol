@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+import "./IInterestRateModel.sol";
+
+/**
+ * @title JumpRateModel
+ * @dev Kinked interest rate model
+ * Features:
+ * - Linear rate (base + slope1) up to the kink utilization
+ * - Steeper slope2 above the kink to pull utilization back down
+ * - Supply rate derived from the same borrow rate, net of the reserve factor
+ */
+contract JumpRateModel is IInterestRateModel {
+    
+    // ============ CONSTANTS ============
+    uint256 public constant BASIS_POINTS = 10000;
+    
+    // ============ STATE VARIABLES ============
+    uint256 public immutable baseRate; // Rate at 0% utilization (basis points)
+    uint256 public immutable slope1; // Rate increase from 0% to 100% utilization below the kink (basis points)
+    uint256 public immutable kink; // Utilization where slope2 kicks in (basis points)
+    uint256 public immutable slope2; // Rate increase from 0% to 100% utilization above the kink (basis points)
+    
+    // ============ CONSTRUCTOR ============
+    
+    /**
+     * @param _baseRate Rate at 0% utilization in basis points
+     * @param _slope1 Slope below the kink in basis points
+     * @param _kink Kink utilization in basis points
+     * @param _slope2 Slope above the kink in basis points
+     */
+    constructor(uint256 _baseRate, uint256 _slope1, uint256 _kink, uint256 _slope2) {
+        require(_kink > 0 && _kink <= BASIS_POINTS, "Invalid kink");
+        
+        baseRate = _baseRate;
+        slope1 = _slope1;
+        kink = _kink;
+        slope2 = _slope2;
+    }
+    
+    // ============ VIEW FUNCTIONS ============
+    
+    /**
+     * @dev Get the utilization of a pool
+     * @param cash Idle pool liquidity
+     * @param borrows Outstanding borrows
+     * @param reserves Protocol reserves
+     * @return Utilization in basis points, capped at 100%
+     */
+    function utilizationRate(uint256 cash, uint256 borrows, uint256 reserves) public pure returns (uint256) {
+        if (borrows == 0) return 0;
+        
+        // Reserves lent out can leave suppliers' assets at or below the borrows
+        if (cash + borrows <= reserves) return BASIS_POINTS;
+        uint256 utilization = (borrows * BASIS_POINTS) / (cash + borrows - reserves);
+        return utilization > BASIS_POINTS ? BASIS_POINTS : utilization;
+    }
+    
+    /**
+     * @dev Get the annual borrow rate
+     * @param cash Idle pool liquidity
+     * @param borrows Outstanding borrows
+     * @param reserves Protocol reserves
+     * @return Borrow rate in basis points
+     */
+    function getBorrowRate(uint256 cash, uint256 borrows, uint256 reserves) public view override returns (uint256) {
+        uint256 utilization = utilizationRate(cash, borrows, reserves);
+        
+        if (utilization <= kink) {
+            return baseRate + (utilization * slope1) / BASIS_POINTS;
+        }
+        
+        uint256 rateAtKink = baseRate + (kink * slope1) / BASIS_POINTS;
+        return rateAtKink + ((utilization - kink) * slope2) / BASIS_POINTS;
+    }
+    
+    /**
+     * @dev Get the annual rate earned by suppliers
+     * @param cash Idle pool liquidity
+     * @param borrows Outstanding borrows
+     * @param reserves Protocol reserves
+     * @param reserveFactor Share of interest kept as reserves (in basis points)
+     * @return Supply rate in basis points
+     */
+    function getSupplyRate(
+        uint256 cash,
+        uint256 borrows,
+        uint256 reserves,
+        uint256 reserveFactor
+    ) external view override returns (uint256) {
+        require(reserveFactor <= BASIS_POINTS, "Invalid reserve factor");
+        
+        uint256 utilization = utilizationRate(cash, borrows, reserves);
+        uint256 borrowRate = getBorrowRate(cash, borrows, reserves);
+        
+        // Suppliers earn what borrowers pay, spread over all pool assets, minus reserves
+        return (borrowRate * utilization * (BASIS_POINTS - reserveFactor)) / (BASIS_POINTS * BASIS_POINTS);
+    }
+}
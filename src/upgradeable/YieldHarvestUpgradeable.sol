@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.22;
+
+import "@openzeppelin/contracts/proxy/utils/Initializable.sol";
+import "@openzeppelin/contracts/proxy/utils/UUPSUpgradeable.sol";
+import "../YieldHarvest.sol";
+
+/**
+ * @title YieldHarvestUpgradeable
+ * @dev UUPS implementation of YieldHarvest, deployed behind an ERC1967Proxy
+ * Features:
+ * - Initializer replaces the constructor
+ * - Upgrades are authorized by a dedicated upgrader, intended to be a TimelockController
+ * - The owner keeps pool configuration without being able to swap the implementation
+ */
+contract YieldHarvestUpgradeable is YieldHarvest, Initializable, UUPSUpgradeable {
+    
+    // ============ STATE VARIABLES ============
+    address public upgrader; // Only account allowed to authorize upgrades
+    
+    // Reserved storage for future proxy-only variables
+    uint256[49] private __gap;
+    
+    // ============ EVENTS ============
+    event UpgraderUpdated(address indexed previousUpgrader, address indexed newUpgrader);
+    
+    // ============ MODIFIERS ============
+    modifier onlyUpgrader() {
+        require(msg.sender == upgrader, "Not upgrader");
+        _;
+    }
+    
+    // ============ CONSTRUCTOR ============
+    
+    /// @custom:oz-upgrades-unsafe-allow constructor
+    constructor() {
+        _disableInitializers();
+    }
+    
+    /**
+     * @dev Initialize the proxy state
+     * @param initialOwner Owner of the pool configuration
+     * @param initialUpgrader Account allowed to upgrade the implementation
+     */
+    function initialize(address initialOwner, address initialUpgrader) external initializer {
+        require(initialOwner != address(0), "Invalid owner");
+        require(initialUpgrader != address(0), "Invalid upgrader");
+        
+        _transferOwnership(initialOwner);
+        
+        upgrader = initialUpgrader;
+        emit UpgraderUpdated(address(0), initialUpgrader);
+    }
+    
+    // ============ ADMIN FUNCTIONS ============
+    
+    /**
+     * @dev Hand the upgrade role to a new account (scheduled through the timelock itself)
+     * @param newUpgrader Address of the new upgrader
+     */
+    function setUpgrader(address newUpgrader) external onlyUpgrader {
+        require(newUpgrader != address(0), "Invalid upgrader");
+        
+        emit UpgraderUpdated(upgrader, newUpgrader);
+        upgrader = newUpgrader;
+    }
+    
+    // ============ VIEW FUNCTIONS ============
+    
+    /**
+     * @dev Implementation version, bumped by every upgrade
+     */
+    function version() external pure virtual returns (string memory) {
+        return "1";
+    }
+    
+    // ============ INTERNAL FUNCTIONS ============
+    
+    function _authorizeUpgrade(address) internal view override onlyUpgrader {}
+}
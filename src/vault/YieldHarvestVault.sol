@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import "@openzeppelin/contracts/token/ERC20/ERC20.sol";
+import "@openzeppelin/contracts/token/ERC20/extensions/ERC4626.sol";
+import "@openzeppelin/contracts/token/ERC20/utils/SafeERC20.sol";
+import "@openzeppelin/contracts/access/Ownable.sol";
+import "@openzeppelin/contracts/utils/ReentrancyGuard.sol";
+import "@openzeppelin/contracts/utils/math/Math.sol";
+import "../YieldHarvest.sol";
+
+/**
+ * @title YieldHarvestVault
+ * @dev ERC-4626 vault auto-compounding a single YieldHarvest pool
+ * Features:
+ * - Deposits are pooled into one FLEXIBLE stake held by the vault
+ * - Anyone can compound the stake's rewards and earn a bounty on them
+ * - Withdrawals take assets out of the position, closing it only below the pool minimum
+ * - Rewards paid out when the position changes keep their bounty for the next compounder
+ * - Assets the position has no room for stay idle and still count towards totalAssets
+ * The pool must pay rewards in its staked token, which is the vault asset.
+ */
+contract YieldHarvestVault is ERC4626, Ownable, ReentrancyGuard {
+    using SafeERC20 for IERC20;
+    
+    // ============ CONSTANTS ============
+    uint256 public constant BASIS_POINTS = 10000;
+    uint256 public constant MAX_COMPOUND_BOUNTY = 500; // 5%
+    
+    // ============ STATE VARIABLES ============
+    YieldHarvest public immutable yieldHarvest;
+    uint256 public compoundBounty; // Share of compounded rewards paid to the caller (basis points)
+    uint256 public stakeId;        // Vault position, valid while hasPosition
+    bool public hasPosition;
+    uint256 public accruedBounty;  // Bounty set aside on rewards paid out outside compound, held idle
+    
+    // ============ EVENTS ============
+    event Compounded(address indexed caller, uint256 rewards, uint256 bounty);
+    event CompoundBountyUpdated(uint256 bounty);
+    
+    // ============ CONSTRUCTOR ============
+    
+    /**
+     * @param _yieldHarvest Staking contract
+     * @param _asset Staked token of the pool, also its reward token
+     * @param _compoundBounty Initial compound bounty (basis points)
+     */
+    constructor(
+        YieldHarvest _yieldHarvest,
+        IERC20 _asset,
+        string memory name,
+        string memory symbol,
+        uint256 _compoundBounty
+    ) ERC4626(_asset) ERC20(name, symbol) Ownable(msg.sender) {
+        require(_compoundBounty <= MAX_COMPOUND_BOUNTY, "Bounty too high");
+        
+        YieldHarvest.PoolConfig memory pool = _yieldHarvest.getPoolConfig(address(_asset));
+        require(pool.isActive, "Pool does not exist");
+        require(pool.rewardToken == address(_asset), "Reward token differs from asset");
+        
+        yieldHarvest = _yieldHarvest;
+        compoundBounty = _compoundBounty;
+        _asset.forceApprove(address(_yieldHarvest), type(uint256).max);
+    }
+    
+    // ============ COMPOUNDING ============
+    
+    /**
+     * @dev Harvest the position's rewards, pay the caller's bounty and restake the rest
+     * @return bounty Amount paid to the caller
+     */
+    function compound() external nonReentrant returns (uint256 bounty) {
+        require(hasPosition && yieldHarvest.calculatePendingRewards(stakeId) > 0, "Nothing to compound");
+        
+        IERC20 token = IERC20(asset());
+        uint256 balanceBefore = token.balanceOf(address(this));
+        yieldHarvest.harvest(stakeId, false);
+        uint256 rewards = token.balanceOf(address(this)) - balanceBefore;
+        
+        bounty = (rewards * compoundBounty) / BASIS_POINTS + accruedBounty;
+        accruedBounty = 0;
+        if (bounty > 0) {
+            token.safeTransfer(msg.sender, bounty);
+        }
+        _stakeIdle();
+        
+        emit Compounded(msg.sender, rewards, bounty);
+    }
+    
+    // ============ ADMIN FUNCTIONS ============
+    
+    /**
+     * @dev Update the compound bounty
+     * @param bounty New bounty (basis points)
+     */
+    function setCompoundBounty(uint256 bounty) external onlyOwner {
+        require(bounty <= MAX_COMPOUND_BOUNTY, "Bounty too high");
+        compoundBounty = bounty;
+        emit CompoundBountyUpdated(bounty);
+    }
+    
+    // ============ ERC-4626 ============
+    
+    /**
+     * @dev Idle assets, the staked position and its pending rewards net of fees and bounty
+     */
+    function totalAssets() public view override returns (uint256 assets) {
+        assets = _idleAssets();
+        if (!hasPosition) return assets;
+        
+        (,,, uint256 staked,,,,,) = yieldHarvest.getStakeDetails(stakeId);
+        uint256 rewards = _netPendingRewards();
+        return assets + staked + rewards - (rewards * compoundBounty) / BASIS_POINTS;
+    }
+    
+    /**
+     * @dev Deposits are limited by the pool's per-stake maximum and capacity
+     */
+    function maxDeposit(address) public view override returns (uint256) {
+        uint256 room = _stakeRoom();
+        uint256 idle = _idleAssets();
+        return room > idle ? room - idle : 0;
+    }
+    
+    function maxMint(address receiver) public view override returns (uint256) {
+        return _convertToShares(maxDeposit(receiver), Math.Rounding.Floor);
+    }
+    
+    function deposit(uint256 assets, address receiver) public override nonReentrant returns (uint256) {
+        return super.deposit(assets, receiver);
+    }
+    
+    function mint(uint256 shares, address receiver) public override nonReentrant returns (uint256) {
+        return super.mint(shares, receiver);
+    }
+    
+    function withdraw(uint256 assets, address receiver, address shareOwner) public override nonReentrant returns (uint256) {
+        return super.withdraw(assets, receiver, shareOwner);
+    }
+    
+    function redeem(uint256 shares, address receiver, address shareOwner) public override nonReentrant returns (uint256) {
+        return super.redeem(shares, receiver, shareOwner);
+    }
+    
+    // ============ INTERNAL FUNCTIONS ============
+    
+    function _deposit(address caller, address receiver, uint256 assets, uint256 shares) internal override {
+        super._deposit(caller, receiver, assets, shares);
+        _stakeIdle();
+    }
+    
+    /**
+     * @dev Take what idle assets don't cover out of the position, restaking what is left idle after
+     */
+    function _withdraw(
+        address caller,
+        address receiver,
+        address shareOwner,
+        uint256 assets,
+        uint256 shares
+    ) internal override {
+        uint256 idle = _idleAssets();
+        if (hasPosition && idle < assets) {
+            _unstake(assets - idle);
+        }
+        
+        super._withdraw(caller, receiver, shareOwner, assets, shares);
+        _stakeIdle();
+    }
+    
+    /**
+     * @dev Withdraw an amount from the position, closing it when the rest would fall below the pool minimum
+     */
+    function _unstake(uint256 amount) internal {
+        _accrueBounty();
+        
+        (,,, uint256 staked,,,,,) = yieldHarvest.getStakeDetails(stakeId);
+        if (amount < staked && staked - amount >= yieldHarvest.getPoolConfig(asset()).minStakeAmount) {
+            yieldHarvest.withdrawFromStake(stakeId, amount);
+        } else {
+            hasPosition = false;
+            yieldHarvest.unstake(stakeId);
+        }
+    }
+    
+    /**
+     * @dev Stake the vault's idle assets into its position, opening one once the pool minimum is reached
+     * Only stakes what the position and the pool have room for, the rest stays idle.
+     */
+    function _stakeIdle() internal {
+        uint256 amount = Math.min(_idleAssets(), _stakeRoom());
+        if (amount == 0) return;
+        
+        if (hasPosition) {
+            _accrueBounty();
+            yieldHarvest.increaseStakeAmount(stakeId, amount);
+            return;
+        }
+        
+        if (amount >= yieldHarvest.getPoolConfig(asset()).minStakeAmount) {
+            stakeId = yieldHarvest.createStake(asset(), amount, YieldHarvest.StakeType.FLEXIBLE, address(0));
+            hasPosition = true;
+        }
+    }
+    
+    /**
+     * @dev Amount the position can still take, limited by the pool's per-stake maximum and capacity
+     */
+    function _stakeRoom() internal view returns (uint256) {
+        YieldHarvest.PoolConfig memory pool = yieldHarvest.getPoolConfig(asset());
+        
+        uint256 staked = 0;
+        if (hasPosition) {
+            (,,, staked,,,,,) = yieldHarvest.getStakeDetails(stakeId);
+        }
+        
+        uint256 stakeRoom = pool.maxStakeAmount > staked ? pool.maxStakeAmount - staked : 0;
+        uint256 poolRoom = pool.poolCap > pool.totalStaked ? pool.poolCap - pool.totalStaked : 0;
+        return Math.min(stakeRoom, poolRoom);
+    }
+    
+    /**
+     * @dev Set aside the bounty on pending rewards the pool is about to pay out with a position change
+     * Keeps the share price where totalAssets valued those rewards, net of the bounty.
+     */
+    function _accrueBounty() internal {
+        accruedBounty += (_netPendingRewards() * compoundBounty) / BASIS_POINTS;
+    }
+    
+    /**
+     * @dev Pending rewards of the position net of the pool's performance fee
+     */
+    function _netPendingRewards() internal view returns (uint256 rewards) {
+        rewards = yieldHarvest.calculatePendingRewards(stakeId);
+        rewards -= (rewards * yieldHarvest.getPoolConfig(asset()).performanceFee) / BASIS_POINTS;
+    }
+    
+    /**
+     * @dev Vault balance not owed to the next compounder
+     */
+    function _idleAssets() internal view returns (uint256) {
+        return IERC20(asset()).balanceOf(address(this)) - accruedBounty;
+    }
+}
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test} from "forge-std/Test.sol";
+import {JumpRateModel} from "../src/rates/JumpRateModel.sol";
+
+contract JumpRateModelTest is Test {
+    JumpRateModel public model;
+
+    function setUp() public {
+        // 2% base, +10% up to the 80% kink, +100% above it
+        model = new JumpRateModel(200, 1000, 8000, 10000);
+    }
+
+    function test_BorrowRate_BelowAndAboveKink() public view {
+        assertEq(model.getBorrowRate(100, 0, 0), 200);
+        // 50% utilization
+        assertEq(model.getBorrowRate(50, 50, 0), 700);
+        // 80% utilization, exactly at the kink
+        assertEq(model.getBorrowRate(20, 80, 0), 1000);
+        // 90% utilization
+        assertEq(model.getBorrowRate(10, 90, 0), 2000);
+    }
+
+    function test_Utilization_ExcludesReserves() public view {
+        // 60 borrowed out of 100 - 20 reserves = 75%
+        assertEq(model.utilizationRate(40, 60, 0), 6000);
+        assertEq(model.utilizationRate(40, 60, 20), 7500);
+    }
+
+    function test_Utilization_CappedWhenReservesExceedSupplierAssets() public view {
+        // Supplier assets of 100 - 150 reserves would divide by zero or go negative
+        assertEq(model.utilizationRate(0, 100, 100), 10000);
+        assertEq(model.utilizationRate(50, 100, 150), 10000);
+        // 100 / (10 + 100 - 20) would be above 100%
+        assertEq(model.utilizationRate(10, 100, 20), 10000);
+        assertEq(model.getBorrowRate(0, 100, 100), 200 + 800 + 2000);
+    }
+
+    function test_SupplyRate_IsBorrowRateNetOfReserveFactor() public view {
+        // 50% utilization, 700 borrow rate, 10% reserve factor
+        assertEq(model.getSupplyRate(50, 50, 0, 1000), (700 * 5000 * 9000) / (10000 * 10000));
+        assertEq(model.getSupplyRate(50, 50, 0, 0), 350);
+    }
+
+    function test_RevertWhen_InvalidKink() public {
+        vm.expectRevert(bytes("Invalid kink"));
+        new JumpRateModel(200, 1000, 10001, 10000);
+    }
+}
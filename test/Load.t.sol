@@ -0,0 +1,1105 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test} from "forge-std/Test.sol";
+import {ERC20TokenLoan} from "../src/Load.sol";
+import {ChainlinkOracleAdapter} from "../src/oracles/ChainlinkOracleAdapter.sol";
+import {JumpRateModel} from "../src/rates/JumpRateModel.sol";
+import {FlashLoanBorrower} from "../src/flash/FlashLoanBorrower.sol";
+import {ReentrancyGuard} from "@openzeppelin/contracts/utils/ReentrancyGuard.sol";
+import {MockERC20} from "./mocks/MockERC20.sol";
+import {MockAggregatorV3} from "./mocks/MockAggregatorV3.sol";
+import {MockFlashBorrower} from "./mocks/MockFlashBorrower.sol";
+
+contract ERC20TokenLoanTest is Test {
+    ERC20TokenLoan public loan;
+    JumpRateModel public rateModel;
+    
+    MockERC20 public usdc;
+    MockERC20 public weth;
+    MockAggregatorV3 public usdcFeed;
+    MockAggregatorV3 public wethFeed;
+    
+    address public lender = makeAddr("lender");
+    address public borrower = makeAddr("borrower");
+    address public liquidator = makeAddr("liquidator");
+    address public lender2 = makeAddr("lender2");
+    
+    function setUp() public virtual {
+        loan = _deployLoan();
+        
+        usdc = new MockERC20("USD Coin", "USDC", 6);
+        weth = new MockERC20("Wrapped Ether", "WETH", 18);
+        
+        usdcFeed = new MockAggregatorV3(8, 1e8);
+        wethFeed = new MockAggregatorV3(8, 2000e8);
+        
+        // 5% base, +2% up to the 80% kink, +30% above it
+        rateModel = new JumpRateModel(500, 200, 8000, 3000);
+        
+        loan.configureToken(address(usdc), true, 15000, 30 days, address(rateModel), 0);
+        loan.approveCollateral(address(usdc), address(weth), true);
+        loan.setPriceFeed(address(usdc), address(new ChainlinkOracleAdapter(address(usdc), address(usdcFeed), 1 hours)), address(0), 0);
+        loan.setPriceFeed(address(weth), address(new ChainlinkOracleAdapter(address(weth), address(wethFeed), 1 hours)), address(0), 0);
+        loan.setLiquidationBonus(address(weth), 500);
+        
+        usdc.mint(lender, 100_000e6);
+        usdc.mint(liquidator, 100_000e6);
+        weth.mint(borrower, 10 ether);
+        
+        vm.prank(lender);
+        usdc.approve(address(loan), type(uint256).max);
+        vm.prank(liquidator);
+        usdc.approve(address(loan), type(uint256).max);
+        vm.startPrank(borrower);
+        usdc.approve(address(loan), type(uint256).max);
+        weth.approve(address(loan), type(uint256).max);
+        vm.stopPrank();
+        
+        vm.prank(lender);
+        loan.depositLiquidity(address(usdc), 100_000e6);
+    }
+    
+    function _deployLoan() internal virtual returns (ERC20TokenLoan) {
+        return new ERC20TokenLoan();
+    }
+    
+    function _borrow(uint256 amount, uint256 collateral) internal returns (uint256) {
+        vm.prank(borrower);
+        return loan.requestLoan(address(usdc), address(weth), amount, collateral, 30 days);
+    }
+    
+    // ============ ORACLES ============
+    
+    function test_RequestLoan_ValuesCollateralAtOraclePrice() public {
+        // 1 WETH at $2000 covers 1300 USDC at 150%
+        uint256 loanId = _borrow(1300e6, 1 ether);
+        
+        assertEq(usdc.balanceOf(borrower), 1300e6);
+        assertEq(loan.getLoan(loanId).collateralAmount, 1 ether);
+    }
+    
+    function test_RevertWhen_CollateralBelowRatioAtOraclePrice() public {
+        vm.expectRevert(bytes("Insufficient collateral"));
+        _borrow(1400e6, 1 ether);
+    }
+    
+    function test_LiquidateLoan_AfterPriceDrop() public {
+        uint256 loanId = _borrow(1000e6, 1 ether);
+        
+        vm.expectRevert(bytes("Loan not liquidatable"));
+        vm.prank(liquidator);
+        loan.liquidateLoan(loanId, type(uint256).max);
+        
+        wethFeed.setAnswer(1300e8);
+        
+        vm.prank(liquidator);
+        loan.liquidateLoan(loanId, type(uint256).max);
+        
+        assertTrue(loan.getLoan(loanId).isActive);
+        assertGt(weth.balanceOf(liquidator), 0);
+    }
+    
+    function test_RevertWhen_PriceStale() public {
+        vm.warp(block.timestamp + 2 hours);
+        
+        vm.expectRevert(bytes("Stale price"));
+        _borrow(1000e6, 1 ether);
+    }
+    
+    function test_RevertWhen_NoPriceFeed() public {
+        MockERC20 other = new MockERC20("Other", "OTH", 18);
+        loan.approveCollateral(address(usdc), address(other), true);
+        other.mint(borrower, 1 ether);
+        
+        vm.startPrank(borrower);
+        other.approve(address(loan), 1 ether);
+        vm.expectRevert(bytes("No price feed"));
+        loan.requestLoan(address(usdc), address(other), 100e6, 1 ether, 30 days);
+        vm.stopPrank();
+    }
+    
+    function test_RevertWhen_OraclesDeviate() public {
+        MockAggregatorV3 backupFeed = new MockAggregatorV3(18, 2300e18);
+        loan.setPriceFeed(
+            address(weth),
+            address(new ChainlinkOracleAdapter(address(weth), address(wethFeed), 1 hours)),
+            address(new ChainlinkOracleAdapter(address(weth), address(backupFeed), 1 hours)),
+            500
+        );
+        
+        vm.expectRevert(bytes("Price deviation too high"));
+        loan.getTokenPrice(address(weth));
+        
+        backupFeed.setAnswer(2050e18);
+        assertEq(loan.getTokenPrice(address(weth)), 2000e18);
+    }
+    
+    // ============ LENDER SHARES ============
+    
+    function test_Deposit_KeepsPositionsPerToken() public {
+        loan.configureToken(address(weth), true, 15000, 30 days, address(rateModel), 0);
+        weth.mint(lender, 5 ether);
+        vm.startPrank(lender);
+        weth.approve(address(loan), 5 ether);
+        loan.depositLiquidity(address(weth), 5 ether);
+        vm.stopPrank();
+        
+        (uint256 usdcShares,,) = loan.lenderPositions(address(usdc), lender);
+        (uint256 wethShares,,) = loan.lenderPositions(address(weth), lender);
+        assertEq(usdcShares, 100_000e6);
+        assertEq(wethShares, 5 ether);
+        assertEq(loan.balanceOfUnderlying(address(weth), lender), 5 ether);
+        
+        vm.prank(lender);
+        vm.expectRevert(bytes("Insufficient shares"));
+        loan.withdrawLiquidity(address(weth), 6 ether);
+    }
+    
+    function test_RepaidInterest_FlowsProRataToLenders() public {
+        usdc.mint(lender2, 50_000e6);
+        vm.startPrank(lender2);
+        usdc.approve(address(loan), type(uint256).max);
+        loan.depositLiquidity(address(usdc), 50_000e6);
+        vm.stopPrank();
+        
+        uint256 loanId = _borrow(10_000e6, 8 ether);
+        vm.warp(block.timestamp + 10 days);
+        
+        uint256 interest = loan.getLoanDebt(loanId) - 10_000e6;
+        assertGt(interest, 0);
+        
+        usdc.mint(borrower, interest);
+        vm.prank(borrower);
+        loan.repayLoan(loanId, type(uint256).max);
+        
+        assertApproxEqAbs(loan.balanceOfUnderlying(address(usdc), lender), 100_000e6 + (interest * 2) / 3, 1);
+        assertApproxEqAbs(loan.balanceOfUnderlying(address(usdc), lender2), 50_000e6 + interest / 3, 1);
+        
+        (uint256 shares,,) = loan.lenderPositions(address(usdc), lender2);
+        vm.prank(lender2);
+        uint256 redeemed = loan.redeemShares(address(usdc), shares);
+        
+        assertEq(usdc.balanceOf(lender2), redeemed);
+        assertGt(redeemed, 50_000e6);
+    }
+    
+    function test_WithdrawLiquidity_LimitedByIdleLiquidity() public {
+        _borrow(10_000e6, 8 ether);
+        
+        vm.prank(lender);
+        vm.expectRevert(bytes("Insufficient liquidity"));
+        loan.withdrawLiquidity(address(usdc), 95_000e6);
+        
+        vm.prank(lender);
+        loan.withdrawLiquidity(address(usdc), 90_000e6);
+        assertEq(usdc.balanceOf(lender), 90_000e6);
+    }
+    
+    // ============ BORROW INDEX ============
+    
+    function test_RepayLoan_EarlyRepaymentChargesInterestToDate() public {
+        uint256 loanId = _borrow(10_000e6, 8 ether);
+        // 10% utilization: 5% base + 0.2% premium
+        assertEq(loan.getLoan(loanId).interestRate, 520);
+        
+        vm.warp(block.timestamp + 10 days);
+        uint256 expectedInterest = (10_000e6 * 520 * 10 days) / (10000 * 365 days);
+        assertApproxEqAbs(loan.getLoanDebt(loanId), 10_000e6 + expectedInterest, 1);
+        
+        usdc.mint(borrower, expectedInterest + 1);
+        vm.prank(borrower);
+        loan.repayLoan(loanId, type(uint256).max);
+        
+        assertFalse(loan.getLoan(loanId).isActive);
+        assertEq(weth.balanceOf(borrower), 10 ether);
+        assertEq(loan.totalBorrowed(address(usdc)), 0);
+    }
+    
+    function test_RepayLoan_Partial() public {
+        uint256 loanId = _borrow(10_000e6, 8 ether);
+        vm.warp(block.timestamp + 10 days);
+        
+        uint256 debtBefore = loan.getLoanDebt(loanId);
+        vm.prank(borrower);
+        loan.repayLoan(loanId, 4_000e6);
+        
+        assertTrue(loan.getLoan(loanId).isActive);
+        assertEq(weth.balanceOf(borrower), 2 ether);
+        assertApproxEqAbs(loan.getLoanDebt(loanId), debtBefore - 4_000e6, 1);
+        
+        usdc.mint(borrower, 100e6);
+        vm.prank(borrower);
+        loan.repayLoan(loanId, type(uint256).max);
+        
+        assertFalse(loan.getLoan(loanId).isActive);
+        assertEq(weth.balanceOf(borrower), 10 ether);
+    }
+    
+    function test_AccrueInterest_GrowsPoolAssets() public {
+        uint256 loanId = _borrow(10_000e6, 8 ether);
+        
+        vm.warp(block.timestamp + 10 days);
+        uint256 debtAfter10Days = loan.getLoanDebt(loanId);
+        uint256 assetsAfter10Days = loan.totalPoolAssets(address(usdc));
+        
+        loan.accrueInterest(address(usdc));
+        assertApproxEqAbs(loan.totalBorrowed(address(usdc)), debtAfter10Days, 1);
+        assertEq(loan.totalPoolAssets(address(usdc)), assetsAfter10Days);
+        
+        vm.warp(block.timestamp + 10 days);
+        assertGt(loan.getLoanDebt(loanId), debtAfter10Days);
+    }
+    
+    // ============ RATE MODEL ============
+    
+    function test_RevertWhen_RateModelExceedsMaxRate() public {
+        JumpRateModel steep = new JumpRateModel(500, 2000, 8000, 50000);
+        
+        vm.expectRevert(bytes("Interest rate too high"));
+        loan.configureToken(address(usdc), true, 15000, 30 days, address(steep), 0);
+    }
+    
+    function test_ReserveFactor_SplitsInterestBetweenLendersAndReserves() public {
+        loan.configureToken(address(usdc), true, 15000, 30 days, address(rateModel), 1000);
+        _borrow(10_000e6, 8 ether);
+        
+        uint256 borrowRate = loan.getBorrowRate(address(usdc));
+        assertEq(borrowRate, 520);
+        // 520 * 10% utilization * 90% to lenders
+        assertEq(loan.getSupplyRate(address(usdc)), 46);
+        
+        uint256 assetsBefore = loan.totalPoolAssets(address(usdc));
+        vm.warp(block.timestamp + 30 days);
+        loan.accrueInterest(address(usdc));
+        
+        uint256 interest = loan.totalBorrowed(address(usdc)) - 10_000e6;
+        assertEq(loan.totalReserves(address(usdc)), interest / 10);
+        assertApproxEqAbs(loan.totalPoolAssets(address(usdc)) - assetsBefore, interest - interest / 10, 1);
+    }
+    
+    // ============ LIQUIDATION ============
+    
+    function test_LiquidateLoan_PartialUpToCloseFactor() public {
+        uint256 loanId = _borrow(1000e6, 1 ether);
+        wethFeed.setAnswer(1300e8);
+        
+        vm.prank(liquidator);
+        loan.liquidateLoan(loanId, type(uint256).max);
+        
+        // 50% of the debt repaid, collateral worth 500 USD + 5% bonus seized at 1300 USD
+        ERC20TokenLoan.Loan memory position = loan.getLoan(loanId);
+        uint256 seized = uint256(525e18) / 1300;
+        uint256 penalty = uint256(25e18) / 1300;
+        assertEq(usdc.balanceOf(liquidator), 100_000e6 - 500e6);
+        assertEq(weth.balanceOf(liquidator), seized);
+        // Another 5% of the repaid value goes to reserves
+        assertEq(loan.totalReserves(address(weth)), penalty);
+        assertEq(position.collateralAmount, 1 ether - seized - penalty);
+        assertApproxEqAbs(loan.getLoanDebt(loanId), 500e6, 1);
+        assertFalse(position.isLiquidated);
+        
+        // Repayment reached the pool's books
+        assertEq(loan.totalLiquidity(address(usdc)), 99_500e6);
+        
+        // Position is healthy again
+        vm.expectRevert(bytes("Loan not liquidatable"));
+        vm.prank(liquidator);
+        loan.liquidateLoan(loanId, type(uint256).max);
+    }
+    
+    function test_LiquidateLoan_OverdueClosesAndReturnsLeftover() public {
+        uint256 loanId = _borrow(1000e6, 1 ether);
+        
+        vm.warp(block.timestamp + 31 days);
+        wethFeed.setAnswer(2000e8);
+        usdcFeed.setAnswer(1e8);
+        
+        uint256 debt = loan.getLoanDebt(loanId);
+        uint256 expectedSeized = (debt * 1e12 * 10500) / 10000 / 2000;
+        uint256 expectedPenalty = (debt * 1e12 * 500) / 10000 / 2000;
+        
+        vm.prank(liquidator);
+        loan.liquidateLoan(loanId, type(uint256).max);
+        
+        ERC20TokenLoan.Loan memory position = loan.getLoan(loanId);
+        assertFalse(position.isActive);
+        assertTrue(position.isLiquidated);
+        assertEq(position.collateralAmount, 0);
+        assertEq(weth.balanceOf(liquidator), expectedSeized);
+        assertEq(weth.balanceOf(borrower), 10 ether - expectedSeized - expectedPenalty);
+        assertEq(loan.totalReserves(address(weth)), expectedPenalty);
+        assertEq(loan.totalBorrowed(address(usdc)), 0);
+    }
+    
+    function test_RevertWhen_CloseFactorOutOfRange() public {
+        vm.expectRevert(bytes("Invalid close factor"));
+        loan.setCloseFactor(10001);
+        
+        loan.setCloseFactor(10000);
+        assertEq(loan.closeFactor(), 10000);
+    }
+    
+    // ============ P2P ORDER BOOK ============
+    
+    function _wethOnly() internal view returns (address[] memory collaterals) {
+        collaterals = new address[](1);
+        collaterals[0] = address(weth);
+    }
+    
+    function _fixedRateDebt(uint256 principal, uint256 rate, uint256 elapsed) internal pure returns (uint256) {
+        uint256 index = 1e18 + (1e18 * rate * elapsed) / (10000 * 365 days);
+        return (principal * index + 1e18 - 1) / 1e18;
+    }
+    
+    function test_AcceptLoanOffer_RepaysLenderAtFixedRate() public {
+        usdc.mint(lender2, 5000e6);
+        vm.startPrank(lender2);
+        usdc.approve(address(loan), type(uint256).max);
+        uint256 offerId = loan.createLoanOffer(address(usdc), 5000e6, 1000, 30 days, _wethOnly());
+        vm.stopPrank();
+        
+        vm.prank(borrower);
+        uint256 loanId = loan.acceptLoanOffer(offerId, 1000e6, address(weth), 1 ether, 30 days);
+        
+        assertEq(usdc.balanceOf(borrower), 1000e6);
+        assertEq(loan.getLoanOffer(offerId).amountAvailable, 4000e6);
+        // Pool liquidity is untouched by P2P loans
+        assertEq(loan.totalLiquidity(address(usdc)), 100_000e6);
+        
+        vm.warp(block.timestamp + 15 days);
+        uint256 debt = loan.getLoanDebt(loanId);
+        assertEq(debt, _fixedRateDebt(1000e6, 1000, 15 days));
+        
+        usdc.mint(borrower, debt - 1000e6);
+        vm.prank(borrower);
+        loan.repayLoan(loanId, type(uint256).max);
+        
+        assertEq(usdc.balanceOf(lender2), debt);
+        assertEq(weth.balanceOf(borrower), 10 ether);
+        assertFalse(loan.getLoan(loanId).isActive);
+        
+        // Unused escrow is returned on cancel
+        vm.prank(lender2);
+        loan.cancelLoanOffer(offerId);
+        assertEq(usdc.balanceOf(lender2), debt + 4000e6);
+        assertEq(loan.getActiveLoanOffers(address(usdc)).length, 0);
+    }
+    
+    function test_MatchLoanOffer_FillsRequestAtOfferRate() public {
+        usdc.mint(lender2, 5000e6);
+        vm.startPrank(lender2);
+        usdc.approve(address(loan), type(uint256).max);
+        uint256 offerId = loan.createLoanOffer(address(usdc), 5000e6, 800, 30 days, _wethOnly());
+        vm.stopPrank();
+        
+        vm.prank(borrower);
+        uint256 requestId = loan.createLoanRequest(address(usdc), 2000e6, address(weth), 2 ether, 1200, 20 days);
+        assertEq(weth.balanceOf(address(loan)), 2 ether);
+        assertEq(loan.getActiveLoanRequests(address(usdc))[0], requestId);
+        
+        vm.prank(liquidator);
+        uint256 loanId = loan.matchLoanOffer(offerId, requestId);
+        
+        ERC20TokenLoan.Loan memory position = loan.getLoan(loanId);
+        assertEq(position.lender, lender2);
+        assertEq(position.interestRate, 800);
+        assertEq(position.dueTime, block.timestamp + 20 days);
+        assertEq(usdc.balanceOf(borrower), 2000e6);
+        assertEq(loan.getLoanOffer(offerId).amountAvailable, 3000e6);
+        assertEq(loan.getActiveLoanRequests(address(usdc)).length, 0);
+    }
+    
+    function test_FillLoanRequest_AndCancelRequest() public {
+        vm.startPrank(borrower);
+        uint256 filledId = loan.createLoanRequest(address(usdc), 1000e6, address(weth), 1 ether, 1000, 10 days);
+        uint256 cancelledId = loan.createLoanRequest(address(usdc), 1000e6, address(weth), 1 ether, 1000, 10 days);
+        loan.cancelLoanRequest(cancelledId);
+        vm.stopPrank();
+        
+        assertEq(weth.balanceOf(borrower), 9 ether);
+        
+        vm.expectRevert(bytes("Request not active"));
+        vm.prank(liquidator);
+        loan.fillLoanRequest(cancelledId);
+        
+        vm.prank(liquidator);
+        uint256 loanId = loan.fillLoanRequest(filledId);
+        
+        assertEq(loan.getLoan(loanId).lender, liquidator);
+        assertEq(usdc.balanceOf(borrower), 1000e6);
+    }
+    
+    function test_RevertWhen_OfferRateAboveRequestRate() public {
+        usdc.mint(lender2, 5000e6);
+        vm.startPrank(lender2);
+        usdc.approve(address(loan), type(uint256).max);
+        uint256 offerId = loan.createLoanOffer(address(usdc), 5000e6, 1500, 30 days, _wethOnly());
+        vm.stopPrank();
+        
+        vm.prank(borrower);
+        uint256 requestId = loan.createLoanRequest(address(usdc), 1000e6, address(weth), 1 ether, 1000, 10 days);
+        
+        vm.expectRevert(bytes("Rate mismatch"));
+        loan.matchLoanOffer(offerId, requestId);
+    }
+    
+    function test_AcceptSignedLoanOffer() public {
+        (address signer, uint256 signerKey) = makeAddrAndKey("signer");
+        usdc.mint(signer, 5000e6);
+        vm.prank(signer);
+        usdc.approve(address(loan), type(uint256).max);
+        
+        ERC20TokenLoan.SignedLoanOffer memory offer = ERC20TokenLoan.SignedLoanOffer({
+            lender: signer,
+            loanToken: address(usdc),
+            amount: 1500e6,
+            interestRate: 900,
+            maxTerm: 30 days,
+            acceptedCollaterals: _wethOnly(),
+            nonce: 1,
+            deadline: block.timestamp + 1 days
+        });
+        (uint8 v, bytes32 r, bytes32 s) = vm.sign(signerKey, loan.hashLoanOffer(offer));
+        bytes memory signature = abi.encodePacked(r, s, v);
+        
+        vm.prank(borrower);
+        uint256 loanId = loan.acceptSignedLoanOffer(offer, signature, 1000e6, address(weth), 1 ether, 30 days);
+        
+        assertEq(loan.getLoan(loanId).lender, signer);
+        assertEq(usdc.balanceOf(signer), 4000e6);
+        assertEq(usdc.balanceOf(borrower), 1000e6);
+        
+        // Only the unfilled remainder can be taken
+        vm.expectRevert(bytes("Invalid amount"));
+        vm.prank(borrower);
+        loan.acceptSignedLoanOffer(offer, signature, 600e6, address(weth), 1 ether, 30 days);
+        
+        // Cancelling the nonce kills the rest of the offer
+        vm.prank(signer);
+        loan.cancelSignedLoanOffer(1);
+        vm.expectRevert(bytes("Offer cancelled"));
+        vm.prank(borrower);
+        loan.acceptSignedLoanOffer(offer, signature, 500e6, address(weth), 1 ether, 30 days);
+    }
+    
+    // ============ POSITION MANAGEMENT ============
+    
+    function test_AddAndWithdrawCollateral_EnforcesRatio() public {
+        uint256 loanId = _borrow(1300e6, 1 ether);
+        
+        // 0.9 WETH = $1800 is below 150% of 1300 USDC
+        vm.expectRevert(bytes("Insufficient collateral"));
+        vm.prank(borrower);
+        loan.withdrawCollateral(loanId, 0.1 ether);
+        
+        vm.startPrank(borrower);
+        loan.addCollateral(loanId, 1 ether);
+        loan.withdrawCollateral(loanId, 0.5 ether);
+        vm.stopPrank();
+        
+        assertEq(loan.getLoan(loanId).collateralAmount, 1.5 ether);
+        assertEq(weth.balanceOf(borrower), 8.5 ether);
+        
+        // Withdrawing down to 150% still leaves a buffer above the liquidation threshold
+        vm.prank(borrower);
+        loan.withdrawCollateral(loanId, 0.525 ether);
+        assertEq(loan.healthFactor(loanId), uint256(1950e18) * 1e18 / 1755e18);
+        assertFalse(loan.isLiquidatable(loanId));
+    }
+    
+    function test_RepayLoan_LateChargesFeeToPool() public {
+        uint256 loanId = _borrow(1000e6, 1 ether);
+        vm.warp(block.timestamp + 31 days);
+        
+        uint256 debt = loan.getLoanDebt(loanId);
+        uint256 fee = (debt * 500) / 10000;
+        usdc.mint(borrower, debt + fee - 1000e6);
+        
+        vm.prank(borrower);
+        loan.repayLoan(loanId, type(uint256).max);
+        
+        assertFalse(loan.getLoan(loanId).isActive);
+        assertEq(usdc.balanceOf(borrower), 0);
+        assertEq(loan.totalLiquidity(address(usdc)), 99_000e6 + debt + fee);
+    }
+    
+    function test_ExtendLoan_CappedAtMaxTerm() public {
+        uint256 loanId = _borrow(1000e6, 1 ether);
+        uint256 start = block.timestamp;
+        vm.warp(start + 20 days);
+        wethFeed.setAnswer(2000e8);
+        usdcFeed.setAnswer(1e8);
+        
+        vm.prank(borrower);
+        loan.extendLoan(loanId, 20 days);
+        assertEq(loan.getLoan(loanId).dueTime, start + 50 days);
+        
+        vm.expectRevert(bytes("Loan duration too long"));
+        vm.prank(borrower);
+        loan.extendLoan(loanId, 1);
+    }
+    
+    function test_RefinanceLoan_MovesP2PLoanIntoPool() public {
+        usdc.mint(lender2, 5000e6);
+        vm.startPrank(lender2);
+        usdc.approve(address(loan), type(uint256).max);
+        uint256 offerId = loan.createLoanOffer(address(usdc), 5000e6, 2000, 30 days, _wethOnly());
+        vm.stopPrank();
+        
+        vm.prank(borrower);
+        uint256 loanId = loan.acceptLoanOffer(offerId, 1000e6, address(weth), 1 ether, 30 days);
+        
+        vm.warp(block.timestamp + 10 days);
+        wethFeed.setAnswer(2000e8);
+        usdcFeed.setAnswer(1e8);
+        uint256 debt = loan.getLoanDebt(loanId);
+        
+        vm.prank(borrower);
+        loan.refinanceLoan(loanId, 30 days);
+        
+        ERC20TokenLoan.Loan memory position = loan.getLoan(loanId);
+        assertEq(position.lender, address(0));
+        assertEq(position.dueTime, block.timestamp + 30 days);
+        assertEq(usdc.balanceOf(lender2), debt);
+        assertEq(loan.totalBorrowed(address(usdc)), debt);
+        assertApproxEqAbs(loan.getLoanDebt(loanId), debt, 1);
+    }
+    
+    // ============ FLASH LOANS ============
+    
+    function test_FlashLoan_FeeAccruesToLenders() public {
+        FlashLoanBorrower receiver = new FlashLoanBorrower(address(loan));
+        uint256 fee = loan.flashFee(address(usdc), 50_000e6);
+        assertEq(fee, 45e6);
+        usdc.mint(address(receiver), fee);
+        
+        receiver.flashBorrow(address(usdc), 50_000e6, "");
+        
+        assertEq(usdc.balanceOf(address(receiver)), 0);
+        assertEq(loan.totalLiquidity(address(usdc)), 100_045e6);
+        assertApproxEqAbs(loan.balanceOfUnderlying(address(usdc), lender), 100_045e6, 1);
+    }
+    
+    function test_RevertWhen_FlashLoanNotRepaid() public {
+        MockFlashBorrower receiver = new MockFlashBorrower(address(loan));
+        usdc.mint(address(receiver), 100e6);
+        receiver.setMode(MockFlashBorrower.Mode.NoApproval);
+        
+        vm.expectRevert();
+        receiver.borrow(address(usdc), 1000e6);
+    }
+    
+    function test_RevertWhen_FlashLoanCallbackInvalid() public {
+        MockFlashBorrower receiver = new MockFlashBorrower(address(loan));
+        usdc.mint(address(receiver), 100e6);
+        receiver.setMode(MockFlashBorrower.Mode.WrongReturn);
+        
+        vm.expectRevert(bytes("Invalid flash loan callback"));
+        receiver.borrow(address(usdc), 1000e6);
+    }
+    
+    function test_RevertWhen_FlashLoanReentered() public {
+        MockFlashBorrower receiver = new MockFlashBorrower(address(loan));
+        usdc.mint(address(receiver), 100e6);
+        receiver.setMode(MockFlashBorrower.Mode.Reenter);
+        
+        vm.expectRevert(ReentrancyGuard.ReentrancyGuardReentrantCall.selector);
+        receiver.borrow(address(usdc), 1000e6);
+    }
+    
+    function test_RevertWhen_FlashLoanExceedsLiquidity() public {
+        assertEq(loan.maxFlashLoan(address(weth)), 0);
+        assertEq(loan.maxFlashLoan(address(usdc)), 100_000e6);
+        
+        MockFlashBorrower receiver = new MockFlashBorrower(address(loan));
+        vm.expectRevert(bytes("Insufficient liquidity"));
+        receiver.borrow(address(usdc), 100_001e6);
+    }
+    
+    // ============ RESERVES ============
+    
+    function test_WithdrawReserves_SendsInterestShareToTreasury() public {
+        address treasury = makeAddr("treasury");
+        loan.setTreasury(treasury);
+        loan.configureToken(address(usdc), true, 15000, 30 days, address(rateModel), 2000);
+        
+        _borrow(10_000e6, 8 ether);
+        vm.warp(block.timestamp + 30 days);
+        loan.accrueInterest(address(usdc));
+        
+        uint256 reserves = loan.totalReserves(address(usdc));
+        assertGt(reserves, 0);
+        uint256 assetsBefore = loan.totalPoolAssets(address(usdc));
+        
+        loan.withdrawReserves(address(usdc), reserves);
+        
+        assertEq(usdc.balanceOf(treasury), reserves);
+        assertEq(loan.totalReserves(address(usdc)), 0);
+        // Lenders' claim is unchanged
+        assertEq(loan.totalPoolAssets(address(usdc)), assetsBefore);
+        
+        vm.expectRevert(bytes("Insufficient reserves"));
+        loan.withdrawReserves(address(usdc), 1);
+    }
+    
+    function test_Reserves_CannotBeBorrowedOrWithdrawnByLenders() public {
+        address treasury = makeAddr("treasury");
+        loan.setTreasury(treasury);
+        loan.configureToken(address(usdc), true, 15000, 30 days, address(rateModel), 2000);
+        
+        uint256 loanId = _borrow(10_000e6, 8 ether);
+        vm.warp(block.timestamp + 30 days);
+        usdc.mint(borrower, 1000e6);
+        vm.prank(borrower);
+        loan.repayLoan(loanId, type(uint256).max);
+        
+        // Lenders redeem everything they own, the reserve cash stays behind
+        (uint256 shares,,) = loan.lenderPositions(address(usdc), lender);
+        vm.prank(lender);
+        loan.redeemShares(address(usdc), shares);
+        
+        uint256 reserves = loan.totalReserves(address(usdc));
+        assertGt(reserves, 0);
+        assertGe(loan.totalLiquidity(address(usdc)), reserves);
+        assertEq(loan.getAvailableLiquidity(address(usdc)), loan.totalLiquidity(address(usdc)) - reserves);
+        assertEq(loan.maxFlashLoan(address(usdc)), loan.getAvailableLiquidity(address(usdc)));
+        
+        vm.expectRevert(bytes("Insufficient liquidity"));
+        _borrow(reserves, 1 ether);
+        
+        loan.withdrawReserves(address(usdc), reserves);
+        assertEq(usdc.balanceOf(treasury), reserves);
+    }
+    
+    function test_EmergencyWithdraw_OnlyUntrackedTokens() public {
+        vm.expectRevert(bytes("Token is part of pool accounting"));
+        loan.emergencyWithdraw(address(usdc), 1);
+        
+        vm.expectRevert(bytes("Token is part of pool accounting"));
+        loan.emergencyWithdraw(address(weth), 1);
+        
+        MockERC20 stray = new MockERC20("Stray", "STRAY", 18);
+        stray.mint(address(loan), 1 ether);
+        loan.emergencyWithdraw(address(stray), 1 ether);
+        assertEq(stray.balanceOf(address(this)), 1 ether);
+    }
+    
+    // ============ CROSS-MARGIN ACCOUNTS ============
+    
+    function _depositAccountCollateral(uint256 amount) internal {
+        loan.setCollateralFactor(address(weth), 7500);
+        vm.prank(borrower);
+        loan.depositAccountCollateral(address(weth), amount);
+    }
+    
+    function test_AccountBorrow_AgainstCollateralBasket() public {
+        MockERC20 wbtc = new MockERC20("Wrapped Bitcoin", "WBTC", 8);
+        MockAggregatorV3 wbtcFeed = new MockAggregatorV3(8, 30_000e8);
+        loan.setPriceFeed(address(wbtc), address(new ChainlinkOracleAdapter(address(wbtc), address(wbtcFeed), 1 hours)), address(0), 0);
+        loan.setCollateralFactor(address(wbtc), 7000);
+        _depositAccountCollateral(1 ether);
+        
+        wbtc.mint(borrower, 0.1e8);
+        vm.startPrank(borrower);
+        wbtc.approve(address(loan), type(uint256).max);
+        loan.depositAccountCollateral(address(wbtc), 0.1e8);
+        vm.stopPrank();
+        
+        // 2000 * 75% + 3000 * 70% = 3600 USD of borrowing power
+        assertEq(loan.getAccountCollateralValue(borrower), 3600e18);
+        
+        vm.prank(borrower);
+        loan.borrowFromAccount(address(usdc), 3000e6);
+        
+        (uint256 liquidity, uint256 shortfall) = loan.getAccountLiquidity(borrower);
+        assertEq(liquidity, 600e18);
+        assertEq(shortfall, 0);
+        assertEq(loan.totalBorrowed(address(usdc)), 3000e6);
+        
+        vm.expectRevert(bytes("Insufficient account liquidity"));
+        vm.prank(borrower);
+        loan.borrowFromAccount(address(usdc), 601e6);
+        
+        // Dropping the WBTC leg would leave the account underwater
+        vm.expectRevert(bytes("Insufficient account liquidity"));
+        vm.prank(borrower);
+        loan.withdrawAccountCollateral(address(wbtc), 0.1e8);
+    }
+    
+    function test_AccountRepay_ReleasesCollateral() public {
+        _depositAccountCollateral(2 ether);
+        
+        vm.prank(borrower);
+        loan.borrowFromAccount(address(usdc), 2000e6);
+        
+        vm.warp(block.timestamp + 10 days);
+        uint256 debt = loan.getAccountBorrowBalance(borrower, address(usdc));
+        assertGt(debt, 2000e6);
+        
+        usdc.mint(borrower, debt - 2000e6);
+        wethFeed.setAnswer(2000e8);
+        usdcFeed.setAnswer(1e8);
+        vm.startPrank(borrower);
+        loan.repayAccountBorrow(address(usdc), type(uint256).max);
+        loan.withdrawAccountCollateral(address(weth), 2 ether);
+        vm.stopPrank();
+        
+        (address[] memory collateralTokens, address[] memory borrowTokens) = loan.getAccountTokens(borrower);
+        assertEq(collateralTokens.length, 0);
+        assertEq(borrowTokens.length, 0);
+        assertEq(weth.balanceOf(borrower), 10 ether);
+    }
+    
+    function test_LiquidateAccount_InShortfall() public {
+        _depositAccountCollateral(2 ether);
+        
+        vm.prank(borrower);
+        loan.borrowFromAccount(address(usdc), 2000e6);
+        
+        vm.expectRevert(bytes("Account not liquidatable"));
+        vm.prank(liquidator);
+        loan.liquidateAccount(borrower, address(usdc), address(weth), type(uint256).max);
+        
+        // 2 WETH at 1200 USD weighted at 75% = 1800 USD against 2000 USD of debt
+        wethFeed.setAnswer(1200e8);
+        (, uint256 shortfall) = loan.getAccountLiquidity(borrower);
+        assertEq(shortfall, 200e18);
+        
+        vm.prank(liquidator);
+        loan.liquidateAccount(borrower, address(usdc), address(weth), type(uint256).max);
+        
+        // Half the debt repaid, 1000 USD + 5% bonus seized, 5% penalty to reserves
+        uint256 seized = uint256(1050e18) / 1200;
+        uint256 penalty = uint256(50e18) / 1200;
+        assertEq(weth.balanceOf(liquidator), seized);
+        assertEq(loan.totalReserves(address(weth)), penalty);
+        assertEq(loan.accountCollateral(borrower, address(weth)), 2 ether - seized - penalty);
+        assertApproxEqAbs(loan.getAccountBorrowBalance(borrower, address(usdc)), 1000e6, 1);
+    }
+    
+    // ============ PAUSES AND CAPS ============
+    
+    function test_Guardian_PausesButCannotUnpause() public {
+        address guardian = makeAddr("guardian");
+        loan.setGuardian(guardian);
+        
+        vm.prank(guardian);
+        loan.setActionPaused(address(usdc), ERC20TokenLoan.Action.BORROW, true);
+        
+        vm.expectRevert(bytes("Action paused"));
+        _borrow(1000e6, 1 ether);
+        
+        // Other actions keep working
+        vm.prank(lender);
+        loan.withdrawLiquidity(address(usdc), 1000e6);
+        
+        vm.expectRevert(bytes("Not authorized"));
+        vm.prank(guardian);
+        loan.setActionPaused(address(usdc), ERC20TokenLoan.Action.BORROW, false);
+        
+        loan.setActionPaused(address(usdc), ERC20TokenLoan.Action.BORROW, false);
+        _borrow(1000e6, 1 ether);
+    }
+    
+    function test_PauseRepayAndWithdraw() public {
+        uint256 loanId = _borrow(1000e6, 1 ether);
+        
+        loan.setActionPaused(address(usdc), ERC20TokenLoan.Action.REPAY, true);
+        loan.setActionPaused(address(usdc), ERC20TokenLoan.Action.WITHDRAW, true);
+        
+        vm.expectRevert(bytes("Action paused"));
+        vm.prank(borrower);
+        loan.repayLoan(loanId, type(uint256).max);
+        
+        vm.expectRevert(bytes("Action paused"));
+        vm.prank(lender);
+        loan.withdrawLiquidity(address(usdc), 1000e6);
+        
+        (bool[5] memory paused,,) = loan.getMarketLimits(address(usdc));
+        assertFalse(paused[uint256(ERC20TokenLoan.Action.BORROW)]);
+        assertTrue(paused[uint256(ERC20TokenLoan.Action.REPAY)]);
+        assertTrue(paused[uint256(ERC20TokenLoan.Action.WITHDRAW)]);
+    }
+    
+    function test_BorrowAndSupplyCaps() public {
+        loan.setBorrowCap(address(usdc), 1500e6);
+        loan.setSupplyCap(address(usdc), 100_500e6);
+        
+        _borrow(1000e6, 1 ether);
+        vm.expectRevert(bytes("Borrow cap reached"));
+        _borrow(501e6, 1 ether);
+        
+        usdc.mint(lender, 1000e6);
+        vm.expectRevert(bytes("Supply cap reached"));
+        vm.prank(lender);
+        loan.depositLiquidity(address(usdc), 501e6);
+        
+        vm.prank(lender);
+        loan.depositLiquidity(address(usdc), 500e6);
+        
+        (, uint256 borrowCap, uint256 supplyCap) = loan.getMarketLimits(address(usdc));
+        assertEq(borrowCap, 1500e6);
+        assertEq(supplyCap, 100_500e6);
+    }
+    
+    // ============ BAD DEBT ============
+    
+    function test_WriteOffBadDebt_CollateralThenInsuranceThenLenders() public {
+        uint256 loanId = _borrow(1000e6, 1 ether);
+        
+        vm.prank(liquidator);
+        loan.fundInsurance(address(usdc), 300e6);
+        
+        // 1 WETH at 1020 USD still covers 1000 USDC, even if not with the liquidation bonus
+        wethFeed.setAnswer(1020e8);
+        vm.expectRevert(bytes("Loan not underwater"));
+        loan.writeOffBadDebt(loanId);
+        
+        wethFeed.setAnswer(500e8);
+        vm.expectRevert(bytes("Insufficient collateral for liquidation"));
+        vm.prank(liquidator);
+        loan.liquidateLoan(loanId, 1000e6);
+        
+        loan.setActionPaused(address(usdc), ERC20TokenLoan.Action.LIQUIDATE, true);
+        vm.expectRevert(bytes("Action paused"));
+        vm.prank(liquidator);
+        loan.writeOffBadDebt(loanId);
+        loan.setActionPaused(address(usdc), ERC20TokenLoan.Action.LIQUIDATE, false);
+        
+        // The caller buys the collateral for the pool at its oracle value less the 5% write-off discount
+        vm.prank(liquidator);
+        loan.writeOffBadDebt(loanId);
+        
+        ERC20TokenLoan.Loan memory position = loan.getLoan(loanId);
+        assertFalse(position.isActive);
+        assertEq(position.collateralAmount, 0);
+        assertEq(weth.balanceOf(liquidator), 1 ether);
+        assertEq(usdc.balanceOf(liquidator), 100_000e6 - 300e6 - 475e6);
+        assertEq(loan.totalReserves(address(weth)), 0);
+        assertEq(loan.totalBorrowed(address(usdc)), 0);
+        assertEq(loan.insuranceFund(address(usdc)), 0);
+        
+        // Lenders absorb only what neither the collateral nor the insurance fund covered
+        assertEq(loan.totalPoolAssets(address(usdc)), 99_775e6);
+        ERC20TokenLoan.BadDebtStats memory stats = loan.getBadDebtStats(address(usdc));
+        assertEq(stats.totalWrittenOff, 1000e6);
+        assertEq(stats.coveredByCollateral, 475e6);
+        assertEq(stats.coveredByInsurance, 300e6);
+        assertEq(stats.coveredByReserves, 0);
+        assertEq(stats.socializedLoss, 225e6);
+        
+        // Remaining liquidity is withdrawable again
+        vm.prank(lender);
+        loan.withdrawLiquidity(address(usdc), 99_000e6);
+    }
+    
+    function test_SetWriteOffDiscount_ChangesCollateralPrice() public {
+        uint256 loanId = _borrow(1000e6, 1 ether);
+        
+        vm.expectRevert(bytes("Write-off discount too high"));
+        loan.setWriteOffDiscount(1001);
+        vm.expectRevert();
+        vm.prank(liquidator);
+        loan.setWriteOffDiscount(0);
+        
+        loan.setWriteOffDiscount(1000);
+        assertEq(loan.writeOffDiscount(), 1000);
+        
+        wethFeed.setAnswer(500e8);
+        vm.prank(liquidator);
+        loan.writeOffBadDebt(loanId);
+        
+        assertEq(usdc.balanceOf(liquidator), 100_000e6 - 450e6);
+        assertEq(loan.getBadDebtStats(address(usdc)).coveredByCollateral, 450e6);
+    }
+    
+    function test_WriteOffBadDebt_GivesCollateralToP2PLender() public {
+        usdc.mint(lender2, 5000e6);
+        vm.startPrank(lender2);
+        usdc.approve(address(loan), type(uint256).max);
+        uint256 offerId = loan.createLoanOffer(address(usdc), 5000e6, 1000, 30 days, _wethOnly());
+        vm.stopPrank();
+        
+        vm.prank(borrower);
+        uint256 loanId = loan.acceptLoanOffer(offerId, 1000e6, address(weth), 1 ether, 30 days);
+        
+        wethFeed.setAnswer(500e8);
+        vm.prank(liquidator);
+        loan.writeOffBadDebt(loanId);
+        
+        assertEq(weth.balanceOf(lender2), 1 ether);
+        assertEq(weth.balanceOf(liquidator), 0);
+        assertEq(usdc.balanceOf(liquidator), 100_000e6);
+        // Pool lenders are untouched
+        assertEq(loan.totalPoolAssets(address(usdc)), 100_000e6);
+        assertEq(loan.getBadDebtStats(address(usdc)).totalWrittenOff, 0);
+    }
+    
+    function test_WriteOffAccountBadDebt_CollateralThenReserves() public {
+        _depositAccountCollateral(1 ether);
+        loan.configureToken(address(usdc), true, 15000, 30 days, address(rateModel), 5000);
+        
+        vm.prank(borrower);
+        loan.borrowFromAccount(address(usdc), 1400e6);
+        
+        vm.warp(block.timestamp + 30 days);
+        usdcFeed.setAnswer(1e8);
+        
+        // 1 WETH at 1500 USD is in shortfall at its 75% factor but still covers the debt
+        wethFeed.setAnswer(1500e8);
+        vm.expectRevert(bytes("Account not underwater"));
+        loan.writeOffAccountBadDebt(borrower);
+        
+        wethFeed.setAnswer(1000e8);
+        loan.accrueInterest(address(usdc));
+        uint256 reserves = loan.totalReserves(address(usdc));
+        uint256 debt = loan.getAccountBorrowBalance(borrower, address(usdc));
+        
+        vm.prank(liquidator);
+        loan.writeOffAccountBadDebt(borrower);
+        
+        assertEq(loan.getAccountBorrowBalance(borrower, address(usdc)), 0);
+        assertEq(loan.accountCollateral(borrower, address(weth)), 0);
+        assertEq(weth.balanceOf(liquidator), 1 ether);
+        assertEq(usdc.balanceOf(liquidator), 100_000e6 - 950e6);
+        assertEq(loan.totalReserves(address(weth)), 0);
+        assertEq(loan.totalReserves(address(usdc)), 0);
+        
+        ERC20TokenLoan.BadDebtStats memory stats = loan.getBadDebtStats(address(usdc));
+        assertEq(stats.totalWrittenOff, debt);
+        assertEq(stats.coveredByCollateral, 950e6);
+        assertEq(stats.coveredByReserves, reserves);
+        assertEq(stats.socializedLoss, debt - 950e6 - reserves);
+    }
+    
+    // ============ KEEPER VIEWS ============
+    
+    function test_GetLiquidatableLoans_SkipsClosedAndHealthyLoans() public {
+        uint256 riskyId = _borrow(1000e6, 1 ether);
+        uint256 safeId = _borrow(500e6, 1 ether);
+        uint256 repaidId = _borrow(200e6, 1 ether);
+        
+        vm.prank(borrower);
+        loan.repayLoan(repaidId, type(uint256).max);
+        
+        assertEq(loan.getActiveLoanCount(), 2);
+        uint256[] memory active = loan.getActiveLoans(0, 10);
+        assertEq(active[0], riskyId);
+        assertEq(active[1], safeId);
+        
+        // 2000 USD against 1000 USDC * 150% * 90%
+        assertEq(loan.healthFactor(riskyId), uint256(2000e18) * 1e18 / 1350e18);
+        
+        wethFeed.setAnswer(1300e8);
+        assertLt(loan.healthFactor(riskyId), loan.HEALTH_FACTOR_ONE());
+        assertFalse(loan.isLiquidatable(safeId));
+        
+        (uint256[] memory liquidatable, uint256 nextOffset) = loan.getLiquidatableLoans(0, 10);
+        assertEq(liquidatable.length, 1);
+        assertEq(liquidatable[0], riskyId);
+        assertEq(nextOffset, 2);
+        
+        (liquidatable, nextOffset) = loan.getLiquidatableLoans(1, 1);
+        assertEq(liquidatable.length, 0);
+        assertEq(nextOffset, 2);
+    }
+    
+    function test_LoanAtMinimumRatio_NotLiquidatable() public {
+        // 1 WETH at 2000 USD backs 1333.33 USDC at 150%
+        uint256 loanId = _borrow(1333e6, 1 ether);
+        
+        assertGt(loan.healthFactor(loanId), loan.HEALTH_FACTOR_ONE());
+        assertFalse(loan.isLiquidatable(loanId));
+        (uint256[] memory liquidatable,) = loan.getLiquidatableLoans(0, 10);
+        assertEq(liquidatable.length, 0);
+        
+        vm.expectRevert(bytes("Loan not liquidatable"));
+        vm.prank(liquidator);
+        loan.liquidateLoan(loanId, type(uint256).max);
+        
+        // Interest over the loan's term doesn't push it below the liquidation threshold
+        vm.warp(block.timestamp + 29 days);
+        wethFeed.setAnswer(2000e8);
+        usdcFeed.setAnswer(1e8);
+        assertFalse(loan.isLiquidatable(loanId));
+    }
+    
+    function test_PreviewLiquidation_MatchesLiquidation() public {
+        uint256 loanId = _borrow(1000e6, 1 ether);
+        
+        (uint256 repayAmount, uint256 seized, uint256 penalty) = loan.previewLiquidation(loanId);
+        assertEq(repayAmount, 0);
+        
+        wethFeed.setAnswer(1300e8);
+        (repayAmount, seized, penalty) = loan.previewLiquidation(loanId);
+        assertEq(repayAmount, 500e6);
+        
+        vm.prank(liquidator);
+        loan.liquidateLoan(loanId, type(uint256).max);
+        
+        assertEq(weth.balanceOf(liquidator), seized);
+        assertEq(loan.totalReserves(address(weth)), penalty);
+        assertEq(usdc.balanceOf(liquidator), 100_000e6 - repayAmount);
+    }
+    
+    // ============ CREDIT DELEGATION ============
+    
+    function test_DelegatedLoan_RepaidIntoDelegatorPosition() public {
+        address delegatee = makeAddr("delegatee");
+        vm.prank(lender);
+        loan.approveDelegation(address(usdc), delegatee, 5000e6, 1000, 30 days);
+        
+        vm.prank(delegatee);
+        uint256 loanId = loan.borrowWithDelegation(address(usdc), lender, 2000e6, 30 days);
+        
+        assertEq(usdc.balanceOf(delegatee), 2000e6);
+        assertEq(loan.getCreditDelegation(lender, address(usdc), delegatee).amount, 3000e6);
+        assertApproxEqAbs(loan.balanceOfUnderlying(address(usdc), lender), 98_000e6, 1);
+        assertTrue(loan.isDelegatedLoan(loanId));
+        assertFalse(loan.isLiquidatable(loanId));
+        
+        vm.expectRevert(bytes("Exceeds delegated credit"));
+        vm.prank(delegatee);
+        loan.borrowWithDelegation(address(usdc), lender, 3001e6, 30 days);
+        
+        vm.warp(block.timestamp + 15 days);
+        uint256 debt = loan.getLoanDebt(loanId);
+        assertEq(debt, _fixedRateDebt(2000e6, 1000, 15 days));
+        
+        usdc.mint(delegatee, debt - 2000e6);
+        vm.startPrank(delegatee);
+        usdc.approve(address(loan), type(uint256).max);
+        loan.repayLoan(loanId, type(uint256).max);
+        vm.stopPrank();
+        
+        assertFalse(loan.getLoan(loanId).isActive);
+        assertApproxEqAbs(loan.balanceOfUnderlying(address(usdc), lender), 98_000e6 + debt, 2);
+    }
+    
+    function test_DelegatedLoan_DefaultTrackedAgainstDelegator() public {
+        address delegatee = makeAddr("delegatee");
+        vm.prank(lender);
+        loan.approveDelegation(address(usdc), delegatee, 5000e6, 1000, 30 days);
+        
+        vm.prank(delegatee);
+        uint256 loanId = loan.borrowWithDelegation(address(usdc), lender, 2000e6, 30 days);
+        
+        vm.expectRevert(bytes("Loan not overdue"));
+        vm.prank(lender);
+        loan.markDelegatedDefault(loanId);
+        
+        vm.warp(block.timestamp + 31 days);
+        vm.expectRevert(bytes("Delegated loan"));
+        vm.prank(liquidator);
+        loan.liquidateLoan(loanId, type(uint256).max);
+        
+        uint256 debt = loan.getLoanDebt(loanId);
+        vm.prank(lender);
+        loan.markDelegatedDefault(loanId);
+        
+        assertFalse(loan.getLoan(loanId).isActive);
+        assertEq(loan.delegatedDefaults(lender, address(usdc)), debt);
+        assertEq(loan.getActiveLoanCount(), 0);
+    }
+}
@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test} from "forge-std/Test.sol";
+import {ERC20TokenLoan} from "../src/Load.sol";
+import {YieldHarvest} from "../src/YieldHarvest.sol";
+import {ChainlinkOracleAdapter} from "../src/oracles/ChainlinkOracleAdapter.sol";
+import {JumpRateModel} from "../src/rates/JumpRateModel.sol";
+import {MockAggregatorV3} from "./mocks/MockAggregatorV3.sol";
+import {MockFeeOnTransferERC20} from "./mocks/MockFeeOnTransferERC20.sol";
+import {MockRebasingERC20} from "./mocks/MockRebasingERC20.sol";
+import {MockUSDT} from "./mocks/MockUSDT.sol";
+
+contract NonStandardTokensTest is Test {
+    ERC20TokenLoan public loan;
+    YieldHarvest public harvest;
+    
+    MockUSDT public usdt;
+    MockFeeOnTransferERC20 public fot;
+    MockRebasingERC20 public reb;
+    
+    address public lender = makeAddr("lender");
+    address public borrower = makeAddr("borrower");
+    
+    function setUp() public {
+        loan = new ERC20TokenLoan();
+        harvest = new YieldHarvest();
+        
+        usdt = new MockUSDT();
+        fot = new MockFeeOnTransferERC20("Fee Token", "FOT", 18, 100); // 1% fee
+        reb = new MockRebasingERC20();
+        
+        JumpRateModel rateModel = new JumpRateModel(500, 200, 8000, 3000);
+        _listToken(address(usdt), address(rateModel));
+        _listToken(address(fot), address(rateModel));
+        _listToken(address(reb), address(rateModel));
+        loan.approveCollateral(address(usdt), address(fot), true);
+        
+        usdt.mint(lender, 10_000e6);
+        fot.mint(lender, 1000e18);
+        fot.mint(borrower, 1000e18);
+        reb.mint(lender, 1000e18);
+        
+        vm.startPrank(lender);
+        usdt.approve(address(loan), type(uint256).max);
+        fot.approve(address(loan), type(uint256).max);
+        reb.approve(address(loan), type(uint256).max);
+        vm.stopPrank();
+        
+        vm.startPrank(borrower);
+        usdt.approve(address(loan), type(uint256).max);
+        fot.approve(address(loan), type(uint256).max);
+        vm.stopPrank();
+    }
+    
+    function _listToken(address token, address rateModel) internal {
+        MockAggregatorV3 feed = new MockAggregatorV3(8, 1e8);
+        loan.configureToken(token, true, 15000, 30 days, rateModel, 0);
+        loan.setPriceFeed(token, address(new ChainlinkOracleAdapter(token, address(feed), 1 hours)), address(0), 0);
+    }
+    
+    // ============ ERC20TokenLoan ============
+    
+    function test_UsdtLikeToken_DepositBorrowRepay() public {
+        vm.prank(lender);
+        loan.depositLiquidity(address(usdt), 10_000e6);
+        assertEq(usdt.balanceOf(address(loan)), 10_000e6);
+        
+        vm.prank(borrower);
+        uint256 loanId = loan.requestLoan(address(usdt), address(fot), 500e6, 1000e18, 30 days);
+        assertEq(usdt.balanceOf(borrower), 500e6);
+        
+        vm.prank(borrower);
+        loan.repayLoan(loanId, type(uint256).max);
+        
+        assertFalse(loan.getLoan(loanId).isActive);
+        assertEq(loan.totalLiquidity(address(usdt)), 10_000e6);
+    }
+    
+    function test_FeeOnTransfer_DepositCreditsAmountReceived() public {
+        vm.prank(lender);
+        loan.depositLiquidity(address(fot), 1000e18);
+        
+        assertEq(loan.totalLiquidity(address(fot)), 990e18);
+        assertEq(fot.balanceOf(address(loan)), 990e18);
+        assertApproxEqAbs(loan.balanceOfUnderlying(address(fot), lender), 990e18, 1);
+        
+        // Every tracked token can be withdrawn
+        (uint256 shares,,) = loan.lenderPositions(address(fot), lender);
+        vm.prank(lender);
+        loan.redeemShares(address(fot), shares);
+        assertLe(fot.balanceOf(address(loan)), 1);
+    }
+    
+    function test_FeeOnTransfer_CollateralCreditsAmountReceived() public {
+        vm.prank(lender);
+        loan.depositLiquidity(address(usdt), 10_000e6);
+        
+        vm.prank(borrower);
+        uint256 loanId = loan.requestLoan(address(usdt), address(fot), 500e6, 1000e18, 30 days);
+        
+        assertEq(loan.getLoan(loanId).collateralAmount, 990e18);
+        assertEq(fot.balanceOf(address(loan)), 990e18);
+    }
+    
+    function test_RebasingToken_PoolAccountingIgnoresRebase() public {
+        vm.prank(lender);
+        loan.depositLiquidity(address(reb), 1000e18);
+        
+        reb.rebase(1.1e18);
+        assertEq(loan.totalLiquidity(address(reb)), 1000e18);
+        
+        (uint256 shares,,) = loan.lenderPositions(address(reb), lender);
+        vm.prank(lender);
+        loan.redeemShares(address(reb), shares);
+        
+        assertApproxEqAbs(reb.balanceOf(lender), 1000e18, 1);
+        // The rebase surplus stays in the contract, untracked by the pool
+        assertApproxEqAbs(reb.balanceOf(address(loan)), 100e18, 1);
+    }
+    
+    // ============ YieldHarvest ============
+    
+    function test_YieldHarvest_FeeOnTransferStakeCreditsAmountReceived() public {
+        harvest.configurePool(address(fot), address(fot), 1000, 7 days, 1e18, 1_000_000e18, 10_000_000e18, 0, 0);
+        
+        vm.startPrank(borrower);
+        fot.approve(address(harvest), type(uint256).max);
+        uint256 stakeId = harvest.createStake(address(fot), 100e18, YieldHarvest.StakeType.FLEXIBLE, address(0));
+        vm.stopPrank();
+        
+        (,,, uint256 amount,,,,,) = harvest.getStakeDetails(stakeId);
+        assertEq(amount, 99e18);
+        assertEq(harvest.totalValueLocked(), 99e18);
+    }
+    
+    function test_YieldHarvest_UsdtLikeStakeAndUnstake() public {
+        harvest.configurePool(address(usdt), address(usdt), 1000, 7 days, 1e6, 1_000_000e6, 10_000_000e6, 0, 0);
+        
+        vm.startPrank(lender);
+        usdt.approve(address(harvest), type(uint256).max);
+        uint256 stakeId = harvest.createStake(address(usdt), 1000e6, YieldHarvest.StakeType.FLEXIBLE, address(0));
+        assertEq(usdt.balanceOf(address(harvest)), 1000e6);
+        
+        harvest.unstake(stakeId);
+        vm.stopPrank();
+        
+        assertEq(usdt.balanceOf(lender), 10_000e6);
+    }
+}
@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test} from "forge-std/Test.sol";
+import {ChainlinkOracleAdapter} from "../src/oracles/ChainlinkOracleAdapter.sol";
+import {TWAPOracleAdapter} from "../src/oracles/TWAPOracleAdapter.sol";
+import {MockERC20} from "./mocks/MockERC20.sol";
+import {MockAggregatorV3} from "./mocks/MockAggregatorV3.sol";
+import {MockUniswapV2Pair} from "./mocks/MockUniswapV2Pair.sol";
+
+contract PriceOraclesTest is Test {
+    MockERC20 public usdc;
+    MockERC20 public weth;
+
+    function setUp() public {
+        vm.warp(1_700_000_000);
+        usdc = new MockERC20("USD Coin", "USDC", 6);
+        weth = new MockERC20("Wrapped Ether", "WETH", 18);
+    }
+
+    function test_Chainlink_NormalizesDecimals() public {
+        MockAggregatorV3 feed8 = new MockAggregatorV3(8, 2000e8);
+        MockAggregatorV3 feed20 = new MockAggregatorV3(20, 2000e20);
+
+        assertEq(new ChainlinkOracleAdapter(address(weth), address(feed8), 1 hours).getPrice(address(weth)), 2000e18);
+        assertEq(new ChainlinkOracleAdapter(address(weth), address(feed20), 1 hours).getPrice(address(weth)), 2000e18);
+    }
+
+    function test_Chainlink_RejectsStaleAndInvalidAnswers() public {
+        MockAggregatorV3 feed = new MockAggregatorV3(8, 2000e8);
+        ChainlinkOracleAdapter adapter = new ChainlinkOracleAdapter(address(weth), address(feed), 1 hours);
+
+        vm.expectRevert(bytes("Unsupported token"));
+        adapter.getPrice(address(usdc));
+
+        feed.setUpdatedAt(block.timestamp - 2 hours);
+        vm.expectRevert(bytes("Stale price"));
+        adapter.getPrice(address(weth));
+
+        feed.setAnswer(0);
+        vm.expectRevert(bytes("Invalid price"));
+        adapter.getPrice(address(weth));
+    }
+
+    function test_TWAP_AveragesOverWindow() public {
+        MockUniswapV2Pair pair = new MockUniswapV2Pair(address(usdc), address(weth));
+        // 2000 USDC per WETH
+        pair.setReserves(2_000_000e6, 1000 ether);
+
+        TWAPOracleAdapter adapter = new TWAPOracleAdapter(address(pair), address(weth), address(0), 30 minutes, 2 hours);
+
+        vm.expectRevert(bytes("TWAP not initialized"));
+        adapter.getPrice(address(weth));
+
+        vm.expectRevert(bytes("Period not elapsed"));
+        adapter.update();
+
+        // Spot moves to 2200 halfway through the window
+        vm.warp(block.timestamp + 15 minutes);
+        pair.setReserves(2_200_000e6, 1000 ether);
+        vm.warp(block.timestamp + 15 minutes);
+        adapter.update();
+
+        assertApproxEqRel(adapter.getPrice(address(weth)), 2100e18, 1e12);
+
+        vm.warp(block.timestamp + 3 hours);
+        vm.expectRevert(bytes("Stale price"));
+        adapter.getPrice(address(weth));
+    }
+
+    function test_TWAP_UsesQuoteOracle() public {
+        MockERC20 dai = new MockERC20("Dai", "DAI", 18);
+        MockUniswapV2Pair pair = new MockUniswapV2Pair(address(weth), address(dai));
+        pair.setReserves(1000 ether, 2_000_000 ether);
+
+        MockAggregatorV3 daiFeed = new MockAggregatorV3(8, 0.99e8);
+        ChainlinkOracleAdapter daiOracle = new ChainlinkOracleAdapter(address(dai), address(daiFeed), 1 days);
+        TWAPOracleAdapter adapter = new TWAPOracleAdapter(address(pair), address(weth), address(daiOracle), 30 minutes, 2 hours);
+
+        vm.warp(block.timestamp + 30 minutes);
+        adapter.update();
+
+        assertApproxEqRel(adapter.getPrice(address(weth)), 1980e18, 1e12);
+    }
+}
@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.22;
+
+import {Test} from "forge-std/Test.sol";
+import {TimelockController} from "@openzeppelin/contracts/governance/TimelockController.sol";
+import {ERC1967Proxy} from "@openzeppelin/contracts/proxy/ERC1967/ERC1967Proxy.sol";
+import {Initializable} from "@openzeppelin/contracts/proxy/utils/Initializable.sol";
+import {UUPSUpgradeable} from "@openzeppelin/contracts/proxy/utils/UUPSUpgradeable.sol";
+import {ERC20TokenLoan} from "../src/Load.sol";
+import {YieldHarvest} from "../src/YieldHarvest.sol";
+import {ERC20TokenLoanUpgradeable} from "../src/upgradeable/ERC20TokenLoanUpgradeable.sol";
+import {YieldHarvestUpgradeable} from "../src/upgradeable/YieldHarvestUpgradeable.sol";
+import {ERC20TokenLoanTest} from "./Load.t.sol";
+import {MockERC20} from "./mocks/MockERC20.sol";
+import {ERC20TokenLoanV2, YieldHarvestV2} from "./mocks/MockUpgradeV2.sol";
+import {YieldHarvestV1} from "./mocks/MockYieldHarvestV1.sol";
+
+abstract contract TimelockUpgradeHelper is Test {
+    uint256 public constant UPGRADE_DELAY = 2 days;
+    
+    TimelockController public timelock;
+    address public admin = makeAddr("admin");
+    
+    function _deployTimelock() internal {
+        address[] memory accounts = new address[](1);
+        accounts[0] = admin;
+        timelock = new TimelockController(UPGRADE_DELAY, accounts, accounts, address(0));
+    }
+    
+    function _upgradeCall(address newImplementation, bytes memory data) internal pure returns (bytes memory) {
+        return abi.encodeCall(UUPSUpgradeable.upgradeToAndCall, (newImplementation, data));
+    }
+    
+    function _upgradeThroughTimelock(address proxy, address newImplementation, bytes memory data) internal {
+        bytes memory payload = _upgradeCall(newImplementation, data);
+        
+        vm.prank(admin);
+        timelock.schedule(proxy, 0, payload, bytes32(0), bytes32(0), UPGRADE_DELAY);
+        vm.warp(block.timestamp + UPGRADE_DELAY);
+        vm.prank(admin);
+        timelock.execute(proxy, 0, payload, bytes32(0), bytes32(0));
+    }
+}
+
+/// @dev Runs the whole lending suite against the proxy, plus upgrade-specific checks
+contract ERC20TokenLoanProxyTest is ERC20TokenLoanTest, TimelockUpgradeHelper {
+    function _deployLoan() internal override returns (ERC20TokenLoan) {
+        _deployTimelock();
+        ERC1967Proxy proxy = new ERC1967Proxy(
+            address(new ERC20TokenLoanUpgradeable()),
+            abi.encodeCall(ERC20TokenLoanUpgradeable.initialize, (address(this), address(timelock)))
+        );
+        return ERC20TokenLoan(address(proxy));
+    }
+    
+    function _proxy() internal view returns (ERC20TokenLoanUpgradeable) {
+        return ERC20TokenLoanUpgradeable(address(loan));
+    }
+    
+    function test_Initialize_SetsOwnerUpgraderAndDefaults() public view {
+        assertEq(loan.owner(), address(this));
+        assertEq(loan.treasury(), address(this));
+        assertEq(loan.closeFactor(), 5000);
+        assertEq(loan.flashLoanFee(), 9);
+        assertEq(loan.writeOffDiscount(), 500);
+        assertEq(_proxy().upgrader(), address(timelock));
+        assertEq(_proxy().version(), "1");
+    }
+    
+    function test_Upgrade_PreservesLoanState() public {
+        uint256 loanId = _borrow(1000e6, 1 ether);
+        (uint256 sharesBefore,,) = loan.lenderPositions(address(usdc), lender);
+        uint256 liquidityBefore = loan.totalLiquidity(address(usdc));
+        
+        ERC20TokenLoanV2 v2 = new ERC20TokenLoanV2();
+        _upgradeThroughTimelock(address(loan), address(v2), abi.encodeCall(ERC20TokenLoanV2.initializeV2, ()));
+        
+        assertEq(_proxy().version(), "2");
+        assertEq(ERC20TokenLoanV2(address(loan)).migratedAt(), block.timestamp);
+        assertEq(loan.owner(), address(this));
+        assertEq(_proxy().upgrader(), address(timelock));
+        
+        (uint256 sharesAfter,,) = loan.lenderPositions(address(usdc), lender);
+        assertEq(sharesAfter, sharesBefore);
+        assertEq(loan.totalLiquidity(address(usdc)), liquidityBefore);
+        assertEq(loan.getLoan(loanId).borrower, borrower);
+        assertEq(loan.getLoan(loanId).collateralAmount, 1 ether);
+        assertEq(loan.getActiveLoanCount(), 1);
+        
+        usdc.mint(borrower, 100e6);
+        vm.prank(borrower);
+        loan.repayLoan(loanId, type(uint256).max);
+        
+        assertFalse(loan.getLoan(loanId).isActive);
+        assertEq(weth.balanceOf(borrower), 10 ether);
+    }
+    
+    function test_RevertWhen_UpgradeNotFromUpgrader() public {
+        address v2 = address(new ERC20TokenLoanV2());
+        
+        vm.expectRevert(bytes("Not upgrader"));
+        _proxy().upgradeToAndCall(v2, "");
+    }
+    
+    function test_RevertWhen_UpgradeBeforeTimelockDelay() public {
+        bytes memory payload = _upgradeCall(address(new ERC20TokenLoanV2()), "");
+        
+        vm.prank(admin);
+        timelock.schedule(address(loan), 0, payload, bytes32(0), bytes32(0), UPGRADE_DELAY);
+        
+        vm.warp(block.timestamp + UPGRADE_DELAY - 1);
+        vm.expectRevert();
+        vm.prank(admin);
+        timelock.execute(address(loan), 0, payload, bytes32(0), bytes32(0));
+    }
+    
+    function test_RevertWhen_InitializedTwice() public {
+        vm.expectRevert(Initializable.InvalidInitialization.selector);
+        _proxy().initialize(lender, lender);
+        
+        ERC20TokenLoanUpgradeable implementation = new ERC20TokenLoanUpgradeable();
+        vm.expectRevert(Initializable.InvalidInitialization.selector);
+        implementation.initialize(lender, lender);
+    }
+}
+
+contract YieldHarvestProxyTest is TimelockUpgradeHelper {
+    YieldHarvestUpgradeable public harvest;
+    MockERC20 public token;
+    
+    address public staker = makeAddr("staker");
+    
+    function setUp() public {
+        _deployTimelock();
+        ERC1967Proxy proxy = new ERC1967Proxy(
+            address(new YieldHarvestUpgradeable()),
+            abi.encodeCall(YieldHarvestUpgradeable.initialize, (address(this), address(timelock)))
+        );
+        harvest = YieldHarvestUpgradeable(address(proxy));
+        
+        token = new MockERC20("Stake Token", "STK", 18);
+        harvest.configurePool(address(token), address(token), 1000, 7 days, 1e18, 1_000_000e18, 10_000_000e18, 0, 0);
+        
+        token.mint(staker, 1000e18);
+        vm.prank(staker);
+        token.approve(address(harvest), type(uint256).max);
+    }
+    
+    function test_Upgrade_PreservesStakes() public {
+        vm.prank(staker);
+        uint256 stakeId = harvest.createStake(address(token), 100e18, YieldHarvest.StakeType.FLEXIBLE, address(0));
+        
+        YieldHarvestV2 v2 = new YieldHarvestV2();
+        _upgradeThroughTimelock(address(harvest), address(v2), abi.encodeCall(YieldHarvestV2.initializeV2, ()));
+        
+        assertEq(harvest.version(), "2");
+        assertEq(YieldHarvestV2(address(harvest)).migratedAt(), block.timestamp);
+        assertEq(harvest.owner(), address(this));
+        assertEq(harvest.totalValueLocked(), 100e18);
+        
+        (address user, address stakeToken,, uint256 amount,,,,, bool isActive) = harvest.getStakeDetails(stakeId);
+        assertEq(user, staker);
+        assertEq(stakeToken, address(token));
+        assertEq(amount, 100e18);
+        assertTrue(isActive);
+        
+        token.mint(address(this), 10e18);
+        token.approve(address(harvest), 10e18);
+        harvest.notifyRewardAmount(address(token), 10e18);
+        vm.prank(staker);
+        harvest.unstake(stakeId);
+        
+        assertEq(harvest.totalValueLocked(), 0);
+        assertGt(token.balanceOf(staker), 1000e18);
+    }
+    
+    function test_Upgrade_FromFirstReleaseLayoutKeepsState() public {
+        ERC1967Proxy proxy = new ERC1967Proxy(
+            address(new YieldHarvestV1()),
+            abi.encodeCall(YieldHarvestV1.initialize, (address(this), address(timelock)))
+        );
+        YieldHarvestV1(address(proxy)).seed(staker, address(token), 100e18, "First release proposal");
+        
+        _upgradeThroughTimelock(address(proxy), address(new YieldHarvestUpgradeable()), "");
+        YieldHarvestUpgradeable upgraded = YieldHarvestUpgradeable(address(proxy));
+        
+        assertEq(upgraded.version(), "1");
+        assertEq(upgraded.owner(), address(this));
+        assertEq(upgraded.upgrader(), address(timelock));
+        assertEq(upgraded.totalStakes(), 1);
+        assertEq(upgraded.totalValueLocked(), 100e18);
+        assertEq(upgraded.governanceProposalCount(), 1);
+        assertEq(upgraded.userStakes(staker, 0), 0);
+        assertEq(upgraded.userPoolStakes(staker, address(token)), 100e18);
+        assertEq(upgraded.userTotalRewards(staker), 10e18);
+        assertTrue(upgraded.whitelistedTokens(address(token)));
+        
+        (address referrer,,,) = upgraded.referrals(staker);
+        assertEq(referrer, address(this));
+        
+        (address user, address stakeToken,, uint256 amount,,,,, bool isActive) = upgraded.getStakeDetails(0);
+        assertEq(user, staker);
+        assertEq(stakeToken, address(token));
+        assertEq(amount, 100e18);
+        assertTrue(isActive);
+        
+        (uint256 proposalId, address creator, string memory description,,, uint256 forVotes,,,,,,) =
+            upgraded.governanceProposals(0);
+        assertEq(proposalId, 0);
+        assertEq(creator, staker);
+        assertEq(description, "First release proposal");
+        assertEq(forVotes, 100e18);
+    }
+    
+    function test_RevertWhen_HarvestUpgradeNotFromUpgrader() public {
+        address v2 = address(new YieldHarvestV2());
+        
+        vm.expectRevert(bytes("Not upgrader"));
+        harvest.upgradeToAndCall(v2, "");
+    }
+    
+    function test_SetUpgrader_OnlyThroughTimelock() public {
+        vm.expectRevert(bytes("Not upgrader"));
+        harvest.setUpgrader(admin);
+        
+        bytes memory payload = abi.encodeCall(YieldHarvestUpgradeable.setUpgrader, (admin));
+        vm.prank(admin);
+        timelock.schedule(address(harvest), 0, payload, bytes32(0), bytes32(0), UPGRADE_DELAY);
+        vm.warp(block.timestamp + UPGRADE_DELAY);
+        vm.prank(admin);
+        timelock.execute(address(harvest), 0, payload, bytes32(0), bytes32(0));
+        
+        assertEq(harvest.upgrader(), admin);
+    }
+}
@@ -0,0 +1,920 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test} from "forge-std/Test.sol";
+import {TimelockController} from "@openzeppelin/contracts/governance/TimelockController.sol";
+import {YieldHarvest} from "../src/YieldHarvest.sol";
+import {BoostCard} from "../src/boost/BoostCard.sol";
+import {MockERC20} from "./mocks/MockERC20.sol";
+
+contract YieldHarvestTest is Test {
+    YieldHarvest public harvest;
+    BoostCard public boostCard;
+    
+    MockERC20 public stakeToken;
+    MockERC20 public rewardToken;
+    
+    address public staker = makeAddr("staker");
+    address public staker2 = makeAddr("staker2");
+    
+    function setUp() public virtual {
+        harvest = new YieldHarvest();
+        
+        stakeToken = new MockERC20("Stake Token", "STK", 18);
+        rewardToken = new MockERC20("Reward Token", "RWD", 18);
+        
+        // 10% base APR
+        harvest.configurePool(address(stakeToken), address(rewardToken), 1000, 7 days, 1e18, 1_000_000e18, 10_000_000e18, 0, 0);
+        
+        stakeToken.mint(staker, 10_000e18);
+        stakeToken.mint(staker2, 10_000e18);
+        rewardToken.mint(address(this), 1_000_000e18);
+        
+        vm.prank(staker);
+        stakeToken.approve(address(harvest), type(uint256).max);
+        vm.prank(staker2);
+        stakeToken.approve(address(harvest), type(uint256).max);
+        rewardToken.approve(address(harvest), type(uint256).max);
+    }
+    
+    function _stake(address user, uint256 amount, YieldHarvest.StakeType stakeType) internal returns (uint256) {
+        vm.prank(user);
+        return harvest.createStake(address(stakeToken), amount, stakeType, address(0));
+    }
+    
+    function _rewardReserve(address token) internal view returns (uint256 reserve) {
+        (,,,,,,,,,,,, reserve,,,,) = harvest.pools(token);
+    }
+    
+    // ============ REWARD BUDGET ============
+    
+    function test_Harvest_PaysRewardTokenFromReserve() public {
+        harvest.notifyRewardAmount(address(stakeToken), 100e18);
+        uint256 stakeId = _stake(staker, 1000e18, YieldHarvest.StakeType.FLEXIBLE);
+        
+        // 10% of 1000 over a fifth of a year
+        vm.warp(block.timestamp + 73 days);
+        vm.prank(staker);
+        harvest.harvest(stakeId, false);
+        
+        assertEq(rewardToken.balanceOf(staker), 20e18);
+        assertEq(_rewardReserve(address(stakeToken)), 80e18);
+        assertEq(stakeToken.balanceOf(address(harvest)), 1000e18);
+        
+        // 80 left at ~0.274 per day
+        assertEq(harvest.getRewardRunway(address(stakeToken)), 292);
+    }
+    
+    function test_Rewards_StopWhenReserveRunsDry() public {
+        harvest.notifyRewardAmount(address(stakeToken), 10e18);
+        uint256 stakeId = _stake(staker, 1000e18, YieldHarvest.StakeType.FLEXIBLE);
+        uint256 stakeId2 = _stake(staker2, 1000e18, YieldHarvest.StakeType.FLEXIBLE);
+        
+        // 200 would be emitted over the year, the reserve only covers 10
+        vm.warp(block.timestamp + 365 days);
+        assertEq(harvest.calculatePendingRewards(stakeId), 5e18);
+        assertEq(harvest.getRewardRunway(address(stakeToken)), 0);
+        
+        vm.prank(staker);
+        harvest.unstake(stakeId);
+        
+        // Principal comes back whole and the other staker's deposit is untouched
+        assertEq(stakeToken.balanceOf(staker), 10_000e18);
+        assertEq(rewardToken.balanceOf(staker), 5e18);
+        assertEq(stakeToken.balanceOf(address(harvest)), 1000e18);
+        
+        // Nothing more is emitted once the reserve is dry
+        vm.warp(block.timestamp + 30 days);
+        assertEq(harvest.calculatePendingRewards(stakeId2), 5e18);
+        vm.prank(staker2);
+        harvest.harvest(stakeId2, false);
+        assertEq(rewardToken.balanceOf(staker2), 5e18);
+        
+        vm.expectRevert(bytes("No rewards to harvest"));
+        vm.prank(staker2);
+        harvest.harvest(stakeId2, false);
+    }
+    
+    function test_RevertWhen_CompoundingDifferentRewardToken() public {
+        harvest.notifyRewardAmount(address(stakeToken), 100e18);
+        uint256 stakeId = _stake(staker, 1000e18, YieldHarvest.StakeType.FLEXIBLE);
+        vm.warp(block.timestamp + 30 days);
+        
+        vm.expectRevert(bytes("Reward token differs from stake"));
+        vm.prank(staker);
+        harvest.harvest(stakeId, true);
+    }
+    
+    function test_Compound_PaysFeeAndRespectsPoolCap() public {
+        MockERC20 compoundToken = new MockERC20("Compound Token", "CMP", 18);
+        // 10% APR paid in the staked token, 10% performance fee, room for 20 more tokens
+        harvest.configurePool(address(compoundToken), address(compoundToken), 1000, 7 days, 1e18, 1_000_000e18, 1020e18, 1000, 0);
+        compoundToken.mint(address(this), 100e18);
+        compoundToken.approve(address(harvest), 100e18);
+        harvest.notifyRewardAmount(address(compoundToken), 100e18);
+        
+        compoundToken.mint(staker, 1000e18);
+        vm.startPrank(staker);
+        compoundToken.approve(address(harvest), 1000e18);
+        uint256 stakeId = harvest.createStake(address(compoundToken), 1000e18, YieldHarvest.StakeType.FLEXIBLE, address(0));
+        vm.stopPrank();
+        
+        vm.warp(block.timestamp + 73 days);
+        uint256 pending = harvest.calculatePendingRewards(stakeId);
+        vm.prank(staker);
+        harvest.harvest(stakeId, true);
+        
+        // The fee goes to the owner, the rest is staked
+        uint256 feeAmount = pending / 10;
+        assertEq(compoundToken.balanceOf(address(this)), feeAmount);
+        (,,, uint256 amount,,,,,) = harvest.getStakeDetails(stakeId);
+        assertEq(amount, 1000e18 + pending - feeAmount);
+        assertEq(compoundToken.balanceOf(address(harvest)), amount + _rewardReserve(address(compoundToken)));
+        
+        // Another fifth of a year would compound past the pool cap
+        vm.warp(block.timestamp + 73 days);
+        vm.expectRevert(bytes("Pool capacity reached"));
+        vm.prank(staker);
+        harvest.harvest(stakeId, true);
+    }
+    
+    function test_RevertWhen_ReconfiguringFundedRewardToken() public {
+        harvest.notifyRewardAmount(address(stakeToken), 100e18);
+        
+        vm.expectRevert(bytes("Reward token in use"));
+        harvest.configurePool(address(stakeToken), address(stakeToken), 1000, 7 days, 1e18, 1_000_000e18, 10_000_000e18, 0, 0);
+        
+        // Same reward token keeps the funded reserve
+        harvest.configurePool(address(stakeToken), address(rewardToken), 2000, 7 days, 1e18, 1_000_000e18, 10_000_000e18, 0, 0);
+        assertEq(_rewardReserve(address(stakeToken)), 100e18);
+    }
+    
+    function test_RevertWhen_NotifyRewardNotOwner() public {
+        vm.expectRevert();
+        vm.prank(staker);
+        harvest.notifyRewardAmount(address(stakeToken), 1e18);
+    }
+    
+    // ============ REWARD INDEX ============
+    
+    function test_UpdatePoolAPR_NotRetroactive() public {
+        harvest.notifyRewardAmount(address(stakeToken), 1000e18);
+        uint256 stakeId = _stake(staker, 1000e18, YieldHarvest.StakeType.FLEXIBLE);
+        
+        vm.warp(block.timestamp + 73 days);
+        harvest.updatePoolAPR(address(stakeToken), 2000);
+        assertEq(harvest.calculatePendingRewards(stakeId), 20e18);
+        
+        vm.warp(block.timestamp + 73 days);
+        assertEq(harvest.calculatePendingRewards(stakeId), 60e18);
+    }
+    
+    function test_LockedStake_EarnsWeightedShares() public {
+        harvest.notifyRewardAmount(address(stakeToken), 1000e18);
+        uint256 flexibleId = _stake(staker, 1000e18, YieldHarvest.StakeType.FLEXIBLE);
+        uint256 lockedId = _stake(staker2, 1000e18, YieldHarvest.StakeType.LOCKED);
+        
+        // 1.5x plus a 0.95% ve boost for the week-long lock, 2509.5 weighted shares at 10% for a fifth of a year
+        vm.warp(block.timestamp + 73 days);
+        assertEq(harvest.calculatePendingRewards(flexibleId), 20e18);
+        assertEq(harvest.calculatePendingRewards(lockedId), 30.19e18);
+        assertEq(harvest.getRewardRunway(address(stakeToken)), 1381);
+    }
+    
+    function test_EarlyUnstake_ReturnsForfeitedRewardsToReserve() public {
+        harvest.notifyRewardAmount(address(stakeToken), 1000e18);
+        uint256 stakeId = _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        
+        vm.warp(block.timestamp + 3 days);
+        assertGt(harvest.calculatePendingRewards(stakeId), 0);
+        
+        vm.prank(staker);
+        harvest.unstake(stakeId);
+        
+        assertEq(rewardToken.balanceOf(staker), 0);
+        assertApproxEqAbs(_rewardReserve(address(stakeToken)), 1000e18, 1e3);
+    }
+    
+    // ============ REFERRALS ============
+    
+    function test_Referral_PaidFromReserveAndCountedInRunway() public {
+        harvest.notifyRewardAmount(address(stakeToken), 100e18);
+        address referrer = makeAddr("referrer");
+        
+        // 1% of the referred stake, in the reward token
+        vm.prank(staker);
+        harvest.createStake(address(stakeToken), 1000e18, YieldHarvest.StakeType.FLEXIBLE, referrer);
+        assertEq(rewardToken.balanceOf(referrer), 10e18);
+        assertEq(_rewardReserve(address(stakeToken)), 90e18);
+        assertEq(stakeToken.balanceOf(address(harvest)), 1000e18);
+        
+        // 90 left at ~0.274 per day
+        assertEq(harvest.getRewardRunway(address(stakeToken)), 328);
+        
+        (uint256 totalReferred, uint256 totalRewards,) = harvest.getReferralStats(referrer);
+        assertEq(totalReferred, 1);
+        assertEq(totalRewards, 10e18);
+        
+        // Self-referrals are ignored
+        vm.prank(staker2);
+        harvest.createStake(address(stakeToken), 1000e18, YieldHarvest.StakeType.FLEXIBLE, staker2);
+        assertEq(rewardToken.balanceOf(staker2), 0);
+        assertEq(_rewardReserve(address(stakeToken)), 90e18);
+    }
+    
+    // ============ BOOST CARDS ============
+    
+    function _mintCard(address to, uint8 tier) internal returns (uint256 cardId) {
+        if (address(boostCard) == address(0)) {
+            boostCard = new BoostCard(address(rewardToken), address(this));
+            harvest.setBoostCard(address(boostCard));
+        }
+        cardId = boostCard.mint(to, tier);
+        vm.prank(to);
+        boostCard.setApprovalForAll(address(harvest), true);
+    }
+    
+    function _stakeBoosted(address user, uint256 amount, uint256 cardId) internal returns (uint256) {
+        vm.prank(user);
+        return harvest.createBoostedStake(address(stakeToken), amount, cardId, address(0));
+    }
+    
+    function test_BoostedStake_LocksCardAndEarnsBoost() public {
+        harvest.notifyRewardAmount(address(stakeToken), 1000e18);
+        uint256 cardId = _mintCard(staker, 3); // Gold, +50%
+        uint256 boostedId = _stakeBoosted(staker, 1000e18, cardId);
+        uint256 flexibleId = _stake(staker2, 1000e18, YieldHarvest.StakeType.FLEXIBLE);
+        
+        assertEq(boostCard.ownerOf(cardId), address(harvest));
+        
+        // Card boost plus the week-long lock's 0.95% ve boost
+        vm.warp(block.timestamp + 73 days);
+        assertEq(harvest.calculatePendingRewards(boostedId), 30.19e18);
+        assertEq(harvest.calculatePendingRewards(flexibleId), 20e18);
+    }
+    
+    function test_WithdrawBoostCard_PaysRewardsAndRemovesBoost() public {
+        harvest.notifyRewardAmount(address(stakeToken), 1000e18);
+        uint256 cardId = _mintCard(staker, 3);
+        uint256 boostedId = _stakeBoosted(staker, 1000e18, cardId);
+        uint256 flexibleId = _stake(staker2, 1000e18, YieldHarvest.StakeType.FLEXIBLE);
+        
+        vm.warp(block.timestamp + 73 days);
+        vm.prank(staker);
+        harvest.withdrawBoostCard(boostedId);
+        
+        assertEq(boostCard.ownerOf(cardId), staker);
+        assertEq(rewardToken.balanceOf(staker), 30.19e18);
+        
+        // Both stakes now carry the same weight, the lock (and its ve boost) has expired
+        vm.warp(block.timestamp + 73 days);
+        assertEq(harvest.calculatePendingRewards(boostedId), 20e18);
+        assertEq(harvest.calculatePendingRewards(flexibleId), 40e18);
+        
+        // The card can be staked back in
+        vm.prank(staker);
+        harvest.attachBoostCard(boostedId, cardId);
+        assertEq(boostCard.ownerOf(cardId), address(harvest));
+    }
+    
+    function test_Unstake_ReturnsBoostCard() public {
+        uint256 cardId = _mintCard(staker, 1);
+        uint256 stakeId = _stakeBoosted(staker, 1000e18, cardId);
+        
+        vm.warp(block.timestamp + 7 days);
+        vm.prank(staker);
+        harvest.unstake(stakeId);
+        
+        assertEq(boostCard.ownerOf(cardId), staker);
+        assertEq(stakeToken.balanceOf(staker), 10_000e18);
+    }
+    
+    function test_RevertWhen_BoostedStakeWithoutCard() public {
+        vm.expectRevert(bytes("Boosted stakes need a card"));
+        _stake(staker, 1000e18, YieldHarvest.StakeType.BOOSTED);
+        
+        uint256 cardId = _mintCard(staker2, 2);
+        vm.expectRevert();
+        _stakeBoosted(staker, 1000e18, cardId);
+    }
+    
+    function test_BoostCard_UpgradeTierAndMetadata() public {
+        uint256 cardId = _mintCard(staker, 3);
+        boostCard.setUpgradePrice(3, 50e18);
+        rewardToken.mint(staker, 50e18);
+        
+        vm.startPrank(staker);
+        rewardToken.approve(address(boostCard), 50e18);
+        boostCard.upgradeTier(cardId);
+        vm.stopPrank();
+        
+        assertEq(boostCard.tierOf(cardId), 4);
+        assertEq(rewardToken.balanceOf(address(this)), 1_000_050e18);
+        
+        vm.expectRevert(bytes("Max tier reached"));
+        vm.prank(staker);
+        boostCard.upgradeTier(cardId);
+        
+        assertEq(vm.indexOf(boostCard.tokenURI(cardId), "data:application/json;base64,"), 0);
+    }
+    
+    // ============ VESTING ============
+    
+    function _vest(address token, uint256 amount, bool revocable) internal returns (uint256 scheduleId) {
+        MockERC20(token).mint(address(this), amount);
+        MockERC20(token).approve(address(harvest), amount);
+        // 30 day cliff, 100 days in 10 day slices
+        scheduleId = harvest.createVestingSchedule(staker, token, amount, 30 days, 100 days, 10 days, revocable);
+    }
+    
+    function test_Vesting_MultipleSchedulesPayTheirOwnToken() public {
+        uint256 rewardSchedule = _vest(address(rewardToken), 1000e18, false);
+        uint256 stakeSchedule = _vest(address(stakeToken), 500e18, false);
+        
+        uint256[] memory ids = harvest.getBeneficiarySchedules(staker);
+        assertEq(ids.length, 2);
+        
+        vm.expectRevert(bytes("No tokens to claim"));
+        vm.prank(staker);
+        harvest.claimVestedTokens(rewardSchedule);
+        
+        vm.warp(block.timestamp + 40 days);
+        assertEq(harvest.getClaimableVestedAmount(rewardSchedule), 400e18);
+        
+        vm.startPrank(staker);
+        harvest.claimVestedTokens(rewardSchedule);
+        harvest.claimVestedTokens(stakeSchedule);
+        vm.stopPrank();
+        
+        assertEq(rewardToken.balanceOf(staker), 400e18);
+        assertEq(stakeToken.balanceOf(staker), 10_000e18 + 200e18);
+        
+        vm.warp(block.timestamp + 60 days);
+        vm.prank(staker);
+        harvest.claimVestedTokens(rewardSchedule);
+        assertEq(rewardToken.balanceOf(staker), 1000e18);
+    }
+    
+    function test_Vesting_RevokeReturnsUnvestedTokens() public {
+        uint256 scheduleId = _vest(address(rewardToken), 1000e18, true);
+        uint256 ownerBalance = rewardToken.balanceOf(address(this));
+        
+        vm.warp(block.timestamp + 50 days);
+        harvest.revokeVestingSchedule(scheduleId);
+        assertEq(rewardToken.balanceOf(address(this)), ownerBalance + 500e18);
+        
+        // What had vested stays claimable, nothing vests after revocation
+        vm.warp(block.timestamp + 50 days);
+        vm.prank(staker);
+        harvest.claimVestedTokens(scheduleId);
+        assertEq(rewardToken.balanceOf(staker), 500e18);
+        
+        vm.expectRevert(bytes("Already revoked"));
+        harvest.revokeVestingSchedule(scheduleId);
+    }
+    
+    function test_RevertWhen_RevokingIrrevocableSchedule() public {
+        uint256 scheduleId = _vest(address(rewardToken), 1000e18, false);
+        
+        vm.expectRevert(bytes("Not revocable"));
+        harvest.revokeVestingSchedule(scheduleId);
+    }
+    
+    function test_Vesting_TransferBeneficiary() public {
+        uint256 scheduleId = _vest(address(rewardToken), 1000e18, false);
+        
+        vm.prank(staker);
+        harvest.transferVestingSchedule(scheduleId, staker2);
+        
+        assertEq(harvest.getBeneficiarySchedules(staker).length, 0);
+        assertEq(harvest.getBeneficiarySchedules(staker2)[0], scheduleId);
+        
+        vm.warp(block.timestamp + 100 days);
+        vm.expectRevert(bytes("Not beneficiary"));
+        vm.prank(staker);
+        harvest.claimVestedTokens(scheduleId);
+        
+        vm.prank(staker2);
+        harvest.claimVestedTokens(scheduleId);
+        assertEq(rewardToken.balanceOf(staker2), 1000e18);
+    }
+    
+    // ============ GOVERNANCE ============
+    
+    function _setUpGovernance(uint256 quorumBps) internal returns (TimelockController timelock) {
+        address[] memory governor = new address[](1);
+        governor[0] = address(harvest);
+        timelock = new TimelockController(2 days, governor, governor, address(0));
+        
+        harvest.setGovernance(address(timelock), quorumBps);
+    }
+    
+    function _proposeAPR(address proposer, uint256 newAPR) internal returns (uint256) {
+        address[] memory targets = new address[](1);
+        targets[0] = address(harvest);
+        uint256[] memory values = new uint256[](1);
+        bytes[] memory calldatas = new bytes[](1);
+        calldatas[0] = abi.encodeCall(YieldHarvest.updatePoolAPR, (address(stakeToken), newAPR));
+        
+        vm.prank(proposer);
+        return harvest.createProposal(targets, values, calldatas, "Raise APR", 3 days);
+    }
+    
+    function _apr() internal view returns (uint256) {
+        return harvest.calculateProjectedAPR(address(stakeToken), YieldHarvest.StakeType.FLEXIBLE, YieldHarvest.BoostCardTier.NONE);
+    }
+    
+    function test_Governance_ExecutesPassedProposalThroughTimelock() public {
+        _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        _stake(staker2, 500e18, YieldHarvest.StakeType.LOCKED);
+        TimelockController timelock = _setUpGovernance(400);
+        vm.warp(block.timestamp + 1);
+        
+        // Ownership moved to the timelock, the deployer no longer controls the pools
+        assertEq(harvest.owner(), address(timelock));
+        vm.expectRevert();
+        harvest.updatePoolAPR(address(stakeToken), 2000);
+        vm.expectRevert();
+        harvest.setGovernance(address(this), 0);
+        
+        uint256 proposalId = _proposeAPR(staker, 2000);
+        vm.prank(staker);
+        harvest.vote(proposalId, true);
+        vm.prank(staker2);
+        harvest.vote(proposalId, false);
+        
+        vm.expectRevert(bytes("Proposal not successful"));
+        harvest.queueProposal(proposalId);
+        
+        vm.warp(block.timestamp + 3 days + 1);
+        harvest.queueProposal(proposalId);
+        assertEq(uint256(harvest.getProposalState(proposalId)), uint256(YieldHarvest.ProposalState.Queued));
+        
+        vm.expectRevert();
+        harvest.executeProposal(proposalId);
+        
+        vm.warp(block.timestamp + timelock.getMinDelay());
+        harvest.executeProposal(proposalId);
+        
+        assertEq(_apr(), 2000);
+        assertEq(uint256(harvest.getProposalState(proposalId)), uint256(YieldHarvest.ProposalState.Executed));
+    }
+    
+    function test_Governance_DefeatedWithoutMajorityOrQuorum() public {
+        _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        _stake(staker2, 1500e18, YieldHarvest.StakeType.LOCKED);
+        _setUpGovernance(5000);
+        vm.warp(block.timestamp + 1);
+        
+        // Outvoted
+        uint256 outvoted = _proposeAPR(staker, 2000);
+        vm.prank(staker);
+        harvest.vote(outvoted, true);
+        vm.prank(staker2);
+        harvest.vote(outvoted, false);
+        
+        // 40% of the voting power cast, below the 50% quorum
+        uint256 noQuorum = _proposeAPR(staker, 3000);
+        vm.prank(staker);
+        harvest.vote(noQuorum, true);
+        
+        vm.warp(block.timestamp + 3 days + 1);
+        assertEq(uint256(harvest.getProposalState(outvoted)), uint256(YieldHarvest.ProposalState.Defeated));
+        assertEq(uint256(harvest.getProposalState(noQuorum)), uint256(YieldHarvest.ProposalState.Defeated));
+        
+        vm.expectRevert(bytes("Proposal not successful"));
+        harvest.queueProposal(noQuorum);
+    }
+    
+    function test_Governance_CreatorCancelsQueuedProposal() public {
+        _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        TimelockController timelock = _setUpGovernance(400);
+        vm.warp(block.timestamp + 1);
+        
+        uint256 proposalId = _proposeAPR(staker, 2000);
+        vm.prank(staker);
+        harvest.vote(proposalId, true);
+        vm.warp(block.timestamp + 3 days + 1);
+        harvest.queueProposal(proposalId);
+        
+        vm.expectRevert(bytes("Not proposal creator"));
+        harvest.cancelProposal(proposalId);
+        
+        vm.prank(staker);
+        harvest.cancelProposal(proposalId);
+        
+        vm.warp(block.timestamp + timelock.getMinDelay());
+        vm.expectRevert(bytes("Proposal not queued"));
+        harvest.executeProposal(proposalId);
+        assertEq(_apr(), 1000);
+    }
+    
+    // ============ VOTING SNAPSHOTS ============
+    
+    function _forVotes(uint256 proposalId) internal view returns (uint256 forVotes) {
+        (,,,,, forVotes,,,,,,) = harvest.governanceProposals(proposalId);
+    }
+    
+    function test_Vote_ReadsPowerAtProposalSnapshot() public {
+        address staker3 = makeAddr("staker3");
+        uint256 stakeId = _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        _setUpGovernance(400);
+        vm.warp(block.timestamp + 1);
+        
+        uint256 proposalId = _proposeAPR(staker, 2000);
+        vm.prank(staker);
+        harvest.vote(proposalId, true);
+        
+        // Move the same tokens to a fresh address and stake them again
+        vm.startPrank(staker);
+        harvest.unstake(stakeId);
+        stakeToken.transfer(staker3, 1000e18);
+        vm.stopPrank();
+        
+        vm.startPrank(staker3);
+        stakeToken.approve(address(harvest), type(uint256).max);
+        harvest.createStake(address(stakeToken), 1000e18, YieldHarvest.StakeType.LOCKED, address(0));
+        vm.stopPrank();
+        
+        vm.warp(block.timestamp + 1);
+        assertGt(harvest.getVotes(staker3), 0);
+        
+        vm.expectRevert(bytes("No voting power"));
+        vm.prank(staker3);
+        harvest.vote(proposalId, true);
+        
+        assertEq(_forVotes(proposalId), _veVotes(1000e18, 1 + 7 days, 1));
+    }
+    
+    function test_Delegate_MovesStakedVotingPower() public {
+        _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        _stake(staker2, 500e18, YieldHarvest.StakeType.LOCKED);
+        uint256 delegatedAt = block.timestamp;
+        vm.warp(block.timestamp + 1);
+        
+        vm.prank(staker);
+        harvest.delegate(staker2);
+        assertEq(harvest.delegates(staker), staker2);
+        
+        // Later stake changes follow the delegation
+        _stake(staker, 500e18, YieldHarvest.StakeType.LOCKED);
+        vm.warp(block.timestamp + 1);
+        
+        assertEq(harvest.getVotes(staker), 0);
+        assertEq(harvest.getVotes(staker2), harvest.userVotingPower(staker) + harvest.userVotingPower(staker2));
+        assertEq(harvest.getPastVotes(staker, delegatedAt), _veVotes(1000e18, delegatedAt + 7 days, delegatedAt));
+        assertEq(
+            harvest.getPastTotalVotingPower(delegatedAt),
+            _veVotes(1000e18, delegatedAt + 7 days, delegatedAt) + _veVotes(500e18, delegatedAt + 7 days, delegatedAt)
+        );
+        
+        _setUpGovernance(400);
+        vm.warp(block.timestamp + 1);
+        uint256 proposalId = _proposeAPR(staker2, 2000);
+        
+        vm.expectRevert(bytes("No voting power"));
+        vm.prank(staker);
+        harvest.vote(proposalId, true);
+        
+        vm.prank(staker2);
+        harvest.vote(proposalId, true);
+        assertEq(_forVotes(proposalId), harvest.getPastVotes(staker2, block.timestamp - 1));
+        assertEq(_forVotes(proposalId), harvest.getPastTotalVotingPower(block.timestamp - 1));
+    }
+    
+    function test_Delegate_OnlyWalksOpenLocks() public {
+        uint256 closedId = _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        uint256 expiredId = _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        
+        vm.warp(block.timestamp + 8 days);
+        vm.prank(staker);
+        harvest.unstake(closedId);
+        uint256 openId = _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        _stake(staker, 1000e18, YieldHarvest.StakeType.FLEXIBLE);
+        
+        uint256[] memory locks = harvest.getOpenLocks(staker);
+        assertEq(locks.length, 2);
+        assertEq(locks[0], expiredId);
+        assertEq(locks[1], openId);
+        
+        // Delegation moves the open lock and drops the expired one
+        vm.prank(staker);
+        harvest.delegate(staker2);
+        
+        locks = harvest.getOpenLocks(staker);
+        assertEq(locks.length, 1);
+        assertEq(locks[0], openId);
+        assertEq(harvest.getVotes(staker), 0);
+        assertEq(harvest.getVotes(staker2), _veVotes(1000e18, block.timestamp + 7 days, block.timestamp));
+        assertEq(harvest.getVotes(staker2), harvest.userVotingPower(staker));
+    }
+    
+    // ============ VOTE ESCROW ============
+    
+    /// @dev Expected voting power of a lock, mirroring the contract's week-rounded linear decay
+    function _veVotes(uint256 amount, uint256 lockEndTime, uint256 timestamp) internal view returns (uint256) {
+        uint256 end = (lockEndTime / harvest.WEEK()) * harvest.WEEK();
+        if (end <= timestamp) return 0;
+        return ((amount * harvest.VE_PRECISION()) / harvest.MAX_LOCK_PERIOD()) * (end - timestamp) / harvest.VE_PRECISION();
+    }
+    
+    function test_VotingPower_DecaysWithRemainingLock() public {
+        uint256 stakedAt = block.timestamp;
+        _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        _stake(staker2, 1000e18, YieldHarvest.StakeType.FLEXIBLE);
+        
+        // Flexible stakes carry no voting power
+        assertEq(harvest.userVotingPower(staker2), 0);
+        assertEq(harvest.userVotingPower(staker), _veVotes(1000e18, stakedAt + 7 days, stakedAt));
+        assertEq(harvest.totalVotingPower(), harvest.userVotingPower(staker));
+        
+        vm.warp(block.timestamp + 3 days);
+        uint256 decayed = _veVotes(1000e18, stakedAt + 7 days, block.timestamp);
+        assertEq(harvest.getVotes(staker), decayed);
+        assertEq(harvest.getPastVotes(staker, stakedAt), _veVotes(1000e18, stakedAt + 7 days, stakedAt));
+        assertLt(decayed, harvest.getPastVotes(staker, stakedAt));
+        
+        // Nothing left once the lock's week has passed
+        vm.warp(stakedAt + 7 days);
+        assertEq(harvest.getVotes(staker), 0);
+        assertEq(harvest.totalVotingPower(), 0);
+    }
+    
+    function test_ExtendLock_RaisesVotingPowerAndBoost() public {
+        harvest.notifyRewardAmount(address(stakeToken), 1000e18);
+        uint256 stakeId = _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        
+        uint256 newLockEnd = block.timestamp + 365 days;
+        vm.prank(staker);
+        harvest.extendLock(stakeId, newLockEnd);
+        assertEq(harvest.getVotes(staker), _veVotes(1000e18, newLockEnd, block.timestamp));
+        
+        // 1.5x plus a 49.86% ve boost for an almost full year of lock
+        vm.warp(block.timestamp + 73 days);
+        assertEq(harvest.calculatePendingRewards(stakeId), 39.972e18);
+        
+        // Harvesting re-reads the decayed boost, now 39.86%
+        vm.prank(staker);
+        harvest.harvest(stakeId, false);
+        assertEq(rewardToken.balanceOf(staker), 39.972e18);
+        
+        vm.warp(block.timestamp + 73 days);
+        assertEq(harvest.calculatePendingRewards(stakeId), 37.972e18);
+    }
+    
+    function test_Kick_ReweightsIdleLockAtDecayedBoost() public {
+        harvest.notifyRewardAmount(address(stakeToken), 1000e18);
+        uint256 stakeId = _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        vm.prank(staker);
+        harvest.extendLock(stakeId, block.timestamp + 365 days);
+        
+        // The owner stays idle, anyone re-weights the stake at its decayed 39.86% boost
+        vm.warp(block.timestamp + 73 days);
+        vm.prank(staker2);
+        harvest.kick(stakeId);
+        
+        (,,,,,,,,,,,,,,, uint256 totalWeightedStake,) = harvest.pools(address(stakeToken));
+        assertEq(totalWeightedStake, 1898.6e18);
+        assertEq(rewardToken.balanceOf(staker), 39.972e18);
+        assertEq(rewardToken.balanceOf(staker2), 0);
+        
+        vm.warp(block.timestamp + 73 days);
+        assertEq(harvest.calculatePendingRewards(stakeId), 37.972e18);
+    }
+    
+    function test_VotingPower_CountsSmallSixDecimalLocks() public {
+        MockERC20 usdc = new MockERC20("USD Coin", "USDC", 6);
+        harvest.configurePool(address(usdc), address(rewardToken), 1000, 365 days, 1e6, 1_000_000e6, 10_000_000e6, 0, 0);
+        usdc.mint(staker, 10e6);
+        
+        vm.startPrank(staker);
+        usdc.approve(address(harvest), type(uint256).max);
+        harvest.createStake(address(usdc), 10e6, YieldHarvest.StakeType.LOCKED, address(0));
+        vm.stopPrank();
+        
+        // 10 USDC over a year would decay at zero votes per second without scaling
+        uint256 votes = harvest.getVotes(staker);
+        assertGt(votes, 0);
+        assertEq(votes, _veVotes(10e6, block.timestamp + 365 days, block.timestamp));
+        assertEq(harvest.userVotingPower(staker), votes);
+    }
+    
+    function test_RevertWhen_ExtendingInvalidLock() public {
+        uint256 lockedId = _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        uint256 flexibleId = _stake(staker, 1000e18, YieldHarvest.StakeType.FLEXIBLE);
+        
+        vm.startPrank(staker);
+        vm.expectRevert(bytes("Not a locked stake"));
+        harvest.extendLock(flexibleId, block.timestamp + 30 days);
+        
+        vm.expectRevert(bytes("Lock not extended"));
+        harvest.extendLock(lockedId, block.timestamp + 1 days);
+        
+        vm.expectRevert(bytes("Lock too long"));
+        harvest.extendLock(lockedId, block.timestamp + 365 days + 1);
+        vm.stopPrank();
+    }
+    
+    function test_IncreaseStakeAmount_AddsToLock() public {
+        uint256 stakedAt = block.timestamp;
+        uint256 stakeId = _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        
+        vm.warp(block.timestamp + 1 days);
+        vm.prank(staker);
+        harvest.increaseStakeAmount(stakeId, 500e18);
+        
+        (,,, uint256 amount,, uint256 lockEndTime,,,) = harvest.getStakeDetails(stakeId);
+        assertEq(amount, 1500e18);
+        assertEq(lockEndTime, stakedAt + 7 days);
+        assertEq(harvest.totalValueLocked(), 1500e18);
+        assertEq(harvest.userPoolStakes(staker, address(stakeToken)), 1500e18);
+        assertEq(harvest.getVotes(staker), _veVotes(1500e18, lockEndTime, block.timestamp));
+        
+        vm.warp(lockEndTime);
+        vm.expectRevert(bytes("Lock expired"));
+        vm.prank(staker);
+        harvest.increaseStakeAmount(stakeId, 500e18);
+    }
+    
+    // ============ GAUGES ============
+    
+    function _rewardRate(address token) internal view returns (uint256 rate) {
+        (,,,,,,,,,,,,,,,, rate) = harvest.pools(token);
+    }
+    
+    function _voteGauge(address voter, address token) internal {
+        address[] memory tokens = new address[](1);
+        tokens[0] = token;
+        uint256[] memory weights = new uint256[](1);
+        weights[0] = 10000;
+        
+        vm.prank(voter);
+        harvest.voteForGauges(tokens, weights);
+    }
+    
+    /// @dev Second pool staked by staker2, both stakers locked for a year
+    function _setUpGauges() internal returns (MockERC20 otherToken, uint256 stakeId, uint256 otherStakeId) {
+        otherToken = new MockERC20("Other Token", "OTH", 18);
+        harvest.configurePool(address(otherToken), address(rewardToken), 1000, 7 days, 1e18, 1_000_000e18, 10_000_000e18, 0, 0);
+        harvest.notifyRewardAmount(address(stakeToken), 1000e18);
+        harvest.notifyRewardAmount(address(otherToken), 1000e18);
+        harvest.setEmissionBudget(address(rewardToken), 70e18);
+        
+        otherToken.mint(staker2, 1000e18);
+        vm.prank(staker2);
+        otherToken.approve(address(harvest), type(uint256).max);
+        
+        stakeId = _stake(staker, 1000e18, YieldHarvest.StakeType.LOCKED);
+        vm.startPrank(staker2);
+        otherStakeId = harvest.createStake(address(otherToken), 500e18, YieldHarvest.StakeType.LOCKED, address(0));
+        harvest.extendLock(otherStakeId, block.timestamp + 365 days);
+        vm.stopPrank();
+        vm.prank(staker);
+        harvest.extendLock(stakeId, block.timestamp + 365 days);
+    }
+    
+    function test_Gauges_SplitBudgetByVotes() public {
+        (MockERC20 otherToken, uint256 stakeId,) = _setUpGauges();
+        uint256 epochDuration = harvest.EPOCH_DURATION();
+        
+        // Epoch 1 votes with the power held when it started
+        vm.warp(epochDuration);
+        assertEq(harvest.currentEpoch(), 1);
+        _voteGauge(staker, address(stakeToken));
+        _voteGauge(staker2, address(otherToken));
+        
+        uint256 stakerVotes = harvest.getPastVotes(staker, epochDuration - 1);
+        uint256 staker2Votes = harvest.getPastVotes(staker2, epochDuration - 1);
+        (address[] memory tokens, uint256[] memory votes, uint256[] memory allocations) = harvest.getEpochAllocations(1);
+        assertEq(tokens.length, 2);
+        assertEq(tokens[0], address(stakeToken));
+        assertEq(votes[0], stakerVotes);
+        assertEq(votes[1], staker2Votes);
+        assertEq(allocations[0], 0);
+        
+        vm.warp(2 * epochDuration);
+        harvest.finalizeEpoch(1);
+        assertFalse(harvest.gaugeEmissionsActive());
+        harvest.setGaugeEmissionsActive(true);
+        
+        (,, allocations) = harvest.getEpochAllocations(1);
+        uint256 totalVotes = stakerVotes + staker2Votes;
+        assertEq(allocations[0], (70e18 * stakerVotes) / totalVotes);
+        assertEq(allocations[1], (70e18 * staker2Votes) / totalVotes);
+        assertEq(_rewardRate(address(stakeToken)), allocations[0] / epochDuration);
+        assertEq(_rewardRate(address(otherToken)), allocations[1] / epochDuration);
+        
+        // The pool now emits at its gauge rate instead of its APR
+        uint256 pendingBefore = harvest.calculatePendingRewards(stakeId);
+        vm.warp(block.timestamp + 1 days);
+        assertApproxEqAbs(
+            harvest.calculatePendingRewards(stakeId) - pendingBefore,
+            _rewardRate(address(stakeToken)) * 1 days,
+            1e6
+        );
+        
+        vm.expectRevert(bytes("Emissions set by gauges"));
+        harvest.updatePoolAPR(address(stakeToken), 2000);
+        
+        // An epoch without votes keeps the rates, history stays readable
+        harvest.setEmissionBudget(address(rewardToken), 140e18);
+        vm.warp(3 * epochDuration);
+        harvest.finalizeEpoch(2);
+        
+        (uint256 epochVotes, bool finalized) = harvest.getGaugeEpoch(2);
+        assertEq(epochVotes, 0);
+        assertTrue(finalized);
+        assertEq(harvest.getEpochBudget(2, address(rewardToken)), 140e18);
+        assertEq(harvest.getEpochBudget(1, address(rewardToken)), 70e18);
+        assertEq(_rewardRate(address(stakeToken)), allocations[0] / epochDuration);
+        
+        // The owner hands emissions back to the APRs
+        harvest.setGaugeEmissionsActive(false);
+        harvest.updatePoolAPR(address(stakeToken), 2000);
+        pendingBefore = harvest.calculatePendingRewards(stakeId);
+        vm.warp(block.timestamp + 1 days);
+        assertLt(
+            harvest.calculatePendingRewards(stakeId) - pendingBefore,
+            _rewardRate(address(stakeToken)) * 1 days
+        );
+    }
+    
+    function test_Gauges_BudgetPerRewardTokenAndLateFinalization() public {
+        (MockERC20 otherToken,,) = _setUpGauges();
+        uint256 epochDuration = harvest.EPOCH_DURATION();
+        
+        // A third pool paying another reward token has its own budget
+        MockERC20 thirdToken = new MockERC20("Third Token", "TRD", 18);
+        MockERC20 bonusToken = new MockERC20("Bonus Token", "BNS", 18);
+        harvest.configurePool(address(thirdToken), address(bonusToken), 1000, 7 days, 1e18, 1_000_000e18, 10_000_000e18, 0, 0);
+        harvest.setEmissionBudget(address(bonusToken), 7e18);
+        
+        // Epoch 1: staker2 splits its votes between the two reward tokens
+        vm.warp(epochDuration);
+        _voteGauge(staker, address(stakeToken));
+        address[] memory tokens = new address[](2);
+        tokens[0] = address(otherToken);
+        tokens[1] = address(thirdToken);
+        uint256[] memory weights = new uint256[](2);
+        weights[0] = 5000;
+        weights[1] = 5000;
+        vm.prank(staker2);
+        harvest.voteForGauges(tokens, weights);
+        
+        // Epoch 2: staker moves to the other pool
+        vm.warp(2 * epochDuration);
+        _voteGauge(staker, address(otherToken));
+        
+        // Both epochs are finalized late and out of order
+        vm.warp(4 * epochDuration);
+        harvest.finalizeEpoch(2);
+        harvest.finalizeEpoch(1);
+        
+        (,, uint256[] memory allocations) = harvest.getEpochAllocations(1);
+        assertApproxEqAbs(allocations[0] + allocations[1], 70e18, 1);
+        assertEq(allocations[2], 7e18);
+        assertEq(harvest.getEpochBudget(1, address(bonusToken)), 7e18);
+        
+        // Rates follow epoch 2, the bonus gauge got no votes then and keeps its rate
+        assertEq(harvest.ratesEpoch(), 2);
+        assertEq(_rewardRate(address(stakeToken)), 0);
+        assertEq(_rewardRate(address(otherToken)), uint256(70e18) / epochDuration);
+        assertEq(_rewardRate(address(thirdToken)), 0);
+    }
+    
+    function test_RevertWhen_GaugeVoteOrFinalizationInvalid() public {
+        (MockERC20 otherToken,,) = _setUpGauges();
+        address outsider = makeAddr("outsider");
+        vm.warp(harvest.EPOCH_DURATION());
+        
+        address[] memory tokens = new address[](2);
+        tokens[0] = address(stakeToken);
+        tokens[1] = address(otherToken);
+        uint256[] memory weights = new uint256[](2);
+        weights[0] = 6000;
+        weights[1] = 5000;
+        
+        vm.expectRevert(bytes("Weights exceed 100%"));
+        vm.prank(staker);
+        harvest.voteForGauges(tokens, weights);
+        
+        vm.expectRevert(bytes("Not a gauge"));
+        _voteGauge(staker, address(0xBEEF));
+        
+        vm.expectRevert(bytes("No voting power"));
+        _voteGauge(outsider, address(stakeToken));
+        
+        _voteGauge(staker, address(stakeToken));
+        assertTrue(harvest.hasVotedGauges(1, staker));
+        vm.expectRevert(bytes("Already voted"));
+        _voteGauge(staker, address(otherToken));
+        
+        vm.expectRevert(bytes("Epoch not finalizable"));
+        harvest.finalizeEpoch(1);
+        
+        vm.warp(2 * harvest.EPOCH_DURATION());
+        harvest.finalizeEpoch(1);
+        vm.expectRevert(bytes("Epoch already finalized"));
+        harvest.finalizeEpoch(1);
+    }
+}
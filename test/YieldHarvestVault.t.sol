@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.20;
+
+import {Test} from "forge-std/Test.sol";
+import {YieldHarvest} from "../src/YieldHarvest.sol";
+import {YieldHarvestVault} from "../src/vault/YieldHarvestVault.sol";
+import {MockERC20} from "./mocks/MockERC20.sol";
+
+contract YieldHarvestVaultTest is Test {
+    YieldHarvest public harvest;
+    YieldHarvestVault public vault;
+    MockERC20 public token;
+    
+    address public alice = makeAddr("alice");
+    address public bob = makeAddr("bob");
+    address public keeper = makeAddr("keeper");
+    
+    function setUp() public {
+        harvest = new YieldHarvest();
+        token = new MockERC20("Stake Token", "STK", 18);
+        
+        // 10% APR, rewards paid in the staked token
+        harvest.configurePool(address(token), address(token), 1000, 7 days, 1e18, 1_000_000e18, 10_000_000e18, 0, 0);
+        token.mint(address(this), 1000e18);
+        token.approve(address(harvest), type(uint256).max);
+        harvest.notifyRewardAmount(address(token), 1000e18);
+        
+        // 1% compound bounty
+        vault = new YieldHarvestVault(harvest, token, "Auto-compounding STK", "acSTK", 100);
+        
+        token.mint(alice, 10_000e18);
+        token.mint(bob, 10_000e18);
+        vm.prank(alice);
+        token.approve(address(vault), type(uint256).max);
+        vm.prank(bob);
+        token.approve(address(vault), type(uint256).max);
+    }
+    
+    function _deposit(address user, uint256 assets) internal returns (uint256) {
+        vm.prank(user);
+        return vault.deposit(assets, user);
+    }
+    
+    function _stakedAmount() internal view returns (uint256 amount) {
+        (,,, amount,,,,,) = harvest.getStakeDetails(vault.stakeId());
+    }
+    
+    function test_Deposit_PoolsIntoOneFlexibleStake() public {
+        assertEq(_deposit(alice, 1000e18), 1000e18);
+        _deposit(bob, 500e18);
+        
+        uint256[] memory positions = harvest.getUserActiveStakes(address(vault));
+        assertEq(positions.length, 1);
+        assertEq(positions[0], vault.stakeId());
+        
+        (,, YieldHarvest.StakeType stakeType,,,,,,) = harvest.getStakeDetails(vault.stakeId());
+        assertEq(uint256(stakeType), uint256(YieldHarvest.StakeType.FLEXIBLE));
+        assertEq(_stakedAmount(), 1500e18);
+        assertEq(vault.totalAssets(), 1500e18);
+        assertEq(vault.balanceOf(bob), 500e18);
+    }
+    
+    function test_Compound_PaysBountyAndRestakes() public {
+        _deposit(alice, 1000e18);
+        
+        // 10% of 1000 over a fifth of a year, counted net of the bounty
+        vm.warp(block.timestamp + 73 days);
+        assertEq(vault.totalAssets(), 1019.8e18);
+        
+        vm.prank(keeper);
+        uint256 bounty = vault.compound();
+        
+        assertEq(bounty, 0.2e18);
+        assertEq(token.balanceOf(keeper), 0.2e18);
+        assertEq(_stakedAmount(), 1019.8e18);
+        assertEq(vault.totalAssets(), 1019.8e18);
+        assertEq(token.balanceOf(address(vault)), 0);
+        
+        vm.expectRevert(bytes("Nothing to compound"));
+        vault.compound();
+    }
+    
+    function test_Redeem_ReturnsCompoundedAssetsAndRestakesRest() public {
+        _deposit(alice, 1000e18);
+        _deposit(bob, 1000e18);
+        
+        vm.warp(block.timestamp + 73 days);
+        vault.compound();
+        
+        uint256 positionId = vault.stakeId();
+        uint256 shares = vault.balanceOf(alice);
+        vm.prank(alice);
+        uint256 assets = vault.redeem(shares, alice, alice);
+        
+        assertApproxEqAbs(assets, 1019.8e18, 1);
+        assertEq(token.balanceOf(alice), 9000e18 + assets);
+        
+        // Bob's share stays staked in the same position
+        assertTrue(vault.hasPosition());
+        assertEq(vault.stakeId(), positionId);
+        assertApproxEqAbs(_stakedAmount(), 1019.8e18, 1);
+        assertApproxEqAbs(vault.convertToAssets(vault.balanceOf(bob)), 1019.8e18, 1);
+    }
+    
+    function test_Withdraw_PartiallyUnstakesAndKeepsBountyForCompounder() public {
+        _deposit(alice, 1000e18);
+        _deposit(bob, 1000e18);
+        uint256 positionId = vault.stakeId();
+        
+        // 40 of rewards pending, worth 39.6 to depositors after the bounty
+        vm.warp(block.timestamp + 73 days);
+        assertEq(vault.totalAssets(), 2039.6e18);
+        
+        vm.prank(alice);
+        vault.withdraw(100e18, alice, alice);
+        
+        // The rewards paid out with the withdrawal are restaked, their bounty stays aside
+        assertEq(vault.stakeId(), positionId);
+        assertEq(_stakedAmount(), 1939.6e18);
+        assertEq(vault.accruedBounty(), 0.4e18);
+        assertEq(vault.totalAssets(), 1939.6e18);
+        assertEq(token.balanceOf(alice), 9100e18);
+    }
+    
+    function test_Deposit_KeepsSharePriceWhenRewardsArePaidOut() public {
+        _deposit(alice, 1000e18);
+        
+        vm.warp(block.timestamp + 73 days);
+        uint256 priceBefore = vault.convertToAssets(1e18);
+        
+        // Topping up the position pays out its rewards to the vault
+        _deposit(bob, 1000e18);
+        assertApproxEqAbs(vault.convertToAssets(1e18), priceBefore, 1);
+        assertEq(vault.totalAssets(), 2019.8e18);
+        assertEq(vault.accruedBounty(), 0.2e18);
+        
+        // The next compounder collects the bounty set aside
+        vm.warp(block.timestamp + 73 days);
+        vm.prank(keeper);
+        uint256 bounty = vault.compound();
+        
+        // 10% of the 2000 staked over a fifth of a year, 1% of it on top of the 0.2 set aside
+        assertEq(bounty, 0.6e18);
+        assertEq(vault.accruedBounty(), 0);
+        assertEq(token.balanceOf(address(vault)), 0);
+    }
+    
+    function test_Compound_LeavesRewardsIdleOncePoolIsFull() public {
+        harvest.configurePool(address(token), address(token), 1000, 7 days, 1e18, 1_000_000e18, 1000e18, 0, 0);
+        _deposit(alice, 1000e18);
+        assertEq(vault.maxDeposit(alice), 0);
+        
+        // 11% at full utilization over a fifth of a year
+        vm.warp(block.timestamp + 73 days);
+        vm.prank(keeper);
+        uint256 bounty = vault.compound();
+        
+        assertEq(bounty, 0.22e18);
+        assertEq(_stakedAmount(), 1000e18);
+        assertEq(token.balanceOf(address(vault)), 21.78e18);
+        assertEq(vault.totalAssets(), 1021.78e18);
+        
+        // Withdrawals are served from the idle rewards and nothing is restaked
+        vm.prank(alice);
+        vault.withdraw(10e18, alice, alice);
+        assertEq(_stakedAmount(), 1000e18);
+        assertEq(token.balanceOf(address(vault)), 11.78e18);
+    }
+    
+    function test_RevertWhen_VaultPoolPaysAnotherToken() public {
+        MockERC20 other = new MockERC20("Other Token", "OTH", 18);
+        harvest.configurePool(address(other), address(token), 1000, 7 days, 1e18, 1_000_000e18, 10_000_000e18, 0, 0);
+        
+        vm.expectRevert(bytes("Reward token differs from asset"));
+        new YieldHarvestVault(harvest, other, "Auto-compounding OTH", "acOTH", 100);
+    }
+}
@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {AggregatorV3Interface} from "../../src/oracles/AggregatorV3Interface.sol";
+
+contract MockAggregatorV3 is AggregatorV3Interface {
+    uint8 public immutable override decimals;
+
+    uint80 public roundId;
+    int256 public answer;
+    uint256 public updatedAt;
+
+    constructor(uint8 decimals_, int256 answer_) {
+        decimals = decimals_;
+        setAnswer(answer_);
+    }
+
+    function setAnswer(int256 answer_) public {
+        roundId++;
+        answer = answer_;
+        updatedAt = block.timestamp;
+    }
+
+    function setUpdatedAt(uint256 updatedAt_) external {
+        updatedAt = updatedAt_;
+    }
+
+    function latestRoundData() external view override returns (uint80, int256, uint256, uint256, uint80) {
+        return (roundId, answer, updatedAt, updatedAt, roundId);
+    }
+}
@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {MockERC20} from "./MockERC20.sol";
+
+/// @dev Burns a fixed share of every transfer, so recipients get less than the amount sent
+contract MockFeeOnTransferERC20 is MockERC20 {
+    uint256 public immutable feeBps;
+
+    constructor(string memory name_, string memory symbol_, uint8 decimals_, uint256 feeBps_)
+        MockERC20(name_, symbol_, decimals_)
+    {
+        feeBps = feeBps_;
+    }
+
+    function _update(address from, address to, uint256 value) internal override {
+        if (from == address(0) || to == address(0)) {
+            super._update(from, to, value);
+            return;
+        }
+        uint256 fee = (value * feeBps) / 10000;
+        super._update(from, address(0), fee);
+        super._update(from, to, value - fee);
+    }
+}
@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {IERC3156FlashBorrower} from "@openzeppelin/contracts/interfaces/IERC3156FlashBorrower.sol";
+import {IERC3156FlashLender} from "@openzeppelin/contracts/interfaces/IERC3156FlashLender.sol";
+import {IERC20} from "@openzeppelin/contracts/token/ERC20/IERC20.sol";
+
+/// @dev Flash borrower that can misbehave in the ways the lender must reject
+contract MockFlashBorrower is IERC3156FlashBorrower {
+    enum Mode {
+        Repay,
+        NoApproval,
+        WrongReturn,
+        Reenter
+    }
+
+    IERC3156FlashLender public immutable lender;
+    Mode public mode;
+
+    constructor(address lender_) {
+        lender = IERC3156FlashLender(lender_);
+    }
+
+    function setMode(Mode mode_) external {
+        mode = mode_;
+    }
+
+    function borrow(address token, uint256 amount) external {
+        lender.flashLoan(this, token, amount, "");
+    }
+
+    function onFlashLoan(address, address token, uint256 amount, uint256 fee, bytes calldata)
+        external
+        returns (bytes32)
+    {
+        if (mode == Mode.Reenter) {
+            lender.flashLoan(this, token, amount, "");
+        }
+        if (mode != Mode.NoApproval) {
+            IERC20(token).approve(address(lender), amount + fee);
+        }
+        if (mode == Mode.WrongReturn) {
+            return bytes32(0);
+        }
+        return keccak256("ERC3156FlashBorrower.onFlashLoan");
+    }
+}
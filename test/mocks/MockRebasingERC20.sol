@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+/// @dev Balances are internal shares scaled by a multiplier that the test can rebase up or down
+contract MockRebasingERC20 {
+    string public name = "Rebasing Token";
+    string public symbol = "REB";
+    uint8 public decimals = 18;
+    uint256 public multiplier = 1e18;
+    uint256 public totalShares;
+
+    mapping(address => uint256) public sharesOf;
+    mapping(address => mapping(address => uint256)) public allowance;
+
+    function totalSupply() external view returns (uint256) {
+        return (totalShares * multiplier) / 1e18;
+    }
+
+    function balanceOf(address account) public view returns (uint256) {
+        return (sharesOf[account] * multiplier) / 1e18;
+    }
+
+    function rebase(uint256 newMultiplier) external {
+        multiplier = newMultiplier;
+    }
+
+    function mint(address to, uint256 amount) external {
+        uint256 shares = (amount * 1e18) / multiplier;
+        sharesOf[to] += shares;
+        totalShares += shares;
+    }
+
+    function approve(address spender, uint256 amount) external returns (bool) {
+        allowance[msg.sender][spender] = amount;
+        return true;
+    }
+
+    function transfer(address to, uint256 amount) external returns (bool) {
+        _transfer(msg.sender, to, amount);
+        return true;
+    }
+
+    function transferFrom(address from, address to, uint256 amount) external returns (bool) {
+        if (allowance[from][msg.sender] != type(uint256).max) {
+            allowance[from][msg.sender] -= amount;
+        }
+        _transfer(from, to, amount);
+        return true;
+    }
+
+    function _transfer(address from, address to, uint256 amount) internal {
+        uint256 shares = (amount * 1e18) / multiplier;
+        sharesOf[from] -= shares;
+        sharesOf[to] += shares;
+    }
+}
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {IUniswapV2Pair} from "../../src/oracles/IUniswapV2Pair.sol";
+
+/// @dev Tracks cumulative prices the same way a real Uniswap V2 pair does on every sync.
+contract MockUniswapV2Pair is IUniswapV2Pair {
+    address public immutable override token0;
+    address public immutable override token1;
+
+    uint256 public override price0CumulativeLast;
+    uint256 public override price1CumulativeLast;
+
+    uint112 private reserve0;
+    uint112 private reserve1;
+    uint32 private blockTimestampLast;
+
+    constructor(address token0_, address token1_) {
+        token0 = token0_;
+        token1 = token1_;
+    }
+
+    function setReserves(uint112 reserve0_, uint112 reserve1_) external {
+        uint32 blockTimestamp = uint32(block.timestamp % 2 ** 32);
+        unchecked {
+            uint32 timeElapsed = blockTimestamp - blockTimestampLast;
+            if (timeElapsed > 0 && reserve0 != 0 && reserve1 != 0) {
+                price0CumulativeLast += ((uint256(reserve1) << 112) / reserve0) * timeElapsed;
+                price1CumulativeLast += ((uint256(reserve0) << 112) / reserve1) * timeElapsed;
+            }
+        }
+        reserve0 = reserve0_;
+        reserve1 = reserve1_;
+        blockTimestampLast = blockTimestamp;
+    }
+
+    function getReserves() external view override returns (uint112, uint112, uint32) {
+        return (reserve0, reserve1, blockTimestampLast);
+    }
+}
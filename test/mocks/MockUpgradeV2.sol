@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.22;
+
+import {ERC20TokenLoanUpgradeable} from "../../src/upgradeable/ERC20TokenLoanUpgradeable.sol";
+import {YieldHarvestUpgradeable} from "../../src/upgradeable/YieldHarvestUpgradeable.sol";
+
+/// @dev Version bump of the lending implementation with one appended variable and a migration step
+contract ERC20TokenLoanV2 is ERC20TokenLoanUpgradeable {
+    uint256 public migratedAt;
+    
+    function initializeV2() external reinitializer(2) {
+        migratedAt = block.timestamp;
+    }
+    
+    function version() external pure override returns (string memory) {
+        return "2";
+    }
+}
+
+/// @dev Version bump of the staking implementation with one appended variable and a migration step
+contract YieldHarvestV2 is YieldHarvestUpgradeable {
+    uint256 public migratedAt;
+    
+    function initializeV2() external reinitializer(2) {
+        migratedAt = block.timestamp;
+    }
+    
+    function version() external pure override returns (string memory) {
+        return "2";
+    }
+}
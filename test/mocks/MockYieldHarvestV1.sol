@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.22;
+
+import {ReentrancyGuard} from "@openzeppelin/contracts/utils/ReentrancyGuard.sol";
+import {Ownable} from "@openzeppelin/contracts/access/Ownable.sol";
+import {Initializable} from "@openzeppelin/contracts/proxy/utils/Initializable.sol";
+import {UUPSUpgradeable} from "@openzeppelin/contracts/proxy/utils/UUPSUpgradeable.sol";
+import {YieldHarvest} from "../../src/YieldHarvest.sol";
+
+/// @dev Storage layout of the first YieldHarvestUpgradeable release, seeded directly to test upgrades from it
+contract YieldHarvestV1 is ReentrancyGuard, Ownable, Initializable, UUPSUpgradeable {
+    uint256 public totalStakes;
+    uint256 public totalValueLocked;
+    uint256 public totalRewardsDistributed;
+    uint256 public governanceProposalCount;
+    
+    mapping(uint256 => YieldHarvest.StakePosition) public stakes;
+    mapping(address => uint256[]) public userStakes;
+    mapping(address => YieldHarvest.PoolConfig) public pools;
+    mapping(address => YieldHarvest.ReferralData) public referrals;
+    mapping(address => uint256) public vestingSchedules;
+    mapping(address => mapping(address => uint256)) public userPoolStakes;
+    mapping(address => uint256) public userTotalRewards;
+    mapping(address => bool) public whitelistedTokens;
+    mapping(address => YieldHarvest.BoostCardTier) public boostCards;
+    mapping(uint256 => YieldHarvest.GovernanceProposal) public governanceProposals;
+    mapping(address => uint256) public userVotingPower;
+    
+    uint256[50] private __gap;
+    
+    address public upgrader;
+    
+    uint256[49] private __proxyGap;
+    
+    /// @custom:oz-upgrades-unsafe-allow constructor
+    constructor() Ownable(msg.sender) {
+        _disableInitializers();
+    }
+    
+    function initialize(address initialOwner, address initialUpgrader) external initializer {
+        _transferOwnership(initialOwner);
+        upgrader = initialUpgrader;
+    }
+    
+    function seed(address user, address token, uint256 amount, string calldata description) external {
+        uint256 stakeId = totalStakes++;
+        YieldHarvest.StakePosition storage stake = stakes[stakeId];
+        stake.user = user;
+        stake.token = token;
+        stake.amount = amount;
+        stake.isActive = true;
+        userStakes[user].push(stakeId);
+        totalValueLocked += amount;
+        
+        userPoolStakes[user][token] = amount;
+        userTotalRewards[user] = amount / 10;
+        whitelistedTokens[token] = true;
+        referrals[user].referrer = owner();
+        
+        YieldHarvest.GovernanceProposal storage proposal = governanceProposals[governanceProposalCount];
+        proposal.proposalId = governanceProposalCount++;
+        proposal.creator = user;
+        proposal.description = description;
+        proposal.forVotes = amount;
+    }
+    
+    function _authorizeUpgrade(address) internal view override {
+        require(msg.sender == upgrader, "Not upgrader");
+    }
+}